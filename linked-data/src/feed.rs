@@ -2,9 +2,60 @@ use crate::IPLDLink;
 
 use serde::{Deserialize, Serialize};
 
+/// Maximum number of links in one feed page, older content moves to a previous page.
+pub const FEED_PAGE_SIZE: usize = 500;
+
 /// Content feed in chronological order.
 /// Direct pin.
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Feed {
     pub content: Vec<IPLDLink>,
+
+    /// Incremented on each update, the highest is the newest feed.
+    #[serde(default)]
+    pub sequence: u64,
+
+    /// Page of older content, None on the oldest page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<IPLDLink>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CID: &str = "bafyreibbmwcfimt5s6rzc3iw4a5cjvk5e4ywoyhshafb7v4pl7ltnr7fyu";
+
+    #[test]
+    fn feed_without_previous_is_single_page() {
+        let json = format!(r#"{{"content":[{{"/":"{}"}}],"sequence":3}}"#, CID);
+
+        let feed: Feed = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(feed.content.len(), 1);
+        assert_eq!(feed.sequence, 3);
+        assert!(feed.previous.is_none());
+    }
+
+    #[test]
+    fn single_page_serialized_without_previous() {
+        let json = format!(r#"{{"content":[{{"/":"{}"}}],"sequence":3}}"#, CID);
+
+        let feed: Feed = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(serde_json::to_string(&feed).unwrap(), json);
+    }
+
+    #[test]
+    fn previous_page_round_trip() {
+        let json = format!(
+            r#"{{"content":[],"sequence":1,"previous":{{"/":"{}"}}}}"#,
+            CID
+        );
+
+        let feed: Feed = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(feed.previous.unwrap().link.to_string(), CID);
+        assert_eq!(serde_json::to_string(&feed).unwrap(), json);
+    }
 }
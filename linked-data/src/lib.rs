@@ -46,7 +46,54 @@ where
 {
     let cid_str: &str = Deserialize::deserialize(deserializer)?;
 
-    let cid = Cid::try_from(cid_str).expect("Deserialize string to CID failed");
+    Cid::try_from(cid_str).map_err(serde::de::Error::custom)
+}
+
+/// Pretty print JSON in debug logs, shared by the web app and the CLI.
+pub const PRETTY_DEBUG_JSON: bool = true;
+
+/// Serialize for debug output. Never panics, returns a fallback message on failure.
+pub fn debug_json<T>(value: &T) -> String
+where
+    T: ?Sized + Serialize,
+{
+    format_json(value, PRETTY_DEBUG_JSON)
+}
+
+fn format_json<T>(value: &T, pretty: bool) -> String
+where
+    T: ?Sized + Serialize,
+{
+    let result = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
 
-    Ok(cid)
+    result.unwrap_or_else(|e| format!("<unserializable: {}>", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn debug_json_compact_and_pretty() {
+        let link = IPLDLink::default();
+
+        assert!(!format_json(&link, false).contains('\n'));
+        assert!(format_json(&link, true).contains('\n'));
+    }
+
+    #[test]
+    fn debug_json_never_panics() {
+        // JSON object keys must be strings.
+        let mut map = HashMap::new();
+        map.insert((1u8, 2u8), 3u8);
+
+        assert!(format_json(&map, false).starts_with("<unserializable"));
+        assert!(debug_json(&map).starts_with("<unserializable"));
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{Address, PeerId};
+use crate::{Address, IPLDLink, PeerId};
 
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -21,6 +21,15 @@ pub struct Moderator {
     pub moderator: Address,
 }
 
+/// Message to report content to moderators. Should always be crypto-signed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Report {
+    /// Link to the reported content.
+    pub content: IPLDLink,
+
+    pub reason: String,
+}
+
 /// List of banned users.
 /// Direct pin.
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -116,6 +125,22 @@ impl ChatModerationCache {
         self.verified.insert(peer.to_owned(), index);
     }
 
+    /// Ban every known peer using one of these addresses.
+    pub fn ban_addresses(&mut self, banned: &HashSet<Address>) {
+        let peers: Vec<PeerId> = self
+            .peers
+            .iter()
+            .zip(self.addresses.iter())
+            .skip(self.ban_index)
+            .filter(|(_, address)| banned.contains(*address))
+            .map(|(peer, _)| peer.clone())
+            .collect();
+
+        for peer in peers {
+            self.ban_peer(&peer);
+        }
+    }
+
     pub fn ban_peer(&mut self, peer: &str) {
         let i = match self.verified.get(peer) {
             Some(i) => *i,
@@ -5,6 +5,9 @@ use serde::{Deserialize, Serialize};
 use secp256k1::recover;
 use secp256k1::{Message, RecoveryId, Signature};
 
+#[cfg(any(feature = "sign", test))]
+pub use secp256k1::SecretKey;
+
 /// Generic crypto-signed message.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SignedMessage<T>
@@ -22,34 +25,69 @@ impl<T> SignedMessage<T>
 where
     T: Serialize,
 {
+    /// Sign data the same way Ethereum wallets sign messages.
+    #[cfg(any(feature = "sign", test))]
+    pub fn sign(data: T, secret_key: &SecretKey) -> Self {
+        let msg = Message::parse(&eth_message_hash(&data));
+
+        let (sig, rec_id) = secp256k1::sign(&msg, secret_key);
+
+        let mut signature = sig.serialize().to_vec();
+        signature.push(rec_id.serialize() + 27);
+
+        let public_key = secp256k1::PublicKey::from_secret_key(secret_key).serialize();
+
+        Self {
+            address: public_key_address(&public_key),
+            data,
+            signature,
+        }
+    }
+
+    /// Returns true if the message was signed by the claimed address.
     pub fn verify(&self) -> bool {
+        self.recover_address() == Some(self.address)
+    }
+
+    /// Returns the address that signed the message, which may differ from the claimed one.
+    /// None if the signature is malformed.
+    pub fn recover_address(&self) -> Option<Address> {
         if self.signature.len() != 65 {
-            return false;
+            return None;
         }
 
-        let message = serde_json::to_vec(&self.data).expect("Cannot Serialize");
+        let hash = eth_message_hash(&self.data);
 
-        let mut eth_message =
-            format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
-        eth_message.extend_from_slice(&message);
+        let msg = Message::parse_slice(&hash).ok()?;
+        let sig = Signature::parse_slice(&self.signature[0..64]).ok()?;
+        let rec_id = RecoveryId::parse_rpc(self.signature[64]).ok()?;
 
-        let hash = keccak256(&eth_message);
+        let public_key = recover(&msg, &sig, &rec_id).ok()?.serialize();
 
-        let msg = Message::parse_slice(&hash).expect("Invalid Message");
-        let sig = Signature::parse_slice(&self.signature[0..64]).expect("Invalid Signature");
-        let rec_id = RecoveryId::parse_rpc(self.signature[64]).expect("Invalid Recovery Id");
+        Some(public_key_address(&public_key))
+    }
+}
 
-        let public_key = match recover(&msg, &sig, &rec_id) {
-            Ok(data) => data.serialize(),
-            Err(_) => return false,
-        };
+/// Hash of data serialized as JSON, prefixed like Ethereum personal messages.
+fn eth_message_hash<T: Serialize>(data: &T) -> [u8; 32] {
+    let message = serde_json::to_vec(data).expect("Cannot Serialize");
 
-        // The public key returned is 65 bytes long, that is because it is prefixed by `0x04` to indicate an uncompressed public key.
-        let hash = keccak256(&public_key[1..]);
+    let mut eth_message = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    eth_message.extend_from_slice(&message);
 
-        // The public address is defined as the low 20 bytes of the keccak hash of the public key.
-        hash[12..] == self.address
-    }
+    keccak256(&eth_message)
+}
+
+/// Ethereum address of an uncompressed public key.
+fn public_key_address(public_key: &[u8; 65]) -> Address {
+    // The public key is 65 bytes long, that is because it is prefixed by `0x04` to indicate an uncompressed public key.
+    let hash = keccak256(&public_key[1..]);
+
+    // The public address is defined as the low 20 bytes of the keccak hash of the public key.
+    let mut address = Address::default();
+    address.copy_from_slice(&hash[12..]);
+
+    address
 }
 
 /// Compute the Keccak-256 hash of input bytes.
@@ -61,3 +99,46 @@ fn keccak256(bytes: &[u8]) -> [u8; 32] {
     hasher.finalize(&mut output);
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret_key() -> SecretKey {
+        SecretKey::parse(&[7u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn signed_message_verifies() {
+        let msg = SignedMessage::sign(42u64, &secret_key());
+
+        assert!(msg.verify());
+    }
+
+    #[test]
+    fn tampered_data_fails() {
+        let mut msg = SignedMessage::sign(42u64, &secret_key());
+        msg.data = 43;
+
+        assert!(!msg.verify());
+    }
+
+    #[test]
+    fn other_address_fails() {
+        let mut msg = SignedMessage::sign(42u64, &secret_key());
+        let signer = msg.address;
+        msg.address = [1u8; 20];
+
+        assert!(!msg.verify());
+        assert_eq!(msg.recover_address(), Some(signer));
+    }
+
+    #[test]
+    fn malformed_signature_fails() {
+        let mut msg = SignedMessage::sign(42u64, &secret_key());
+        msg.signature.pop();
+
+        assert!(!msg.verify());
+        assert_eq!(msg.recover_address(), None);
+    }
+}
@@ -1,6 +1,7 @@
-use crate::IPLDLink;
+use crate::signature::SignedMessage;
+use crate::{Address, IPLDLink};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
@@ -20,11 +21,41 @@ pub struct VideoMetadata {
     /// Link to Raw node of thumbnail image.
     pub image: IPLDLink,
 
+    /// Link to Raw node of a smaller thumbnail image, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<IPLDLink>,
+
     /// Link to TimecodeNode.
     pub video: IPLDLink,
 
     /// Timestamp at the time of publication in Unix time.
     pub timestamp: u64,
+
+    /// Link to Engagement node, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engagement: Option<IPLDLink>,
+
+    /// Requirement to watch this video, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access: Option<AccessControl>,
+
+    /// Captions in various languages.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subtitles: Vec<SubtitleTrack>,
+
+    /// Link to Raw node of a WebVTT file whose cues are preview images, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previews: Option<IPLDLink>,
+}
+
+/// Captions for one language.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct SubtitleTrack {
+    /// BCP 47 language code.
+    pub language: String,
+
+    /// Link to Raw node of WebVTT file.
+    pub vtt: IPLDLink,
 }
 
 impl VideoMetadata {
@@ -38,8 +69,21 @@ impl VideoMetadata {
             title,
             duration,
             image: image.into(),
+            thumbnail: None,
             video: video.into(),
             timestamp,
+            engagement: None,
+            access: None,
+            subtitles: Vec::new(),
+            previews: None,
+        }
+    }
+
+    /// Returns the image of this size, the full image is used if it is the only one.
+    pub fn image_link(&self, size: ImageSize) -> Cid {
+        match (size, self.thumbnail) {
+            (ImageSize::Thumbnail, Some(thumbnail)) => thumbnail.link,
+            _ => self.image.link,
         }
     }
 
@@ -47,6 +91,7 @@ impl VideoMetadata {
         &mut self,
         title: Option<String>,
         image: Option<Cid>,
+        thumbnail: Option<Cid>,
         video: Option<Cid>,
         duration: Option<f64>,
     ) {
@@ -58,6 +103,10 @@ impl VideoMetadata {
             self.image = img.into();
         }
 
+        if let Some(thumb) = thumbnail {
+            self.thumbnail = Some(thumb.into());
+        }
+
         if let Some(vid) = video {
             self.video = vid.into();
         }
@@ -73,12 +122,45 @@ impl VideoMetadata {
     }
 }
 
+/// Image sizes a video can be displayed with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageSize {
+    /// Small image for lists and grids.
+    Thumbnail,
+
+    /// Full size image for the player.
+    Full,
+}
+
+/// Requirement to watch a video.
+/// Only checked client-side, this is not DRM.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum AccessControl {
+    /// Hold a token from this contract.
+    Token(Address),
+
+    /// Be one of these addresses.
+    Allowlist(Vec<Address>),
+}
+
+/// View and like counts of a video.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Engagement {
+    pub views: u64,
+    pub likes: u64,
+}
+
 /// Root CID.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TimecodeNode {
     /// ../time/..
     #[serde(rename = "time")]
     pub timecode: IPLDLink,
+
+    /// ../index/..
+    /// Link to the ChunkedIndex of the whole video, absent for older videos.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<IPLDLink>,
 }
 
 /// Links all hour nodes for multiple hours of video.
@@ -129,8 +211,17 @@ pub struct VideoNode {
     pub setup: Option<IPLDLink>,
 
     /// ../time/hour/0/minute/36/second/12/video/previous/..
+    /// None only for the first node of a stream, marking the start of the chain.
     #[serde(rename = "previous")]
     pub previous: Option<IPLDLink>,
+
+    /// Media timestamps restart at this node.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub discontinuity: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 /// Contains initialization data for video stream.
@@ -141,7 +232,7 @@ pub struct SetupNode {
     pub tracks: Vec<Track>, // ../time/hour/0/minute/36/second/12/video/setup/track/0/..
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Track {
     pub name: String,  // ../time/hour/0/minute/36/second/12/video/setup/track/2/name
     pub codec: String, // ../time/hour/0/minute/36/second/12/video/setup/track/3/codec
@@ -151,3 +242,251 @@ pub struct Track {
 
     pub bandwidth: usize, // ../time/hour/0/minute/36/second/12/video/setup/track/4/bandwidth
 }
+
+impl Track {
+    /// Audio tracks are named "audio" or "audio_" followed by a language.
+    pub fn is_audio(&self) -> bool {
+        self.name == "audio" || self.name.starts_with("audio_")
+    }
+
+    /// Language of an audio track, if any.
+    pub fn language(&self) -> Option<&str> {
+        self.name.strip_prefix("audio_")
+    }
+}
+
+/// Maps stream timecodes in seconds to VideoNodes.
+/// Allow seeking without walking previous links.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SegmentIndex {
+    #[serde(rename = "segment")]
+    pub segments: BTreeMap<u64, IPLDLink>,
+}
+
+impl SegmentIndex {
+    /// Returns the timecode and CID of the segment at or before this timecode.
+    pub fn lookup(&self, timecode: u64) -> Option<(u64, Cid)> {
+        self.segments
+            .range(..=timecode)
+            .next_back()
+            .or_else(|| self.segments.iter().next())
+            .map(|(time, ipld)| (*time, ipld.link))
+    }
+
+    /// Returns the timecode and CID of the segment closest to this timecode.
+    pub fn nearest(&self, timecode: u64) -> Option<(u64, Cid)> {
+        let before = self.segments.range(..=timecode).next_back();
+        let after = self.segments.range(timecode..).next();
+
+        let (time, ipld) = match (before, after) {
+            (Some(before), Some(after)) => {
+                if timecode - before.0 <= after.0 - timecode {
+                    before
+                } else {
+                    after
+                }
+            }
+            (Some(before), None) => before,
+            (None, Some(after)) => after,
+            (None, None) => return None,
+        };
+
+        Some((*time, ipld.link))
+    }
+
+    /// Returns the timecode of this segment, if indexed.
+    pub fn timecode_of(&self, cid: &Cid) -> Option<u64> {
+        self.segments
+            .iter()
+            .find(|(_, ipld)| ipld.link == *cid)
+            .map(|(time, _)| *time)
+    }
+}
+
+/// Seconds of video indexed by each chunk of a ChunkedIndex.
+pub const INDEX_CHUNK_DURATION: u64 = 3600;
+
+/// SegmentIndex of a whole video split in chunks, no node grows with the duration.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ChunkedIndex {
+    /// ../index/chunk/2/segment/7250/..
+    /// Chunks that failed to be added are absent.
+    #[serde(rename = "chunk")]
+    pub chunks: BTreeMap<u64, IPLDLink>,
+}
+
+/// Returns the number of the chunk indexing this timecode.
+pub fn index_chunk(timecode: u64) -> u64 {
+    timecode / INDEX_CHUNK_DURATION
+}
+
+/// GossipSub Live Video Message.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum VideoMessage {
+    /// Link to the latest VideoNode.
+    Link(IPLDLink),
+
+    /// Crypto-signed link to the latest VideoNode.
+    Signed(SignedMessage<IPLDLink>),
+
+    /// Link to the latest SegmentIndex.
+    Index(IPLDLink),
+
+    /// Crypto-signed live stream information.
+    Announcement(SignedMessage<LiveAnnouncement>),
+
+    /// Crypto-signed periodic signal that the streamer is still online.
+    Heartbeat(SignedMessage<LiveHeartbeat>),
+
+    /// Stream ended cleanly, no more segments will be published.
+    End,
+}
+
+/// Streamer liveness signal, the sequence increases with each heartbeat.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct LiveHeartbeat {
+    pub sequence: u64,
+
+    /// Unix time in seconds when the heartbeat was sent.
+    pub timestamp: u64,
+}
+
+/// Viewer playback health, published on the health topic of a live stream.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HealthReport {
+    /// Name of the video track being played.
+    pub track: String,
+
+    /// Live segments dropped by the viewer since playback started.
+    pub skipped: usize,
+
+    /// Seconds buffered ahead of playback.
+    pub buffered: f64,
+}
+
+/// Topic viewers publish health reports to, derived from the live video topic.
+pub fn health_topic(topic: &str) -> String {
+    format!("{}_health", topic)
+}
+
+/// Live stream information displayed before video starts.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LiveAnnouncement {
+    pub title: String,
+
+    /// Link to Raw node of poster image.
+    pub poster: Option<IPLDLink>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> VideoMetadata {
+        VideoMetadata::create("Title".into(), 60.0, Cid::default(), Cid::default())
+    }
+
+    #[test]
+    fn absent_access_not_serialized() {
+        let value = serde_json::to_value(&metadata()).unwrap();
+
+        assert!(value.get("access").is_none());
+    }
+
+    #[test]
+    fn access_round_trip() {
+        let mut metadata = metadata();
+        metadata.access = Some(AccessControl::Allowlist(vec![[1u8; 20], [2u8; 20]]));
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        let decoded: VideoMetadata = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded.access, metadata.access);
+    }
+
+    #[test]
+    fn absent_engagement_not_serialized() {
+        let value = serde_json::to_value(&metadata()).unwrap();
+
+        assert!(value.get("engagement").is_none());
+    }
+
+    #[test]
+    fn metadata_without_engagement_deserialized() {
+        let mut value = serde_json::to_value(&metadata()).unwrap();
+        value.as_object_mut().unwrap().remove("engagement");
+
+        let json = value.to_string();
+        let metadata: VideoMetadata = serde_json::from_str(&json).unwrap();
+
+        assert!(metadata.engagement.is_none());
+    }
+
+    fn segment(n: u64) -> Cid {
+        use cid::multihash::{Code, MultihashDigest};
+
+        Cid::new_v1(0x71, Code::Sha2_256.digest(&n.to_be_bytes()))
+    }
+
+    fn index(timecodes: &[u64]) -> SegmentIndex {
+        SegmentIndex {
+            segments: timecodes.iter().map(|t| (*t, segment(*t).into())).collect(),
+        }
+    }
+
+    #[test]
+    fn lookup_exact_timecode() {
+        let index = index(&[0, 4, 8, 12]);
+
+        assert_eq!(index.lookup(8), Some((8, segment(8))));
+    }
+
+    #[test]
+    fn lookup_gap_returns_segment_before() {
+        let index = index(&[0, 4, 8, 12]);
+
+        assert_eq!(index.lookup(7), Some((4, segment(4))));
+        assert_eq!(index.lookup(100), Some((12, segment(12))));
+    }
+
+    #[test]
+    fn nearest_snaps_to_closest() {
+        let index = index(&[0, 4, 8, 12]);
+
+        assert_eq!(index.nearest(5), Some((4, segment(4))));
+        assert_eq!(index.nearest(7), Some((8, segment(8))));
+        assert_eq!(index.nearest(8), Some((8, segment(8))));
+    }
+
+    #[test]
+    fn nearest_tie_prefers_earlier() {
+        let index = index(&[0, 4]);
+
+        assert_eq!(index.nearest(2), Some((0, segment(0))));
+    }
+
+    #[test]
+    fn nearest_outside_index() {
+        let index = index(&[10, 20]);
+
+        assert_eq!(index.nearest(0), Some((10, segment(10))));
+        assert_eq!(index.nearest(99), Some((20, segment(20))));
+        assert_eq!(SegmentIndex::default().nearest(5), None);
+    }
+
+    #[test]
+    fn chunk_boundaries() {
+        assert_eq!(index_chunk(0), 0);
+        assert_eq!(index_chunk(INDEX_CHUNK_DURATION - 1), 0);
+        assert_eq!(index_chunk(INDEX_CHUNK_DURATION), 1);
+        assert_eq!(index_chunk(6 * INDEX_CHUNK_DURATION + 59), 6);
+    }
+
+    #[test]
+    fn lookup_before_window_returns_first() {
+        let index = index(&[10, 14]);
+
+        assert_eq!(index.lookup(3), Some((10, segment(10))));
+        assert_eq!(SegmentIndex::default().lookup(3), None);
+    }
+}
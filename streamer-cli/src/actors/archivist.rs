@@ -1,17 +1,27 @@
 use crate::utils::dag_nodes::ipfs_dag_put_node_async;
 
+use futures_util::future::BoxFuture;
+use futures_util::stream::{FuturesOrdered, StreamExt};
+
 use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot::Sender;
 
+use ipfs_api::response::Error;
 use ipfs_api::IpfsClient;
 
-use linked_data::video::{DayNode, HourNode, MinuteNode, SecondNode, TimecodeNode};
+use linked_data::video::{
+    index_chunk, ChunkedIndex, DayNode, HourNode, MinuteNode, SecondNode, SegmentIndex,
+    TimecodeNode,
+};
 
 use cid::Cid;
 
 pub enum Archive {
     Chat(Cid),
-    Video(Cid),
-    Finalize,
+    /// VideoNode CID and ingest request ID.
+    Video((Cid, usize)),
+    /// Acknowledged once the final node is pinned or finalizing failed.
+    Finalize(Sender<()>),
 }
 
 pub struct Archivist {
@@ -19,15 +29,32 @@ pub struct Archivist {
 
     archive_rx: UnboundedReceiver<Archive>,
 
-    video_chat_buffer: Option<SecondNode>,
+    /// SecondNode and the ingest request ID of its video segment.
+    video_chat_buffer: Option<(usize, SecondNode)>,
+
+    /// Maximum number of SecondNodes being added simultaneously.
+    concurrency: usize,
+
+    /// SecondNodes being added, completed in the order they were pushed.
+    pending: FuturesOrdered<BoxFuture<'static, (usize, Result<Cid, Error>)>>,
 
     minute_node: MinuteNode,
     hour_node: HourNode,
     day_node: DayNode,
+
+    /// Timecode of every video segment, allows seeking to the nearest segment.
+    /// Only the current chunk is buffered, older ones are already added.
+    index: SegmentIndex,
+    index_chunks: ChunkedIndex,
+    timecode: u64,
 }
 
 impl Archivist {
-    pub fn new(ipfs: IpfsClient, archive_rx: UnboundedReceiver<Archive>) -> Self {
+    pub fn new(
+        ipfs: IpfsClient,
+        archive_rx: UnboundedReceiver<Archive>,
+        concurrency: usize,
+    ) -> Self {
         Self {
             ipfs,
 
@@ -35,6 +62,9 @@ impl Archivist {
 
             video_chat_buffer: None,
 
+            concurrency: concurrency.max(1),
+            pending: FuturesOrdered::new(),
+
             minute_node: MinuteNode {
                 links_to_seconds: Vec::with_capacity(60),
             },
@@ -46,17 +76,32 @@ impl Archivist {
             day_node: DayNode {
                 links_to_hours: Vec::with_capacity(24),
             },
+
+            index: SegmentIndex::default(),
+            index_chunks: ChunkedIndex::default(),
+            timecode: 0,
         }
     }
 
     pub async fn start(&mut self) {
         println!("✅ Archive System Online");
 
-        while let Some(event) = self.archive_rx.recv().await {
-            match event {
-                Archive::Chat(cid) => self.archive_chat_message(cid),
-                Archive::Video(cid) => self.archive_video_segment(cid).await,
-                Archive::Finalize => self.finalize().await,
+        loop {
+            tokio::select! {
+                event = self.archive_rx.recv() => match event {
+                    Some(Archive::Chat(cid)) => self.archive_chat_message(cid),
+                    Some(Archive::Video((cid, id))) => self.archive_video_segment(cid, id).await,
+                    Some(Archive::Finalize(ack)) => {
+                        self.finalize().await;
+
+                        let _ = ack.send(());
+                    }
+                    None => break,
+                },
+                // Link SecondNodes as soon as they are added instead of waiting for the next segment.
+                Some((id, result)) = self.pending.next(), if !self.pending.is_empty() => {
+                    self.link_second(id, result).await
+                }
             }
         }
 
@@ -66,7 +111,7 @@ impl Archivist {
     /// Link chat message to SecondNodes.
     fn archive_chat_message(&mut self, msg_cid: Cid) {
         let node = match self.video_chat_buffer.as_mut() {
-            Some(node) => node,
+            Some((_, node)) => node,
             None => return,
         };
 
@@ -74,7 +119,17 @@ impl Archivist {
     }
 
     /// Buffers SecondNodes, waiting for chat messages to be linked.
-    async fn archive_video_segment(&mut self, cid: Cid) {
+    async fn archive_video_segment(&mut self, cid: Cid, id: usize) {
+        #[cfg(debug_assertions)]
+        println!("Archive: request {} timecode {}", id, self.timecode);
+
+        if index_chunk(self.timecode) != self.current_chunk() {
+            self.collect_index_chunk().await;
+        }
+
+        self.index.segments.insert(self.timecode, cid.into());
+        self.timecode += 1;
+
         let second_node = SecondNode {
             link_to_video: cid.into(),
             links_to_chat: Vec::with_capacity(5),
@@ -82,40 +137,60 @@ impl Archivist {
 
         let node = self.video_chat_buffer.take();
 
-        self.video_chat_buffer = Some(second_node);
+        self.video_chat_buffer = Some((id, second_node));
 
-        let node = match node {
-            Some(node) => node,
+        let (id, node) = match node {
+            Some(buffered) => buffered,
             None => return,
         };
 
-        self.collect_second(node).await;
+        self.collect_second(id, node);
 
-        if self.minute_node.links_to_seconds.len() < 60 {
-            return;
+        while self.pending.len() >= self.concurrency {
+            self.link_next_second().await;
         }
+    }
 
-        self.collect_minute().await;
+    /// Create DAG node containing a link to video segment and all chat messages.
+    fn collect_second(&mut self, id: usize, node: SecondNode) {
+        let ipfs = self.ipfs.clone();
 
-        if self.hour_node.links_to_minutes.len() < 60 {
-            return;
-        }
+        let future = async move { (id, ipfs_dag_put_node_async(&ipfs, &node).await) };
 
-        self.collect_hour().await;
+        self.pending.push(Box::pin(future));
     }
 
-    /// Create DAG node containing a link to video segment and all chat messages.
-    /// MinuteNode is then appended with the CID.
-    async fn collect_second(&mut self, node: SecondNode) {
-        let cid = match ipfs_dag_put_node_async(&self.ipfs, &node).await {
+    /// Wait for the oldest pending SecondNode then link it.
+    /// Order is preserved even if nodes are added out of order.
+    async fn link_next_second(&mut self) {
+        if let Some((id, result)) = self.pending.next().await {
+            self.link_second(id, result).await;
+        }
+    }
+
+    /// Append the SecondNode CID to MinuteNode.
+    async fn link_second(&mut self, id: usize, result: Result<Cid, Error>) {
+        let cid = match result {
             Ok(cid) => cid,
             Err(e) => {
-                eprintln!("❗ IPFS: dag put failed {}", e);
+                eprintln!("❗ IPFS: request {} dag put failed {}", id, e);
                 return;
             }
         };
 
         self.minute_node.links_to_seconds.push(cid.into());
+
+        if self.minute_node.links_to_seconds.len() < 60 {
+            return;
+        }
+
+        self.collect_minute().await;
+
+        if self.hour_node.links_to_minutes.len() < 60 {
+            return;
+        }
+
+        self.collect_hour().await;
     }
 
     /// Create DAG node containing 60 SecondNode links. HourNode is then appended with the CID.
@@ -148,14 +223,44 @@ impl Archivist {
         self.day_node.links_to_hours.push(cid.into());
     }
 
+    /// Chunk of the buffered index.
+    fn current_chunk(&self) -> u64 {
+        match self.index.segments.keys().next() {
+            Some(timecode) => index_chunk(*timecode),
+            None => index_chunk(self.timecode),
+        }
+    }
+
+    /// Create DAG node containing one chunk of the index. ChunkedIndex is then appended with the CID.
+    async fn collect_index_chunk(&mut self) {
+        if self.index.segments.is_empty() {
+            return;
+        }
+
+        let chunk = self.current_chunk();
+
+        match ipfs_dag_put_node_async(&self.ipfs, &self.index).await {
+            Ok(cid) => {
+                self.index_chunks.chunks.insert(chunk, cid.into());
+            }
+            Err(e) => eprintln!("❗ IPFS: dag put failed {}", e),
+        }
+
+        self.index.segments.clear();
+    }
+
     /// Create all remaining DAG nodes then pin and print the final CID.
     async fn finalize(&mut self) {
         self.archive_rx.close();
 
         println!("Collecting Nodes...");
 
-        if let Some(node) = self.video_chat_buffer.take() {
-            self.collect_second(node).await;
+        if let Some((id, node)) = self.video_chat_buffer.take() {
+            self.collect_second(id, node);
+        }
+
+        while !self.pending.is_empty() {
+            self.link_next_second().await;
         }
 
         if !self.minute_node.links_to_seconds.is_empty() {
@@ -179,8 +284,23 @@ impl Archivist {
             }
         };
 
+        self.collect_index_chunk().await;
+
+        let index = if self.index_chunks.chunks.is_empty() {
+            None
+        } else {
+            match ipfs_dag_put_node_async(&self.ipfs, &self.index_chunks).await {
+                Ok(cid) => Some(cid.into()),
+                Err(e) => {
+                    eprintln!("❗ IPFS: dag put failed {}", e);
+                    None
+                }
+            }
+        };
+
         let stream = TimecodeNode {
             timecode: cid.into(),
+            index,
         };
 
         let cid = match ipfs_dag_put_node_async(&self.ipfs, &stream).await {
@@ -199,3 +319,89 @@ impl Archivist {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cid::multihash::{Code, MultihashDigest};
+
+    use tokio::sync::mpsc::unbounded_channel;
+    use tokio::sync::oneshot;
+
+    fn second(n: u64) -> Cid {
+        Cid::new_v1(0x71, Code::Sha2_256.digest(&n.to_be_bytes()))
+    }
+
+    #[tokio::test]
+    async fn out_of_order_seconds_linked_in_order() {
+        let (_archive_tx, archive_rx) = unbounded_channel();
+        let mut archivist = Archivist::new(IpfsClient::default(), archive_rx, 3);
+
+        let mut senders = Vec::new();
+
+        for _ in 0..3 {
+            let (tx, rx) = oneshot::channel();
+
+            archivist
+                .pending
+                .push(Box::pin(async move { (0, Ok(rx.await.unwrap())) }));
+
+            senders.push(tx);
+        }
+
+        // The last node is added first.
+        for (n, tx) in senders.into_iter().enumerate().rev() {
+            tx.send(second(n as u64)).unwrap();
+        }
+
+        while !archivist.pending.is_empty() {
+            archivist.link_next_second().await;
+        }
+
+        let links: Vec<Cid> = archivist
+            .minute_node
+            .links_to_seconds
+            .iter()
+            .map(|ipld| ipld.link)
+            .collect();
+
+        assert_eq!(links, vec![second(0), second(1), second(2)]);
+    }
+
+    #[tokio::test]
+    async fn failed_second_skipped() {
+        let (_archive_tx, archive_rx) = unbounded_channel();
+        let mut archivist = Archivist::new(IpfsClient::default(), archive_rx, 3);
+
+        archivist.pending.push(Box::pin(async {
+            (0, Err(Error::Uncategorized("dag put failed".into())))
+        }));
+        archivist
+            .pending
+            .push(Box::pin(async { (1, Ok(second(1))) }));
+
+        while !archivist.pending.is_empty() {
+            archivist.link_next_second().await;
+        }
+
+        assert_eq!(archivist.minute_node.links_to_seconds.len(), 1);
+        assert_eq!(archivist.minute_node.links_to_seconds[0].link, second(1));
+    }
+
+    #[tokio::test]
+    async fn request_id_follows_segment() {
+        let (_archive_tx, archive_rx) = unbounded_channel();
+        let mut archivist = Archivist::new(IpfsClient::default(), archive_rx, 3);
+
+        archivist.archive_video_segment(second(0), 7).await;
+
+        assert_eq!(archivist.video_chat_buffer.as_ref().unwrap().0, 7);
+
+        // The buffered SecondNode is added with the ID of its own segment.
+        archivist.archive_video_segment(second(1), 8).await;
+
+        assert_eq!(archivist.video_chat_buffer.as_ref().unwrap().0, 8);
+        assert_eq!(archivist.pending.len(), 1);
+    }
+}
@@ -4,16 +4,25 @@ use crate::utils::dag_nodes::ipfs_dag_put_node_async;
 
 use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::time::interval;
 
 use ipfs_api::IpfsClient;
 
-use linked_data::video::VideoNode;
+use linked_data::signature::SignedMessage;
+use linked_data::video::{LiveHeartbeat, SegmentIndex, VideoMessage, VideoNode};
 use linked_data::IPLDLink;
 
 use cid::Cid;
 
+/// Number of minted nodes between each segment index update.
+const INDEX_PUBLISH_INTERVAL: usize = 10;
+
+/// Number of seconds of video kept in the segment index.
+const INDEX_WINDOW: u64 = 3600;
+
 pub struct VideoAggregator {
     ipfs: IpfsClient,
 
@@ -28,12 +37,21 @@ pub struct VideoAggregator {
     node_mint_count: usize,
     video_nodes: VecDeque<VideoNode>,
 
+    /// Added to FFMPEG segment indices after timestamps restarted.
+    index_offset: usize,
+    discontinuity: bool,
+
     previous: Option<IPLDLink>,
+
+    index: SegmentIndex,
+
+    heartbeat_sequence: u64,
 }
 
 #[derive(Debug)]
 pub enum VideoData {
-    Segment((PathBuf, Cid)),
+    /// Segment path, CID and ingest request ID.
+    Segment((PathBuf, Cid, usize)),
     Setup((IPLDLink, usize)),
 }
 
@@ -57,28 +75,55 @@ impl VideoAggregator {
 
             node_mint_count: 0,
             video_nodes: VecDeque::with_capacity(5),
+
+            index_offset: 0,
+            discontinuity: false,
+
             previous: None,
+
+            index: SegmentIndex::default(),
+
+            heartbeat_sequence: 0,
         }
     }
 
     pub async fn start(&mut self) {
         println!("✅ Video System Online");
 
-        while let Some(msg) = self.service_rx.recv().await {
-            match msg {
-                VideoData::Segment((path, cid)) => self.media_seg(path, cid).await,
-                VideoData::Setup((link, len)) => {
-                    self.track_len = len;
-                    self.setup_link = Some(link);
-                }
+        let mut heartbeat_enable = self.config.pubsub_enable && self.config.heartbeat_interval > 0;
+
+        // Unsigned heartbeats would be rejected by viewers.
+        if heartbeat_enable && self.config.signing_key.is_none() {
+            eprintln!("❗ Video: no signing key, heartbeats disabled");
+            heartbeat_enable = false;
+        }
+
+        let mut heartbeat = interval(Duration::from_secs(self.config.heartbeat_interval.max(1)));
+
+        loop {
+            tokio::select! {
+                msg = self.service_rx.recv() => match msg {
+                    Some(VideoData::Segment((path, cid, id))) => self.media_seg(path, cid, id).await,
+                    Some(VideoData::Setup((link, len))) => {
+                        self.track_len = len;
+                        self.setup_link = Some(link);
+                    }
+                    None => break,
+                },
+                _ = heartbeat.tick(), if heartbeat_enable => self.heartbeat().await,
             }
         }
 
+        // Viewers can tell a clean end from a stalled stream.
+        if self.config.pubsub_enable {
+            self.publish(&VideoMessage::End).await;
+        }
+
         println!("❌ Video System Offline");
     }
 
     /// Update or create VideoNode in queue then try to mint one.
-    async fn media_seg(&mut self, path: PathBuf, cid: Cid) {
+    async fn media_seg(&mut self, path: PathBuf, cid: Cid, id: usize) {
         let quality = path
             .parent()
             .expect("Orphan path!")
@@ -96,8 +141,31 @@ impl VideoAggregator {
             .parse::<usize>()
             .expect("Not a number");
 
+        #[cfg(debug_assertions)]
+        println!("Video: request {} segment {} {}", id, quality, index);
+
+        if index + self.index_offset < self.node_mint_count {
+            if index != 0 {
+                eprintln!(
+                    "❗ Video: request {} segment {} is behind the stream, skipped",
+                    id, index
+                );
+                return;
+            }
+
+            // FFMPEG restarted numbering, drop incomplete nodes and start a new sequence.
+            eprintln!(
+                "❗ Video: request {} discontinuity detected, new sequence started",
+                id
+            );
+
+            self.video_nodes.clear();
+            self.index_offset = self.node_mint_count;
+            self.discontinuity = true;
+        }
+
         // relative index for in memory video nodes
-        let buffer_index = index - self.node_mint_count;
+        let buffer_index = index + self.index_offset - self.node_mint_count;
 
         if let Some(node) = self.video_nodes.get_mut(buffer_index) {
             node.tracks.insert(quality.to_owned(), cid.into());
@@ -117,10 +185,13 @@ impl VideoAggregator {
 
             let previous = None;
 
+            let discontinuity = std::mem::take(&mut self.discontinuity);
+
             let node = VideoNode {
                 tracks,
                 setup,
                 previous,
+                discontinuity,
             };
 
             self.video_nodes.push_back(node);
@@ -129,7 +200,7 @@ impl VideoAggregator {
         // try to mint in case something failed previously
         while let Some(cid) = self.mint_video_node().await {
             if let Some(archive_tx) = self.archive_tx.as_ref() {
-                let msg = Archive::Video(cid);
+                let msg = Archive::Video((cid, id));
 
                 if let Err(error) = archive_tx.send(msg) {
                     eprintln!("❗ Archive receiver hung up! Error: {}", error);
@@ -143,12 +214,75 @@ impl VideoAggregator {
                     eprintln!("❗ IPFS: pubsub pub failed {}", e);
                 }
             }
+
+            self.update_index(cid).await;
         }
 
         #[cfg(debug_assertions)]
         println!("Video: {} buffered nodes", self.video_nodes.len());
     }
 
+    /// Add the latest VideoNode to the segment index and periodically publish it.
+    async fn update_index(&mut self, cid: Cid) {
+        let timecode = (self.node_mint_count - 1) as u64;
+
+        self.index.segments.insert(timecode, cid.into());
+
+        if timecode >= INDEX_WINDOW {
+            self.index.segments = self.index.segments.split_off(&(timecode - INDEX_WINDOW));
+        }
+
+        if !self.config.pubsub_enable || self.node_mint_count % INDEX_PUBLISH_INTERVAL != 0 {
+            return;
+        }
+
+        let index_cid = match ipfs_dag_put_node_async(&self.ipfs, &self.index).await {
+            Ok(res) => res,
+            Err(e) => {
+                eprintln!("❗ IPFS: dag put failed {}", e);
+                return;
+            }
+        };
+
+        let msg = VideoMessage::Index(index_cid.into());
+
+        self.publish(&msg).await;
+    }
+
+    /// Publish a signed heartbeat on the live video topic.
+    async fn heartbeat(&mut self) {
+        let secret_key = match self.config.signing_key.as_ref() {
+            Some(key) => key,
+            None => return,
+        };
+
+        self.heartbeat_sequence += 1;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+
+        let data = LiveHeartbeat {
+            sequence: self.heartbeat_sequence,
+            timestamp,
+        };
+
+        let msg = VideoMessage::Heartbeat(SignedMessage::sign(data, secret_key));
+
+        self.publish(&msg).await;
+    }
+
+    /// Publish a message on the live video topic.
+    async fn publish(&self, msg: &VideoMessage) {
+        let json_string = serde_json::to_string(msg).expect("Can't serialize");
+
+        let topic = &self.config.pubsub_topic;
+
+        if let Err(e) = self.ipfs.pubsub_pub(topic, &json_string).await {
+            eprintln!("❗ IPFS: pubsub pub failed {}", e);
+        }
+    }
+
     /// Mint the first VideoNode in queue if it meets all requirements.
     async fn mint_video_node(&mut self) -> Option<Cid> {
         let node = self.video_nodes.front_mut()?;
@@ -2,17 +2,29 @@ use crate::utils::dag_nodes::{
     ipfs_dag_get_node_async, ipfs_dag_put_node_async, search_keypairs, update_ipns,
 };
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::fs;
+use tokio::time;
+
+use futures_util::stream::{self, StreamExt};
 
 use ipfs_api::response::Error;
 use ipfs_api::IpfsClient;
 
 use linked_data::blog::FullPost;
-use linked_data::feed::Feed;
+use linked_data::feed::{Feed, FEED_PAGE_SIZE};
 use linked_data::video::{DayNode, HourNode, MinuteNode, VideoMetadata};
 
 use cid::Cid;
 
+use serde::Serialize;
+use serde_json::Value;
+
 use structopt::StructOpt;
 
 pub const FEED_KEY: &str = "feed";
@@ -33,6 +45,9 @@ enum Command {
 
     /// Delete content from your feed.
     Delete(DeleteContent),
+
+    /// Save your feed and all content metadata to a file.
+    Export(ExportContent),
 }
 
 pub async fn content_feed_cli(cli: ContentFeed) {
@@ -46,6 +61,7 @@ pub async fn content_feed_cli(cli: ContentFeed) {
             UpdateContent::Video(video) => update_video(video).await,
         },
         Command::Delete(delete) => delete_content(delete).await,
+        Command::Export(export) => export_content(export).await,
     };
 
     if let Err(e) = res {
@@ -67,13 +83,37 @@ async fn add_content_to_feed(ipfs: &IpfsClient, new_cid: Cid) -> Result<usize, E
 
     let mut feed = get_feed(ipfs).await?;
 
+    let older: usize = get_pages(ipfs, &feed)
+        .await?
+        .iter()
+        .map(|(_, page)| page.content.len())
+        .sum();
+
     ipfs.pin_add(&new_cid.to_string(), true).await?;
 
+    if feed.content.len() >= FEED_PAGE_SIZE {
+        let page = Feed {
+            content: std::mem::take(&mut feed.content),
+            sequence: feed.sequence,
+            previous: feed.previous.take(),
+        };
+
+        let page_cid = ipfs_dag_put_node_async(ipfs, &page).await?;
+
+        ipfs.pin_add(&page_cid.to_string(), false).await?;
+
+        println!("Full Page Moved To {}", page_cid);
+
+        feed.previous = Some(page_cid.into());
+    }
+
     feed.content.push(new_cid.into());
 
+    feed.sequence += 1;
+
     update_ipns(&ipfs, &FEED_KEY, &feed).await?;
 
-    Ok(feed.content.len() - 1)
+    Ok(older + feed.content.len() - 1)
 }
 
 #[derive(Debug, StructOpt)]
@@ -123,6 +163,10 @@ pub struct AddVideo {
     #[structopt(short, long)]
     image: Cid,
 
+    /// Smaller version of the image shown in the video grid.
+    #[structopt(long)]
+    thumbnail: Option<Cid>,
+
     /// The new video timecode CID.
     #[structopt(short, long)]
     video: Cid,
@@ -134,12 +178,14 @@ async fn add_video(command: AddVideo) -> Result<(), Error> {
     let AddVideo {
         title,
         image,
+        thumbnail,
         video,
     } = command;
 
     let duration = get_video_duration(&ipfs, &video).await?;
 
-    let metadata = VideoMetadata::create(title, duration, image, video);
+    let mut metadata = VideoMetadata::create(title, duration, image, video);
+    metadata.thumbnail = thumbnail.map(|cid| cid.into());
 
     let new_cid = ipfs_dag_put_node_async(&ipfs, &metadata).await?;
 
@@ -163,7 +209,7 @@ enum UpdateContent {
 
 #[derive(Debug, StructOpt)]
 pub struct UpdatePost {
-    /// The content feed index of the post to update.
+    /// The index of the post to update, counting from the oldest content.
     #[structopt(long)]
     index: usize,
 
@@ -183,7 +229,7 @@ pub struct UpdatePost {
 async fn update_blog(command: UpdatePost) -> Result<(), Error> {
     let ipfs = IpfsClient::default();
 
-    let mut feed = get_feed(&ipfs).await?;
+    let mut feed = FeedPages::load(&ipfs).await?;
 
     let UpdatePost {
         index,
@@ -192,11 +238,13 @@ async fn update_blog(command: UpdatePost) -> Result<(), Error> {
         content,
     } = command;
 
-    let old_cid = match feed.content.get(index) {
-        Some(mt) => mt.link,
+    let (page, position) = match feed.locate(index) {
+        Some(location) => location,
         None => return Err(Error::Uncategorized("Blog Post Index Not Found".into())),
     };
 
+    let old_cid = feed.page_mut(page).content[position].link;
+
     ipfs.pin_rm(&old_cid.to_string(), true).await?;
 
     let mut metadata: FullPost = ipfs_dag_get_node_async(&ipfs, &old_cid.to_string()).await?;
@@ -211,9 +259,9 @@ async fn update_blog(command: UpdatePost) -> Result<(), Error> {
 
     ipfs.pin_add(&new_cid.to_string(), true).await?;
 
-    feed.content[index] = new_cid.into();
+    feed.page_mut(page).content[position] = new_cid.into();
 
-    update_ipns(&ipfs, &FEED_KEY, &feed).await?;
+    feed.save(&ipfs, page).await?;
 
     println!("✅ Weblog Post Updated In Content Feed At Index {}", index);
 
@@ -222,7 +270,7 @@ async fn update_blog(command: UpdatePost) -> Result<(), Error> {
 
 #[derive(Debug, StructOpt)]
 pub struct UpdateVideo {
-    /// The content feed index of the video to update.
+    /// The index of the video to update, counting from the oldest content.
     #[structopt(long)]
     index: usize,
 
@@ -234,6 +282,10 @@ pub struct UpdateVideo {
     #[structopt(short, long)]
     image: Option<Cid>,
 
+    /// Smaller version of the image shown in the video grid.
+    #[structopt(long)]
+    thumbnail: Option<Cid>,
+
     /// The new video timecode CID.
     #[structopt(short, long)]
     video: Option<Cid>,
@@ -242,20 +294,23 @@ pub struct UpdateVideo {
 async fn update_video(command: UpdateVideo) -> Result<(), Error> {
     let ipfs = IpfsClient::default();
 
-    let mut feed = get_feed(&ipfs).await?;
+    let mut feed = FeedPages::load(&ipfs).await?;
 
     let UpdateVideo {
         index,
         title,
         image,
+        thumbnail,
         video,
     } = command;
 
-    let old_cid = match feed.content.get(index) {
-        Some(mt) => mt.link,
+    let (page, position) = match feed.locate(index) {
+        Some(location) => location,
         None => return Err(Error::Uncategorized("Video Index Not Found".into())),
     };
 
+    let old_cid = feed.page_mut(page).content[position].link;
+
     let mut metadata: VideoMetadata = ipfs_dag_get_node_async(&ipfs, &old_cid.to_string()).await?;
 
     let duration = match video {
@@ -263,7 +318,7 @@ async fn update_video(command: UpdateVideo) -> Result<(), Error> {
         None => None,
     };
 
-    metadata.update(title, image, video, duration);
+    metadata.update(title, image, thumbnail, video, duration);
 
     let new_cid = ipfs_dag_put_node_async(&ipfs, &metadata).await?;
 
@@ -271,9 +326,9 @@ async fn update_video(command: UpdateVideo) -> Result<(), Error> {
 
     println!("Updating Content Feed...");
 
-    feed.content[index] = new_cid.into();
+    feed.page_mut(page).content[position] = new_cid.into();
 
-    update_ipns(&ipfs, &FEED_KEY, &feed).await?;
+    feed.save(&ipfs, page).await?;
 
     println!("✅ Video Post Updated In Content Feed At Index {}", index);
 
@@ -282,7 +337,7 @@ async fn update_video(command: UpdateVideo) -> Result<(), Error> {
 
 #[derive(Debug, StructOpt)]
 pub struct DeleteContent {
-    /// The index of the content to delete.
+    /// The index of the content to delete, counting from the oldest content.
     #[structopt(short, long)]
     index: usize,
 }
@@ -291,20 +346,168 @@ async fn delete_content(command: DeleteContent) -> Result<(), Error> {
     println!("Deleting Content...");
     let ipfs = IpfsClient::default();
 
-    let mut feed = get_feed(&ipfs).await?;
+    let mut feed = FeedPages::load(&ipfs).await?;
 
-    let link = feed.content.remove(command.index);
+    let (page, position) = match feed.locate(command.index) {
+        Some(location) => location,
+        None => return Err(Error::Uncategorized("Content Index Not Found".into())),
+    };
+
+    let link = feed.page_mut(page).content.remove(position);
 
     ipfs.pin_rm(&link.link.to_string(), true).await?;
 
-    update_ipns(&ipfs, &FEED_KEY, &feed).await?;
+    feed.save(&ipfs, page).await?;
 
     println!("✅ Post In Content Feed At Index {} Deleted", command.index);
 
     Ok(())
 }
 
-async fn get_feed(ipfs: &IpfsClient) -> Result<Feed, Error> {
+#[derive(Debug, StructOpt)]
+pub struct ExportContent {
+    /// Path of the exported file.
+    #[structopt(short, long, default_value = "channel_export.json")]
+    output: PathBuf,
+
+    /// Maximum number of nodes fetched simultaneously.
+    #[structopt(short, long, default_value = "8")]
+    concurrency: usize,
+
+    /// Seconds to wait for each node before reporting it unreachable.
+    #[structopt(short, long, default_value = "30")]
+    timeout: u64,
+}
+
+#[derive(Serialize)]
+struct ChannelExport {
+    feed: Feed,
+
+    /// Older feed pages, newest first.
+    pages: Vec<Feed>,
+
+    /// Content CIDs mapped to their nodes.
+    content: BTreeMap<String, Value>,
+
+    /// Content that could not be fetched.
+    missing: Vec<String>,
+}
+
+async fn export_content(command: ExportContent) -> Result<(), Error> {
+    println!("Exporting Content Feed...");
+    let ipfs = IpfsClient::default();
+
+    let ExportContent {
+        output,
+        concurrency,
+        timeout,
+    } = command;
+
+    let cid = resolve_feed(&ipfs).await?;
+
+    let feed: Feed = ipfs_dag_get_node_async(&ipfs, &cid.to_string()).await?;
+
+    let pages: Vec<Feed> = get_pages(&ipfs, &feed)
+        .await?
+        .into_iter()
+        .map(|(_, page)| page)
+        .collect();
+
+    let timeout = Duration::from_secs(timeout);
+
+    let export = build_export(
+        feed,
+        pages,
+        |cid| {
+            let ipfs = &ipfs;
+
+            async move { ipfs_dag_get_node_async::<Value>(ipfs, &cid).await }
+        },
+        concurrency,
+        timeout,
+    )
+    .await;
+
+    let count = export.content.len();
+    let unreachable = export.missing.len();
+
+    write_export(&output, &export).await?;
+
+    println!("✅ Exported {} Nodes To {:?}", count, output);
+
+    if unreachable > 0 {
+        eprintln!(
+            "❗ {} Nodes Unreachable, see missing in export",
+            unreachable
+        );
+    }
+
+    Ok(())
+}
+
+/// Fetch every content node of the feed and its pages, unreachable nodes are listed as missing.
+async fn build_export<F, Fut>(
+    feed: Feed,
+    pages: Vec<Feed>,
+    fetch: F,
+    concurrency: usize,
+    timeout: Duration,
+) -> ChannelExport
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Value, Error>>,
+{
+    let links = feed
+        .content
+        .iter()
+        .chain(pages.iter().flat_map(|page| page.content.iter()));
+
+    let mut results = stream::iter(links.map(|ipld| {
+        let cid = ipld.link.to_string();
+        let node = fetch(cid.clone());
+
+        async move { (cid, time::timeout(timeout, node).await) }
+    }))
+    .buffer_unordered(concurrency.max(1));
+
+    let mut content = BTreeMap::new();
+    let mut missing = Vec::new();
+
+    while let Some((cid, result)) = results.next().await {
+        match result {
+            Ok(Ok(node)) => {
+                content.insert(cid, node);
+            }
+            Ok(Err(e)) => {
+                eprintln!("❗ IPFS: {} {}", cid, e);
+                missing.push(cid);
+            }
+            Err(_) => {
+                eprintln!("❗ Timed out fetching {}", cid);
+                missing.push(cid);
+            }
+        }
+    }
+
+    drop(results);
+
+    ChannelExport {
+        feed,
+        pages,
+        content,
+        missing,
+    }
+}
+
+async fn write_export(output: &Path, export: &ChannelExport) -> Result<(), Error> {
+    let data = serde_json::to_vec_pretty(export).expect("Serialization failed");
+
+    fs::write(output, data)
+        .await
+        .map_err(|e| Error::Uncategorized(format!("Cannot write {:?}. {}", output, e)))
+}
+
+async fn resolve_feed(ipfs: &IpfsClient) -> Result<Cid, Error> {
     let mut res = ipfs.key_list().await?;
 
     let keypair = match search_keypairs(&FEED_KEY, &mut res) {
@@ -319,6 +522,12 @@ async fn get_feed(ipfs: &IpfsClient) -> Result<Feed, Error> {
 
     let cid = Cid::try_from(res.path).expect("Invalid Cid");
 
+    Ok(cid)
+}
+
+async fn get_feed(ipfs: &IpfsClient) -> Result<Feed, Error> {
+    let cid = resolve_feed(ipfs).await?;
+
     ipfs.pin_rm(&cid.to_string(), false).await?;
 
     let node = ipfs_dag_get_node_async(ipfs, &cid.to_string()).await?;
@@ -326,6 +535,101 @@ async fn get_feed(ipfs: &IpfsClient) -> Result<Feed, Error> {
     Ok(node)
 }
 
+/// Older feed pages with their CIDs, newest first.
+async fn get_pages(ipfs: &IpfsClient, feed: &Feed) -> Result<Vec<(Cid, Feed)>, Error> {
+    let mut pages = Vec::new();
+    let mut previous = feed.previous.as_ref().map(|ipld| ipld.link);
+
+    while let Some(cid) = previous {
+        let page: Feed = ipfs_dag_get_node_async(ipfs, &cid.to_string()).await?;
+
+        previous = page.previous.as_ref().map(|ipld| ipld.link);
+
+        pages.push((cid, page));
+    }
+
+    Ok(pages)
+}
+
+/// Newest feed and every older page, content is indexed from the oldest.
+struct FeedPages {
+    feed: Feed,
+
+    /// Older pages with their CIDs, newest first.
+    pages: Vec<(Cid, Feed)>,
+}
+
+impl FeedPages {
+    async fn load(ipfs: &IpfsClient) -> Result<Self, Error> {
+        let feed = get_feed(ipfs).await?;
+
+        let pages = get_pages(ipfs, &feed).await?;
+
+        Ok(Self { feed, pages })
+    }
+
+    /// Page and position of the content at this index. Page 0 is the newest feed.
+    fn locate(&self, index: usize) -> Option<(usize, usize)> {
+        let lengths: Vec<usize> = std::iter::once(&self.feed)
+            .chain(self.pages.iter().map(|(_, page)| page))
+            .map(|page| page.content.len())
+            .collect();
+
+        locate_content(&lengths, index)
+    }
+
+    fn page_mut(&mut self, page: usize) -> &mut Feed {
+        match page {
+            0 => &mut self.feed,
+            page => &mut self.pages[page - 1].1,
+        }
+    }
+
+    /// Add the edited page and every newer one, each linking to the new older page, then publish the feed.
+    async fn save(mut self, ipfs: &IpfsClient, edited: usize) -> Result<(), Error> {
+        let mut previous = None;
+
+        for page in (1..=edited).rev() {
+            let (old_cid, node) = &mut self.pages[page - 1];
+
+            if let Some(link) = previous {
+                node.previous = Some(link);
+            }
+
+            let cid = ipfs_dag_put_node_async(ipfs, node).await?;
+
+            ipfs.pin_add(&cid.to_string(), false).await?;
+            ipfs.pin_rm(&old_cid.to_string(), false).await?;
+
+            previous = Some(cid.into());
+        }
+
+        if let Some(link) = previous {
+            self.feed.previous = Some(link);
+        }
+
+        self.feed.sequence += 1;
+
+        update_ipns(ipfs, &FEED_KEY, &self.feed).await
+    }
+}
+
+/// Page and position of the content at this index, counting from the oldest content.
+/// Lengths are of the newest page first.
+fn locate_content(lengths: &[usize], index: usize) -> Option<(usize, usize)> {
+    let mut start = 0;
+
+    for (page, len) in lengths.iter().enumerate().rev() {
+        if index < start + len {
+            return Some((page, index - start));
+        }
+
+        start += len;
+    }
+
+    None
+}
+
 async fn get_video_duration(ipfs: &IpfsClient, video: &Cid) -> Result<f64, Error> {
     let path = format!("{}/time", video.to_string());
 
@@ -349,3 +653,107 @@ async fn get_video_duration(ipfs: &IpfsClient, video: &Cid) -> Result<f64, Error
 
     Ok(duration)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_page_index_unchanged() {
+        assert_eq!(locate_content(&[3], 0), Some((0, 0)));
+        assert_eq!(locate_content(&[3], 2), Some((0, 2)));
+        assert_eq!(locate_content(&[3], 3), None);
+    }
+
+    #[test]
+    fn older_content_found_in_previous_pages() {
+        // Newest feed holds 2, previous pages hold 500 then the oldest 500.
+        let lengths = [2, FEED_PAGE_SIZE, FEED_PAGE_SIZE];
+
+        assert_eq!(locate_content(&lengths, 0), Some((2, 0)));
+        assert_eq!(locate_content(&lengths, 499), Some((2, 499)));
+        assert_eq!(locate_content(&lengths, 500), Some((1, 0)));
+        assert_eq!(locate_content(&lengths, 999), Some((1, 499)));
+        assert_eq!(locate_content(&lengths, 1000), Some((0, 0)));
+        assert_eq!(locate_content(&lengths, 1001), Some((0, 1)));
+        assert_eq!(locate_content(&lengths, 1002), None);
+    }
+
+    #[test]
+    fn empty_pages_skipped() {
+        let lengths = [0, 1, 0];
+
+        assert_eq!(locate_content(&lengths, 0), Some((1, 0)));
+        assert_eq!(locate_content(&lengths, 1), None);
+    }
+
+    fn content(n: u64) -> Cid {
+        use cid::multihash::{Code, MultihashDigest};
+
+        Cid::new_v1(0x71, Code::Sha2_256.digest(&n.to_be_bytes()))
+    }
+
+    fn page(cids: &[Cid]) -> Feed {
+        Feed {
+            content: cids.iter().map(|cid| (*cid).into()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn export_lists_unreachable_nodes() {
+        let feed = page(&[content(2)]);
+        let pages = vec![page(&[content(0), content(1)])];
+
+        // Mock DAG, the second content is missing.
+        let dag: BTreeMap<String, Value> = vec![
+            (content(0).to_string(), Value::from(0)),
+            (content(2).to_string(), Value::from(2)),
+        ]
+        .into_iter()
+        .collect();
+
+        let fetch = |cid: String| {
+            let node = dag.get(&cid).cloned();
+
+            async move { node.ok_or_else(|| Error::Uncategorized("Not Found".into())) }
+        };
+
+        let export = build_export(feed, pages, fetch, 2, Duration::from_secs(1)).await;
+
+        assert_eq!(export.content, dag);
+        assert_eq!(export.missing, vec![content(1).to_string()]);
+    }
+
+    #[tokio::test]
+    async fn export_timeout_reported_missing() {
+        let feed = page(&[content(0)]);
+
+        let fetch = |_| async {
+            time::sleep(Duration::from_secs(60)).await;
+
+            Ok(Value::Null)
+        };
+
+        let export = build_export(feed, Vec::new(), fetch, 1, Duration::from_millis(10)).await;
+
+        assert!(export.content.is_empty());
+        assert_eq!(export.missing, vec![content(0).to_string()]);
+    }
+
+    #[tokio::test]
+    async fn export_write_error_returned() {
+        let export = build_export(
+            Feed::default(),
+            Vec::new(),
+            |_| async { Ok(Value::Null) },
+            1,
+            Duration::from_secs(1),
+        )
+        .await;
+
+        let output = Path::new("/nonexistent/channel_export.json");
+
+        assert!(write_export(output, &export).await.is_err());
+    }
+}
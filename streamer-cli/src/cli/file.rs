@@ -1,6 +1,9 @@
 use crate::actors::{Archivist, SetupAggregator, VideoAggregator};
 use crate::server::start_server;
 use crate::utils::config::Configuration;
+use crate::utils::ipfs::wait_for_ipfs;
+
+use std::time::Duration;
 
 use tokio::sync::mpsc::unbounded_channel;
 
@@ -9,22 +12,38 @@ use ipfs_api::IpfsClient;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
-pub struct File {}
+pub struct File {
+    /// Number of attempts to reach IPFS before aborting.
+    #[structopt(long, default_value = "5")]
+    ipfs_attempts: usize,
+
+    /// Seconds before retrying to reach IPFS, doubled after each attempt up to 30.
+    #[structopt(long, default_value = "1")]
+    ipfs_interval: u64,
+}
+
+pub async fn file_cli(file: File) {
+    let File {
+        ipfs_attempts,
+        ipfs_interval,
+    } = file;
 
-pub async fn file_cli(_file: File) {
     let ipfs = IpfsClient::default();
 
-    if let Err(e) = ipfs.id(None).await {
-        eprintln!("❗ IPFS must be started beforehand. {}", e);
+    if let Err(e) = wait_for_ipfs(&ipfs, ipfs_attempts, ipfs_interval).await {
+        eprintln!(
+            "❗ IPFS unreachable after {} attempts. IPFS must be started beforehand. {}",
+            ipfs_attempts, e
+        );
         return;
     }
 
     println!("Initialization...");
 
-    let config = match Configuration::from_file().await {
+    let config = match Configuration::load().await {
         Ok(conf) => conf,
         Err(e) => {
-            eprintln!("❗ Configuration file not found. {}", e);
+            eprintln!("❗ Configuration error. {}", e);
             return;
         }
     };
@@ -36,13 +55,15 @@ pub async fn file_cli(_file: File) {
         chat,
     } = config;
 
+    let finalize_timeout = Duration::from_secs(archive.finalize_timeout);
+
     let mut handles = Vec::with_capacity(4);
 
     let (archive_tx, archive_rx) = unbounded_channel();
 
     archive.archive_live_chat = false;
 
-    let mut archivist = Archivist::new(ipfs.clone(), archive_rx);
+    let mut archivist = Archivist::new(ipfs.clone(), archive_rx, archive.concurrency);
 
     let archive_handle = tokio::spawn(async move {
         archivist.start().await;
@@ -78,6 +99,7 @@ pub async fn file_cli(_file: File) {
             video_tx,
             setup_tx,
             Some(archive_tx),
+            finalize_timeout,
             ipfs,
             chat.topic,
         )
@@ -1,6 +1,9 @@
 use crate::actors::{Archivist, ChatAggregator, SetupAggregator, VideoAggregator};
 use crate::server::start_server;
 use crate::utils::config::Configuration;
+use crate::utils::ipfs::wait_for_ipfs;
+
+use std::time::Duration;
 
 use tokio::sync::mpsc::unbounded_channel;
 
@@ -17,27 +20,40 @@ pub struct Stream {
     /// Disable all archiving.
     #[structopt(long)]
     no_archive: bool,
+
+    /// Number of attempts to reach IPFS before aborting.
+    #[structopt(long, default_value = "5")]
+    ipfs_attempts: usize,
+
+    /// Seconds before retrying to reach IPFS, doubled after each attempt up to 30.
+    #[structopt(long, default_value = "1")]
+    ipfs_interval: u64,
 }
 
 pub async fn stream_cli(stream: Stream) {
     let Stream {
         no_chat,
         no_archive,
+        ipfs_attempts,
+        ipfs_interval,
     } = stream;
 
     let ipfs = IpfsClient::default();
 
-    if ipfs.id(None).await.is_err() {
-        eprintln!("❗ IPFS must be started beforehand. Aborting...");
+    if let Err(e) = wait_for_ipfs(&ipfs, ipfs_attempts, ipfs_interval).await {
+        eprintln!(
+            "❗ IPFS unreachable after {} attempts. IPFS must be started beforehand. {}",
+            ipfs_attempts, e
+        );
         return;
     }
 
     println!("Initialization...");
 
-    let config = match Configuration::from_file().await {
+    let config = match Configuration::load().await {
         Ok(conf) => conf,
         Err(e) => {
-            eprintln!("❗ Configuration file not found. {}", e);
+            eprintln!("❗ Configuration error. {}", e);
             return;
         }
     };
@@ -49,6 +65,8 @@ pub async fn stream_cli(stream: Stream) {
         chat,
     } = config;
 
+    let finalize_timeout = Duration::from_secs(archive.finalize_timeout);
+
     let mut handles = Vec::with_capacity(4);
 
     let topic = chat.topic.clone();
@@ -69,7 +87,7 @@ pub async fn stream_cli(stream: Stream) {
 
             archive.archive_live_chat = !no_chat;
 
-            let mut archivist = Archivist::new(ipfs.clone(), archive_rx);
+            let mut archivist = Archivist::new(ipfs.clone(), archive_rx, archive.concurrency);
 
             let archive_handle = tokio::spawn(async move {
                 archivist.start().await;
@@ -111,6 +129,7 @@ pub async fn stream_cli(stream: Stream) {
             video_tx,
             setup_tx,
             archive_tx,
+            finalize_timeout,
             ipfs,
             topic,
         )
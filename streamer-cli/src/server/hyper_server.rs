@@ -3,9 +3,12 @@ use crate::server::services::put_requests;
 
 use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use tokio::signal::ctrl_c;
 use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+use tokio::time::timeout;
 
 use hyper::service::{make_service_fn, service_fn};
 use hyper::Server;
@@ -16,13 +19,16 @@ async fn shutdown_signal(
     ipfs: IpfsClient,
     topic: String,
     archive_tx: Option<UnboundedSender<Archive>>,
+    finalize_timeout: Duration,
 ) {
     ctrl_c()
         .await
         .expect("Failed to install CTRL+C signal handler");
 
     if let Some(archive_tx) = archive_tx {
-        let msg = Archive::Finalize;
+        let (ack_tx, ack_rx) = oneshot::channel();
+
+        let msg = Archive::Finalize(ack_tx);
 
         if let Err(error) = archive_tx.send(msg) {
             eprintln!("Archive receiver hung up {}", error);
@@ -32,6 +38,23 @@ async fn shutdown_signal(
         ipfs.pubsub_pub(&topic, "Stopping")
             .await
             .expect("PubSub Pub Failed!");
+
+        // Wait for the archive root before the server stops.
+        match wait_for_finalize(ack_rx, finalize_timeout).await {
+            Ok(()) => println!("✅ Archive Finalized"),
+            Err(e) => eprintln!("❗ {}", e),
+        }
+    }
+}
+
+async fn wait_for_finalize(
+    ack_rx: oneshot::Receiver<()>,
+    finalize_timeout: Duration,
+) -> Result<(), &'static str> {
+    match timeout(finalize_timeout, ack_rx).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(_)) => Err("Archive stopped before finalizing"),
+        Err(_) => Err("Archive finalize timed out, the archive may be incomplete"),
     }
 }
 
@@ -40,6 +63,7 @@ pub async fn start_server(
     video_tx: UnboundedSender<VideoData>,
     setup_tx: UnboundedSender<SetupData>,
     archive_tx: Option<UnboundedSender<Archive>>,
+    finalize_timeout: Duration,
     ipfs: IpfsClient,
     topic: String,
 ) {
@@ -63,7 +87,12 @@ pub async fn start_server(
 
     println!("✅ Ingess Server Online");
 
-    let graceful = server.with_graceful_shutdown(shutdown_signal(ipfs_clone, topic, archive_tx));
+    let graceful = server.with_graceful_shutdown(shutdown_signal(
+        ipfs_clone,
+        topic,
+        archive_tx,
+        finalize_timeout,
+    ));
 
     if let Err(e) = graceful.await {
         eprintln!("Server: {}", e);
@@ -71,3 +100,48 @@ pub async fn start_server(
 
     println!("❌ Ingess Server Offline");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn shutdown_waits_for_ack() {
+        let (ack_tx, ack_rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+
+            ack_tx.send(()).unwrap();
+        });
+
+        let result = wait_for_finalize(ack_rx, Duration::from_secs(5)).await;
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn shutdown_times_out_without_ack() {
+        let (_ack_tx, ack_rx) = oneshot::channel();
+
+        let result = wait_for_finalize(ack_rx, Duration::from_millis(10)).await;
+
+        assert_eq!(
+            result,
+            Err("Archive finalize timed out, the archive may be incomplete")
+        );
+    }
+
+    #[tokio::test]
+    async fn archivist_dropped_before_ack() {
+        let (ack_tx, ack_rx) = oneshot::channel();
+
+        drop(ack_tx);
+
+        let result = wait_for_finalize(ack_rx, Duration::from_secs(5)).await;
+
+        assert_eq!(result, Err("Archive stopped before finalizing"));
+    }
+}
@@ -3,6 +3,7 @@ use crate::actors::{SetupData, VideoData};
 use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use futures_util::stream::TryStreamExt;
 
@@ -22,6 +23,9 @@ const M3U8: &str = "m3u8";
 pub const MP4: &str = "mp4";
 pub const M4S: &str = "m4s";
 
+/// Incrementing ID used to trace a request across logs.
+static REQUEST_ID: AtomicUsize = AtomicUsize::new(0);
+
 const OPTIONS: ipfs_api::request::Add = ipfs_api::request::Add {
     trickle: None,
     only_hash: None,
@@ -41,8 +45,10 @@ pub async fn put_requests(
     setup_tx: UnboundedSender<SetupData>,
     ipfs: IpfsClient,
 ) -> Result<Response<Body>, Error> {
+    let id = REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+
     #[cfg(debug_assertions)]
-    println!("Service: {:#?}", req);
+    println!("Service: request {} {:#?}", id, req);
 
     let mut res = Response::new(Body::empty());
 
@@ -60,7 +66,7 @@ pub async fn put_requests(
     }
 
     if path.extension().unwrap() == M3U8 {
-        return manifest_response(res, body, &path, setup_tx).await;
+        return manifest_response(res, body, &path, setup_tx, id).await;
     }
 
     //Change error type
@@ -72,23 +78,23 @@ pub async fn put_requests(
 
     let cid = match ipfs.add_with_options(reader, OPTIONS).await {
         Ok(res) => Cid::try_from(res.hash).expect("Invalid Cid"),
-        Err(error) => return internal_error_response(res, &error),
+        Err(error) => return internal_error_response(res, &error, id),
     };
 
     #[cfg(debug_assertions)]
-    println!("IPFS: add => {}", &cid.to_string());
+    println!("IPFS: request {} add => {}", id, &cid.to_string());
 
     if path.extension().unwrap() == M4S {
-        let msg = VideoData::Segment((path.to_path_buf(), cid));
+        let msg = VideoData::Segment((path.to_path_buf(), cid, id));
 
         if let Err(error) = video_tx.send(msg) {
-            return internal_error_response(res, &error);
+            return internal_error_response(res, &error, id);
         }
     } else if path.extension().unwrap() == MP4 {
         let msg = SetupData::Segment((path.to_path_buf(), cid));
 
         if let Err(error) = setup_tx.send(msg) {
-            return internal_error_response(res, &error);
+            return internal_error_response(res, &error, id);
         }
     }
 
@@ -118,19 +124,20 @@ async fn manifest_response(
     body: Body,
     path: &Path,
     setup_tx: UnboundedSender<SetupData>,
+    id: usize,
 ) -> Result<Response<Body>, Error> {
     let bytes = hyper::body::to_bytes(body).await?;
 
     let playlist = match m3u8_rs::parse_playlist(&bytes) {
         Ok((_, playlist)) => playlist,
-        Err(e) => return internal_error_response(res, &e),
+        Err(e) => return internal_error_response(res, &e, id),
     };
 
     if let Playlist::MasterPlaylist(playlist) = playlist {
         let msg = SetupData::Playlist(playlist);
 
         if let Err(error) = setup_tx.send(msg) {
-            return internal_error_response(res, &error);
+            return internal_error_response(res, &error, id);
         }
     }
 
@@ -149,8 +156,9 @@ async fn manifest_response(
 fn internal_error_response(
     mut res: Response<Body>,
     error: &dyn Debug,
+    id: usize,
 ) -> Result<Response<Body>, Error> {
-    eprintln!("Service: {:#?}", error);
+    eprintln!("Service: request {} {:#?}", id, error);
 
     *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
 
@@ -1,15 +1,34 @@
 use tokio::fs;
 
-use std::io::Error;
+use std::env;
+use std::io::{Error, ErrorKind};
 use std::net::SocketAddr;
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
+use linked_data::signature::SecretKey;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ArchiveConfig {
     #[serde(skip)]
     pub archive_live_chat: bool, // get from argument not file
+
+    /// Maximum number of nodes being added to IPFS simultaneously.
+    #[serde(default = "default_archive_concurrency")]
+    pub concurrency: usize,
+
+    /// Seconds to wait for the archive to be finalized at shutdown.
+    #[serde(default = "default_finalize_timeout")]
+    pub finalize_timeout: u64,
+}
+
+fn default_archive_concurrency() -> usize {
+    4
+}
+
+fn default_finalize_timeout() -> u64 {
+    60
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -18,6 +37,18 @@ pub struct VideoConfig {
     pub pubsub_enable: bool, // get from argument not file
 
     pub pubsub_topic: String,
+
+    /// Seconds between live heartbeats, 0 to disable.
+    #[serde(default = "default_heartbeat_interval")]
+    pub heartbeat_interval: u64,
+
+    /// Key signing heartbeats, never saved to the file.
+    #[serde(skip)]
+    pub signing_key: Option<SecretKey>,
+}
+
+fn default_heartbeat_interval() -> u64 {
+    5
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -41,6 +72,13 @@ pub struct Configuration {
 
 const CONFIG_LOCATION: &str = "config.json";
 
+/// Environment variables overriding configuration file fields.
+const ENV_INPUT_SOCKET_ADDR: &str = "DIT_INPUT_SOCKET_ADDR";
+const ENV_ARCHIVE_CONCURRENCY: &str = "DIT_ARCHIVE_CONCURRENCY";
+const ENV_VIDEO_TOPIC: &str = "DIT_VIDEO_TOPIC";
+const ENV_CHAT_TOPIC: &str = "DIT_CHAT_TOPIC";
+const ENV_SIGNING_KEY: &str = "DIT_SIGNING_KEY";
+
 impl Configuration {
     pub async fn from_file() -> Result<Self, Error> {
         let config = fs::read(CONFIG_LOCATION).await?;
@@ -49,6 +87,82 @@ impl Configuration {
         Ok(config)
     }
 
+    /// Read the configuration file, apply environment overrides then validate.
+    pub async fn load() -> Result<Self, Error> {
+        let mut config = Self::from_file().await?;
+
+        config.apply_env_overrides()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<(), Error> {
+        if let Ok(addr) = env::var(ENV_INPUT_SOCKET_ADDR) {
+            self.input_socket_addr = SocketAddr::from_str(&addr).map_err(|e| {
+                invalid(format!(
+                    "{} {:?} is not a socket address. {}",
+                    ENV_INPUT_SOCKET_ADDR, addr, e
+                ))
+            })?;
+        }
+
+        if let Ok(concurrency) = env::var(ENV_ARCHIVE_CONCURRENCY) {
+            self.archive.concurrency = concurrency.parse().map_err(|e| {
+                invalid(format!(
+                    "{} {:?} is not a number. {}",
+                    ENV_ARCHIVE_CONCURRENCY, concurrency, e
+                ))
+            })?;
+        }
+
+        if let Ok(topic) = env::var(ENV_VIDEO_TOPIC) {
+            self.video.pubsub_topic = topic;
+        }
+
+        if let Ok(topic) = env::var(ENV_CHAT_TOPIC) {
+            self.chat.topic = topic;
+        }
+
+        if let Ok(key) = env::var(ENV_SIGNING_KEY) {
+            self.video.signing_key =
+                Some(parse_secret_key(&key).map_err(|e| {
+                    invalid(format!("{} is not a secret key. {}", ENV_SIGNING_KEY, e))
+                })?);
+        }
+
+        Ok(())
+    }
+
+    /// Check fields that would otherwise fail once the actors are running.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.input_socket_addr.port() == 0 {
+            return Err(invalid(
+                "input_socket_addr must have a non-zero port to receive video.".into(),
+            ));
+        }
+
+        if self.archive.concurrency == 0 {
+            return Err(invalid("archive.concurrency must be at least 1.".into()));
+        }
+
+        if self.video.pubsub_topic.trim().is_empty() {
+            return Err(invalid("video.pubsub_topic must not be empty.".into()));
+        }
+
+        if self.chat.topic.trim().is_empty() {
+            return Err(invalid("chat.topic must not be empty.".into()));
+        }
+
+        if self.video.pubsub_topic == self.chat.topic {
+            return Err(invalid(
+                "video.pubsub_topic and chat.topic must be different.".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
     pub async fn save_to_file(&self) -> Result<(), Error> {
         let data = serde_json::to_vec_pretty(&self)?;
 
@@ -56,6 +170,20 @@ impl Configuration {
     }
 }
 
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Parse a hex encoded secp256k1 secret key, with or without 0x prefix.
+fn parse_secret_key(key: &str) -> Result<SecretKey, String> {
+    let key = key.trim();
+    let key = key.strip_prefix("0x").unwrap_or(key);
+
+    let bytes = hex::decode(key).map_err(|e| e.to_string())?;
+
+    SecretKey::parse_slice(&bytes).map_err(|e| format!("{:?}", e))
+}
+
 impl Default for Configuration {
     fn default() -> Self {
         Self {
@@ -63,11 +191,15 @@ impl Default for Configuration {
 
             archive: ArchiveConfig {
                 archive_live_chat: true,
+                concurrency: default_archive_concurrency(),
+                finalize_timeout: default_finalize_timeout(),
             },
 
             video: VideoConfig {
                 pubsub_enable: true,
                 pubsub_topic: "defluencer_live_video".into(),
+                heartbeat_interval: default_heartbeat_interval(),
+                signing_key: None,
             },
 
             chat: ChatConfig {
@@ -78,3 +210,102 @@ impl Default for Configuration {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET_KEY: &str = "0707070707070707070707070707070707070707070707070707070707070707";
+
+    #[test]
+    fn default_is_valid() {
+        assert!(Configuration::default().validate().is_ok());
+    }
+
+    #[test]
+    fn zero_port_rejected() {
+        let mut config = Configuration::default();
+        config.input_socket_addr.set_port(0);
+
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn zero_concurrency_rejected() {
+        let mut config = Configuration::default();
+        config.archive.concurrency = 0;
+
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn empty_topics_rejected() {
+        let mut config = Configuration::default();
+        config.video.pubsub_topic = " ".into();
+
+        assert!(config.validate().is_err());
+
+        let mut config = Configuration::default();
+        config.chat.topic = String::new();
+
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn shared_topic_rejected() {
+        let mut config = Configuration::default();
+        config.chat.topic = config.video.pubsub_topic.clone();
+
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn secret_key_parsing() {
+        assert!(parse_secret_key(SECRET_KEY).is_ok());
+        assert!(parse_secret_key(&format!(" 0x{}\n", SECRET_KEY)).is_ok());
+        assert!(parse_secret_key("0x07").is_err());
+        assert!(parse_secret_key("not hex").is_err());
+    }
+
+    // Environment variables are shared by every test thread, keep them in one test.
+    #[test]
+    fn env_overrides() {
+        let mut config = Configuration::default();
+
+        env::set_var(ENV_INPUT_SOCKET_ADDR, "0.0.0.0:3000");
+        env::set_var(ENV_ARCHIVE_CONCURRENCY, "12");
+        env::set_var(ENV_VIDEO_TOPIC, "video_override");
+        env::set_var(ENV_CHAT_TOPIC, "chat_override");
+        env::set_var(ENV_SIGNING_KEY, SECRET_KEY);
+
+        let result = config.apply_env_overrides();
+
+        assert!(result.is_ok());
+        assert_eq!(config.input_socket_addr.port(), 3000);
+        assert_eq!(config.archive.concurrency, 12);
+        assert_eq!(config.video.pubsub_topic, "video_override");
+        assert_eq!(config.chat.topic, "chat_override");
+        assert!(config.video.signing_key.is_some());
+
+        env::set_var(ENV_ARCHIVE_CONCURRENCY, "many");
+        assert!(Configuration::default().apply_env_overrides().is_err());
+        env::remove_var(ENV_ARCHIVE_CONCURRENCY);
+
+        env::set_var(ENV_INPUT_SOCKET_ADDR, "localhost");
+        assert!(Configuration::default().apply_env_overrides().is_err());
+        env::remove_var(ENV_INPUT_SOCKET_ADDR);
+
+        env::set_var(ENV_SIGNING_KEY, "0x07");
+        assert!(Configuration::default().apply_env_overrides().is_err());
+        env::remove_var(ENV_SIGNING_KEY);
+
+        env::remove_var(ENV_VIDEO_TOPIC);
+        env::remove_var(ENV_CHAT_TOPIC);
+
+        let mut config = Configuration::default();
+
+        assert!(config.apply_env_overrides().is_ok());
+        assert_eq!(config.video.pubsub_topic, "defluencer_live_video");
+        assert!(config.video.signing_key.is_none());
+    }
+}
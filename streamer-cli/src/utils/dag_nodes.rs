@@ -11,6 +11,9 @@ use ipfs_api::IpfsClient;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+#[cfg(debug_assertions)]
+use linked_data::debug_json;
+
 use cid::Cid;
 
 /// Serialize then add dag node to IPFS. Return a CID.
@@ -19,10 +22,7 @@ where
     T: ?Sized + Serialize,
 {
     #[cfg(debug_assertions)]
-    println!(
-        "Serde: Serialize => {}",
-        serde_json::to_string_pretty(node).unwrap()
-    );
+    println!("Serde: Serialize => {}", debug_json(node));
 
     let json_string = serde_json::to_string(node).expect("Serialization failed");
 
@@ -53,10 +53,7 @@ where
     let node = serde_json::from_slice::<T>(&data).expect("Deserialization failed");
 
     #[cfg(debug_assertions)]
-    println!(
-        "Serde: Deserialize => {}",
-        serde_json::to_string_pretty(&node).unwrap()
-    );
+    println!("Serde: Deserialize => {}", debug_json(&node));
 
     Ok(node)
 }
@@ -0,0 +1,118 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::sleep;
+
+use ipfs_api::response::Error;
+use ipfs_api::IpfsClient;
+
+/// Longest wait between two attempts.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Wait for the IPFS daemon to answer, retrying with exponential backoff up to MAX_DELAY.
+pub async fn wait_for_ipfs(ipfs: &IpfsClient, attempts: usize, interval: u64) -> Result<(), Error> {
+    retry_with_backoff(|| ipfs.id(None), attempts, Duration::from_secs(interval))
+        .await
+        .map(|_| ())
+}
+
+/// Call until it succeeds or all attempts failed, returning the last error.
+async fn retry_with_backoff<F, Fut, T>(
+    mut call: F,
+    attempts: usize,
+    interval: Duration,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut delay = interval.min(MAX_DELAY);
+    let mut attempt = 1;
+
+    loop {
+        let error = match call().await {
+            Ok(res) => return Ok(res),
+            Err(e) => e,
+        };
+
+        if attempt >= attempts {
+            return Err(error);
+        }
+
+        eprintln!(
+            "❗ IPFS not ready, retrying in {}s ({}/{})",
+            delay.as_secs(),
+            attempt,
+            attempts
+        );
+
+        sleep(delay).await;
+
+        delay = next_delay(delay);
+        attempt += 1;
+    }
+}
+
+fn next_delay(delay: Duration) -> Duration {
+    delay.checked_mul(2).unwrap_or(MAX_DELAY).min(MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures_util::future::{ready, Ready};
+
+    #[test]
+    fn delay_doubles() {
+        assert_eq!(next_delay(Duration::from_secs(1)), Duration::from_secs(2));
+        assert_eq!(next_delay(Duration::from_secs(4)), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn delay_capped() {
+        assert_eq!(next_delay(Duration::from_secs(16)), MAX_DELAY);
+        assert_eq!(next_delay(MAX_DELAY), MAX_DELAY);
+    }
+
+    #[test]
+    fn many_attempts_never_overflow() {
+        let mut delay = Duration::from_secs(1);
+
+        for _ in 0..1000 {
+            delay = next_delay(delay);
+        }
+
+        assert_eq!(delay, MAX_DELAY);
+        assert_eq!(next_delay(Duration::MAX), MAX_DELAY);
+    }
+
+    /// Fails the first calls, like a daemon still starting.
+    fn starting_daemon(failures: usize) -> impl FnMut() -> Ready<Result<usize, Error>> {
+        let mut calls = 0;
+
+        move || {
+            calls += 1;
+
+            if calls <= failures {
+                ready(Err(Error::Uncategorized("Connection refused".into())))
+            } else {
+                ready(Ok(calls))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn connected_after_failures() {
+        let result = retry_with_backoff(starting_daemon(2), 5, Duration::from_millis(1)).await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_last_attempt() {
+        let result = retry_with_backoff(starting_daemon(3), 3, Duration::from_millis(1)).await;
+
+        assert!(result.is_err());
+    }
+}
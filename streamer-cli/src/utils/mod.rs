@@ -1,2 +1,3 @@
 pub mod config;
 pub mod dag_nodes;
+pub mod ipfs;
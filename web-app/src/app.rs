@@ -1,19 +1,30 @@
-use crate::pages::{Home, Live, Settings, Video, Videos};
+use crate::pages::{Home, Live, Play, Settings, Video, Videos};
 use crate::utils::ipfs::IpfsService;
-use crate::utils::web3::Web3Service;
+use crate::utils::web3::{add_provider_listener, network_name, ProviderListener, Web3Service};
+
+use wasm_bindgen_futures::spawn_local;
 
 use yew::prelude::{html, Component, ComponentLink, Html, ShouldRender};
+use yew::services::ConsoleService;
 use yew_router::prelude::{Router, Switch};
 
 use cid::Cid;
 
+use web3::types::U256;
+
 pub const ENS_NAME: &str = "sionois";
 
+/// GossipSub topic where content reports are sent to moderators.
+pub const REPORT_TOPIC: &str = "defluencer_reports";
+
 #[derive(Switch, Debug, Clone)]
 pub enum AppRoute {
     #[to = "/#/video/{cid}"]
     Video(Cid),
 
+    #[to = "/#/play/{cid}"]
+    Play(Cid),
+
     #[to = "/#/settings"]
     Settings,
 
@@ -28,23 +39,57 @@ pub enum AppRoute {
 }
 
 pub struct App {
+    link: ComponentLink<Self>,
+
     web3: Web3Service,
     ipfs: IpfsService,
+
+    /// Incremented when the provider changes, Live and Videos pages are recreated to resolve names again.
+    generation: usize,
+    network: Option<String>,
+
+    _listeners: Vec<ProviderListener>,
+}
+
+pub enum Msg {
+    ProviderChanged,
+    ChainId(Result<U256, web3::Error>),
 }
 
 impl Component for App {
-    type Message = ();
+    type Message = Msg;
     type Properties = ();
 
-    fn create(_props: Self::Properties, _: ComponentLink<Self>) -> Self {
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let web3 = Web3Service::new().unwrap();
         let ipfs = IpfsService::new();
 
-        Self { web3, ipfs }
+        let _listeners = ["chainChanged", "accountsChanged"]
+            .iter()
+            .filter_map(|event| {
+                add_provider_listener(event, link.callback(|_| Msg::ProviderChanged))
+            })
+            .collect();
+
+        let app = Self {
+            link,
+            web3,
+            ipfs,
+            generation: 0,
+            network: None,
+            _listeners,
+        };
+
+        app.get_chain_id();
+
+        app
     }
 
-    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
-        false
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::ProviderChanged => self.on_provider_changed(),
+            Msg::ChainId(result) => self.on_chain_id(result),
+        }
     }
 
     fn change(&mut self, _props: Self::Properties) -> ShouldRender {
@@ -54,16 +99,24 @@ impl Component for App {
     fn view(&self) -> Html {
         let web3 = self.web3.clone();
         let ipfs = self.ipfs.clone();
+        let key = self.generation.to_string();
 
         html! {
             <>
+                {
+                    match self.network.as_ref() {
+                        Some(network) => html! { <div class="network">{ network }</div> },
+                        None => html! {},
+                    }
+                }
                 <Router<AppRoute>
                     render = Router::render(move |switch: AppRoute| {
                         match switch {
-                            AppRoute::Video(cid) => html! { <Video ipfs=ipfs.clone() metadata_cid=cid /> },
+                            AppRoute::Video(cid) => html! { <Video ipfs=ipfs.clone() web3=web3.clone() metadata_cid=cid /> },
+                            AppRoute::Play(cid) => html! { <Play ipfs=ipfs.clone() root_cid=cid /> },
                             AppRoute::Settings => html! { <Settings /> },
-                            AppRoute::Live => html! { <Live ipfs=ipfs.clone() web3=web3.clone() /> },
-                            AppRoute::Videos => html! { <Videos ipfs=ipfs.clone() web3=web3.clone() /> },
+                            AppRoute::Live => html! { <Live key=key.clone() ipfs=ipfs.clone() web3=web3.clone() /> },
+                            AppRoute::Videos => html! { <Videos key=key.clone() ipfs=ipfs.clone() web3=web3.clone() /> },
                             AppRoute::Home => html! { <Home /> },
                         }
                     })
@@ -72,3 +125,42 @@ impl Component for App {
         }
     }
 }
+
+impl App {
+    fn get_chain_id(&self) {
+        let cb = self.link.callback_once(Msg::ChainId);
+        let web3 = self.web3.clone();
+
+        spawn_local(async move { cb.emit(web3.get_chain_id().await) });
+    }
+
+    /// Callback when the user switch network or account.
+    fn on_provider_changed(&mut self) -> bool {
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Provider Changed");
+
+        match Web3Service::new() {
+            Ok(web3) => self.web3 = web3,
+            Err(e) => ConsoleService::error(&format!("{:?}", e)),
+        }
+
+        self.generation += 1;
+        self.get_chain_id();
+
+        true
+    }
+
+    fn on_chain_id(&mut self, result: Result<U256, web3::Error>) -> bool {
+        let chain_id = match result {
+            Ok(id) => id,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return false;
+            }
+        };
+
+        self.network = Some(network_name(chain_id.low_u64()));
+
+        true
+    }
+}
@@ -2,13 +2,15 @@ use std::collections::VecDeque;
 use std::rc::Rc;
 use std::str;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-use crate::components::chat::message::{MessageData, UIMessage};
+use crate::components::chat::message::{MessageData, Role, UIMessage};
 use crate::utils::ipfs::{IpfsService, PubsubSubResponse};
 
 use wasm_bindgen_futures::spawn_local;
 
 use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::interval::{IntervalService, IntervalTask};
 use yew::services::ConsoleService;
 
 use cid::Cid;
@@ -16,12 +18,17 @@ use cid::Cid;
 use linked_data::chat::{ChatId, Message, MessageType, UnsignedMessage};
 use linked_data::moderation::{Ban, Bans, ChatModerationCache, Moderators};
 use linked_data::signature::SignedMessage;
-use linked_data::PeerId;
+use linked_data::{Address, PeerId};
 
 use reqwest::Error;
 
+use web3::types::H160;
+
 use blockies::Ethereum;
 
+/// Delay between moderation list updates.
+const MODERATION_REFRESH: Duration = Duration::from_secs(60);
+
 pub struct Display {
     link: ComponentLink<Self>,
 
@@ -32,6 +39,13 @@ pub struct Display {
 
     bans: Option<Bans>,
     mods: Option<Moderators>,
+    streamer: Option<Address>,
+
+    ban_list: Rc<str>,
+    mod_list: Rc<str>,
+    bans_cid: Option<Cid>,
+    mods_cid: Option<Cid>,
+    _refresh_task: IntervalTask,
 
     next_id: usize,
     chat_messages: VecDeque<MessageData>,
@@ -45,6 +59,7 @@ pub enum Msg {
     Origin((PeerId, Message, Result<SignedMessage<ChatId>, Error>)),
     BanList(Result<(Cid, Bans), Error>),
     ModList(Result<(Cid, Moderators), Error>),
+    RefreshLists,
 }
 
 #[derive(Properties, Clone)]
@@ -53,6 +68,10 @@ pub struct Props {
     pub topic: Rc<str>,
     pub ban_list: Rc<str>,
     pub mod_list: Rc<str>,
+
+    /// Address shown with a streamer badge.
+    #[prop_or_default]
+    pub streamer: Option<Address>,
 }
 
 impl Component for Display {
@@ -65,6 +84,7 @@ impl Component for Display {
             topic,
             ban_list,
             mod_list,
+            streamer,
         } = props;
 
         let client = ipfs.clone();
@@ -76,17 +96,8 @@ impl Component for Display {
 
         spawn_local(async move { client.pubsub_sub(sub_topic, cb, sig).await });
 
-        let cb = link.callback_once(Msg::BanList);
-        let client = ipfs.clone();
-        let ipns = ban_list.to_string();
-
-        spawn_local(async move { cb.emit(client.resolve_and_dag_get(ipns).await) });
-
-        let cb = link.callback_once(Msg::ModList);
-        let client = ipfs.clone();
-        let ipns = mod_list.to_string();
-
-        spawn_local(async move { cb.emit(client.resolve_and_dag_get(ipns).await) });
+        let _refresh_task =
+            IntervalService::spawn(MODERATION_REFRESH, link.callback(|_| Msg::RefreshLists));
 
         //https://github.com/ethereum/blockies
         //https://docs.rs/blockies/0.3.0/blockies/struct.Ethereum.html
@@ -98,7 +109,7 @@ impl Component for Display {
             spot_color: None,
         };
 
-        Self {
+        let mut display = Self {
             link,
 
             ipfs,
@@ -108,12 +119,23 @@ impl Component for Display {
 
             bans: None,
             mods: None,
+            streamer,
+
+            ban_list,
+            mod_list,
+            bans_cid: None,
+            mods_cid: None,
+            _refresh_task,
 
             chat_messages: VecDeque::with_capacity(20),
             next_id: 0,
 
             drop_sig,
-        }
+        };
+
+        display.refresh_lists();
+
+        display
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
@@ -122,11 +144,18 @@ impl Component for Display {
             Msg::Origin((peer, msg, result)) => self.on_signed_msg(peer, msg, result),
             Msg::BanList(result) => self.on_ban_list_resolved(result),
             Msg::ModList(result) => self.on_mod_list_resolved(result),
+            Msg::RefreshLists => self.refresh_lists(),
         }
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        false
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.streamer == props.streamer {
+            return false;
+        }
+
+        self.streamer = props.streamer;
+
+        self.update_roles()
     }
 
     fn view(&self) -> Html {
@@ -216,7 +245,22 @@ impl Display {
         #[cfg(debug_assertions)]
         ConsoleService::info("Signed Message Received");
 
-        let trusted = sign_msg.verify();
+        // A valid signature from another address is an impersonation attempt.
+        let trusted = match sign_msg.recover_address() {
+            Some(signer) if signer == sign_msg.address => true,
+            Some(signer) => {
+                ConsoleService::warn(&format!(
+                    "Signer Mismatch => claimed {:?} actual {:?}",
+                    H160::from(sign_msg.address),
+                    H160::from(signer)
+                ));
+                false
+            }
+            None => {
+                ConsoleService::warn("Invalid Signature");
+                false
+            }
+        };
 
         self.mod_db.add_peer(
             &sign_msg.data.peer,
@@ -264,16 +308,26 @@ impl Display {
         #[cfg(debug_assertions)]
         ConsoleService::info(&format!("Message => {}", &msg.message));
 
-        let address = self.mod_db.get_address(peer).unwrap();
+        let address = *self.mod_db.get_address(peer).unwrap();
         let name = self.mod_db.get_name(peer).unwrap();
 
         let mut data = Vec::new();
 
         self.img_gen
-            .create_icon(&mut data, address)
+            .create_icon(&mut data, &address)
             .expect("Invalid Blocky");
 
-        let msg_data = MessageData::new(self.next_id, &data, &name, &msg.message);
+        let role = role_of(self.streamer.as_ref(), self.mods.as_ref(), &address);
+
+        let msg_data = MessageData::new(
+            self.next_id,
+            peer,
+            address,
+            role,
+            &data,
+            &name,
+            &msg.message,
+        );
 
         self.chat_messages.push_back(msg_data);
 
@@ -304,39 +358,187 @@ impl Display {
         false
     }
 
+    /// Resolve the moderation lists again, moderators may have updated them.
+    fn refresh_lists(&mut self) -> bool {
+        let cb = self.link.callback_once(Msg::BanList);
+        let client = self.ipfs.clone();
+        let ipns = self.ban_list.to_string();
+
+        spawn_local(async move { cb.emit(client.resolve_and_dag_get(ipns).await) });
+
+        let cb = self.link.callback_once(Msg::ModList);
+        let client = self.ipfs.clone();
+        let ipns = self.mod_list.to_string();
+
+        spawn_local(async move { cb.emit(client.resolve_and_dag_get(ipns).await) });
+
+        false
+    }
+
     /// Callback when IPFS dag get ban list node.
     fn on_ban_list_resolved(&mut self, result: Result<(Cid, Bans), Error>) -> bool {
-        let bans = match result {
-            Ok((_, bans)) => bans,
+        let (cid, bans) = match result {
+            Ok((cid, bans)) => (cid, bans),
             Err(e) => {
                 ConsoleService::error(&format!("{:?}", e));
                 return false;
             }
         };
 
+        if self.bans_cid == Some(cid) {
+            return false;
+        }
+
         #[cfg(debug_assertions)]
         ConsoleService::info("Chat Ban List Received");
 
+        // The complete list is swapped in at once then applied to known peers and displayed messages.
+        let hidden = hide_banned(&mut self.mod_db, &mut self.chat_messages, &bans);
+
         self.bans = Some(bans);
+        self.bans_cid = Some(cid);
 
-        false
+        hidden
     }
 
     /// Callback when IPFS dag get mod list node.
     fn on_mod_list_resolved(&mut self, result: Result<(Cid, Moderators), Error>) -> bool {
-        let mods = match result {
-            Ok((_, mods)) => mods,
+        let (cid, mods) = match result {
+            Ok((cid, mods)) => (cid, mods),
             Err(e) => {
                 ConsoleService::error(&format!("{:?}", e));
                 return false;
             }
         };
 
+        if self.mods_cid == Some(cid) {
+            return false;
+        }
+
         #[cfg(debug_assertions)]
         ConsoleService::info("Chat Moderator List Received");
 
         self.mods = Some(mods);
+        self.mods_cid = Some(cid);
 
-        false
+        self.update_roles()
+    }
+
+    /// Recompute badges of displayed messages, returns true if any changed.
+    fn update_roles(&mut self) -> bool {
+        let mut changed = false;
+
+        for i in 0..self.chat_messages.len() {
+            let role = role_of(
+                self.streamer.as_ref(),
+                self.mods.as_ref(),
+                &self.chat_messages[i].address,
+            );
+
+            let msg = &mut self.chat_messages[i];
+
+            if msg.role != role {
+                msg.role = role;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// Badge of this address, the streamer first then moderators.
+fn role_of(
+    streamer: Option<&Address>,
+    mods: Option<&Moderators>,
+    address: &Address,
+) -> Option<Role> {
+    if streamer == Some(address) {
+        return Some(Role::Streamer);
+    }
+
+    match mods {
+        Some(mods) if mods.mods.contains(address) => Some(Role::Moderator),
+        _ => None,
+    }
+}
+
+/// Ban peers using a banned address and hide their messages. Returns true if any were hidden.
+fn hide_banned(
+    mod_db: &mut ChatModerationCache,
+    messages: &mut VecDeque<MessageData>,
+    bans: &Bans,
+) -> bool {
+    mod_db.ban_addresses(&bans.banned);
+
+    let count = messages.len();
+
+    messages.retain(|msg| !mod_db.is_banned(&msg.peer));
+
+    count != messages.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: usize, peer: &str, address: Address) -> MessageData {
+        MessageData::new(id, peer, address, None, &[], peer, "hello")
+    }
+
+    #[test]
+    fn updated_ban_list_hides_shown_messages() {
+        let (alice, bob) = ([1u8; 20], [2u8; 20]);
+
+        let mut mod_db = ChatModerationCache::new(4, 0);
+        mod_db.add_peer("alice", Cid::default(), alice, None);
+        mod_db.add_peer("bob", Cid::default(), bob, None);
+
+        let mut messages: VecDeque<MessageData> = vec![
+            message(0, "alice", alice),
+            message(1, "bob", bob),
+            message(2, "alice", alice),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut bans = Bans::default();
+
+        assert!(!hide_banned(&mut mod_db, &mut messages, &bans));
+        assert_eq!(messages.len(), 3);
+
+        // Moderators updated the list.
+        bans.banned.insert(alice);
+
+        assert!(hide_banned(&mut mod_db, &mut messages, &bans));
+
+        let ids: Vec<usize> = messages.iter().map(|msg| msg.id).collect();
+
+        assert_eq!(ids, vec![1]);
+        assert!(mod_db.is_banned("alice"));
+        assert!(!mod_db.is_banned("bob"));
+    }
+
+    #[test]
+    fn mod_listed_address_gets_mod_badge() {
+        let (streamer, moderator, viewer) = ([1u8; 20], [2u8; 20], [3u8; 20]);
+
+        let mut mods = Moderators::default();
+
+        assert_eq!(role_of(Some(&streamer), Some(&mods), &moderator), None);
+
+        // List updated while chatting.
+        mods.mods.insert(moderator);
+
+        assert_eq!(
+            role_of(Some(&streamer), Some(&mods), &moderator),
+            Some(Role::Moderator)
+        );
+        assert_eq!(
+            role_of(Some(&streamer), Some(&mods), &streamer),
+            Some(Role::Streamer)
+        );
+        assert_eq!(role_of(Some(&streamer), Some(&mods), &viewer), None);
+        assert_eq!(role_of(None, None, &moderator), None);
     }
 }
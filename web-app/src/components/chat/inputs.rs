@@ -27,6 +27,9 @@ use reqwest::Error;
 
 const SIGN_MSG_KEY: &str = "signed_message";
 
+/// Default maximum number of characters per chat message.
+const DEFAULT_MAX_LENGTH: usize = 500;
+
 enum DisplayState {
     Connect,
     NameOk(String),
@@ -45,6 +48,10 @@ pub struct Inputs {
     web3: Web3Service,
 
     temp_msg: Option<String>,
+    feedback: Option<String>,
+
+    max_length: usize,
+    filter_content: bool,
 
     address: Option<Address>,
     peer_id: Option<String>,
@@ -74,6 +81,14 @@ pub struct Props {
     pub ipfs: IpfsService,
     pub web3: Web3Service,
     pub topic: Rc<str>,
+
+    /// Maximum number of characters per message.
+    #[prop_or(DEFAULT_MAX_LENGTH)]
+    pub max_length: usize,
+
+    /// Strip control characters before sending.
+    #[prop_or(true)]
+    pub filter_content: bool,
 }
 
 impl Component for Inputs {
@@ -81,7 +96,13 @@ impl Component for Inputs {
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let Props { ipfs, web3, topic } = props;
+        let Props {
+            ipfs,
+            web3,
+            topic,
+            max_length,
+            filter_content,
+        } = props;
 
         let window = web_sys::window().expect("Can't get window");
         let storage = get_local_storage(&window);
@@ -103,6 +124,10 @@ impl Component for Inputs {
             web3,
 
             temp_msg: None,
+            feedback: None,
+
+            max_length,
+            filter_content,
 
             address: None,
             peer_id: None,
@@ -145,6 +170,12 @@ impl Component for Inputs {
                     placeholder="Input text here...">
                     </textarea>
                     <button class="send_button" onclick=self.link.callback(|_| Msg::Enter)>{ "Send" }</button>
+                    {
+                        match self.feedback.as_ref() {
+                            Some(feedback) => html! { <div class="input_feedback">{ feedback }</div> },
+                            None => html! {},
+                        }
+                    }
                 </div> }
             }
             DisplayState::Connect => {
@@ -220,6 +251,17 @@ impl Inputs {
             None => return false,
         };
 
+        let message = match sanitize_message(&message, self.max_length, self.filter_content) {
+            Ok(msg) => msg,
+            Err(feedback) => {
+                self.temp_msg = Some(message);
+                self.feedback = Some(feedback);
+                return true;
+            }
+        };
+
+        let render = self.feedback.take().is_some();
+
         if let Some(text_area) = self.text_area.as_ref() {
             text_area.set_value("");
         }
@@ -247,7 +289,7 @@ impl Inputs {
             let _ = client.pubsub_pub(topic, json_string).await;
         });
 
-        false
+        render
     }
 
     /// Trigger ethereum request accounts.
@@ -408,3 +450,60 @@ impl Inputs {
         true
     }
 }
+
+/// Enforce message limits, returns the message to send or a feedback message.
+fn sanitize_message(message: &str, max_length: usize, filter: bool) -> Result<String, String> {
+    let message = if filter {
+        message.chars().filter(|c| !c.is_control()).collect()
+    } else {
+        message.to_owned()
+    };
+
+    let message = message.trim();
+
+    if message.is_empty() {
+        return Err("Message is empty.".to_owned());
+    }
+
+    let length = message.chars().count();
+
+    if length > max_length {
+        return Err(format!(
+            "Message too long ({} / {} characters).",
+            length, max_length
+        ));
+    }
+
+    Ok(message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_at_limit_accepted() {
+        assert_eq!(sanitize_message("héllo", 5, true), Ok("héllo".to_owned()));
+    }
+
+    #[test]
+    fn message_over_limit_rejected() {
+        assert!(sanitize_message("hello!", 5, true).is_err());
+    }
+
+    #[test]
+    fn empty_message_rejected() {
+        assert!(sanitize_message("", 5, true).is_err());
+        assert!(sanitize_message("   ", 5, true).is_err());
+        assert!(sanitize_message("\u{7}\n", 5, true).is_err());
+    }
+
+    #[test]
+    fn control_characters_filtered() {
+        assert_eq!(sanitize_message(" a\u{7}b ", 5, true), Ok("ab".to_owned()));
+        assert_eq!(
+            sanitize_message("a\u{7}b", 5, false),
+            Ok("a\u{7}b".to_owned())
+        );
+    }
+}
@@ -4,24 +4,46 @@ use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRende
 
 use yewtil::NeqAssign;
 
+use linked_data::Address;
+
 use cid::multibase::Base;
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    Streamer,
+    Moderator,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MessageData {
     pub id: usize,
+    pub peer: Rc<str>,
+    pub address: Address,
+    pub role: Option<Role>,
     img_data: Rc<str>,
     sender_name: Rc<str>,
     message: Rc<str>,
 }
 
 impl MessageData {
-    pub fn new(id: usize, img_data: &[u8], name: &str, message: &str) -> Self {
+    pub fn new(
+        id: usize,
+        peer: &str,
+        address: Address,
+        role: Option<Role>,
+        img_data: &[u8],
+        name: &str,
+        message: &str,
+    ) -> Self {
         let base = Base::Base64;
         let encoded = base.encode(img_data);
         let url = format!("data:image/png;base64,{}", encoded);
 
         Self {
             id,
+            peer: Rc::from(peer),
+            address,
+            role,
             img_data: Rc::from(url),
             sender_name: Rc::from(name),
             message: Rc::from(message),
@@ -32,6 +54,7 @@ impl MessageData {
         html! {
             <div class="chat_message">
                 <img src=self.img_data height="32" width="32" />
+                { view_badge(self.role) }
                 <h3>{ &self.sender_name }</h3>
                 <p>{ &self.message }</p>
             </div>
@@ -64,3 +87,24 @@ impl Component for UIMessage {
         self.message_data.render()
     }
 }
+
+fn view_badge(role: Option<Role>) -> Html {
+    match role {
+        Some(Role::Streamer) => html! { <span class="chat_badge">{ "Streamer" }</span> },
+        Some(Role::Moderator) => html! { <span class="chat_badge">{ "Mod" }</span> },
+        None => html! {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moderator_badge_rendered() {
+        let expected = html! { <span class="chat_badge">{ "Mod" }</span> };
+
+        assert_eq!(view_badge(Some(Role::Moderator)), expected);
+        assert_eq!(view_badge(None), html! {});
+    }
+}
@@ -8,10 +8,13 @@ use crate::utils::web3::Web3Service;
 
 use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
 
+use linked_data::Address;
+
 pub struct ChatWindow {
     topic: Rc<str>,
     ban_list: Rc<str>,
     mod_list: Rc<str>,
+    streamer: Option<Address>,
     web3: Web3Service,
     ipfs: IpfsService,
 }
@@ -23,6 +26,10 @@ pub struct Props {
     pub topic: Rc<str>,
     pub ban_list: Rc<str>,
     pub mod_list: Rc<str>,
+
+    /// Address of the streamer, messages they sign get a badge.
+    #[prop_or_default]
+    pub streamer: Option<Address>,
 }
 
 impl Component for ChatWindow {
@@ -36,12 +43,14 @@ impl Component for ChatWindow {
             topic,
             ban_list,
             mod_list,
+            streamer,
         } = props;
 
         Self {
             topic,
             ban_list,
             mod_list,
+            streamer,
             web3,
             ipfs,
         }
@@ -51,14 +60,20 @@ impl Component for ChatWindow {
         false
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        false
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.streamer == props.streamer {
+            return false;
+        }
+
+        self.streamer = props.streamer;
+
+        true
     }
 
     fn view(&self) -> Html {
         html! {
         <div class="chat_window">
-            <Display ipfs=self.ipfs.clone() topic=self.topic.clone() ban_list=self.ban_list.clone() mod_list=self.mod_list.clone() />
+            <Display ipfs=self.ipfs.clone() topic=self.topic.clone() ban_list=self.ban_list.clone() mod_list=self.mod_list.clone() streamer=self.streamer />
             <Inputs ipfs=self.ipfs.clone() topic=self.topic.clone() web3=self.web3.clone() />
         </div>
         }
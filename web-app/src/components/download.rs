@@ -0,0 +1,431 @@
+use crate::components::seconds_to_timecode;
+use crate::utils::bitrate::{format_bitrate, BitrateUnit};
+use crate::utils::cancel::CancelToken;
+use crate::utils::ipfs::IpfsService;
+use crate::utils::timer::sleep;
+
+use super::video_player::SETUP_PATH;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::ConsoleService;
+use yew::Callback;
+
+use linked_data::video::{SetupNode, Track, VideoMetadata};
+
+use reqwest::Error;
+
+/// Milliseconds before the object URL of a saved file is revoked.
+/// Revoking right after the click can cancel the download in some browsers.
+const REVOKE_DELAY: i32 = 60_000;
+
+enum DisplayState {
+    Idle,
+    Loading,
+    Confirm,
+    Downloading(usize),
+    Done,
+    Failed,
+}
+
+/// Download the highest quality variant of a video as audio and video files.
+pub struct DownloadButton {
+    link: ComponentLink<Self>,
+
+    ipfs: IpfsService,
+    metadata: VideoMetadata,
+    cancel: CancelToken,
+
+    state: DisplayState,
+    setup: Option<SetupNode>,
+}
+
+pub enum Msg {
+    Open,
+    Setup(Result<SetupNode, Error>),
+    Start,
+    Cancel,
+    Progress(usize),
+    Done(Result<(Vec<u8>, Vec<u8>), Error>),
+    Reset,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub ipfs: IpfsService,
+    pub metadata: VideoMetadata,
+}
+
+impl Component for DownloadButton {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let Props { ipfs, metadata } = props;
+
+        Self {
+            link,
+
+            ipfs,
+            metadata,
+            cancel: CancelToken::new(),
+
+            state: DisplayState::Idle,
+            setup: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Open => self.on_open(),
+            Msg::Setup(result) => self.on_setup(result),
+            Msg::Start => self.on_start(),
+            Msg::Cancel => self.on_cancel(),
+            Msg::Progress(count) => self.on_progress(count),
+            Msg::Done(result) => self.on_done(result),
+            Msg::Reset => self.on_cancel(),
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let content = match self.state {
+            DisplayState::Idle => {
+                html! { <button class="download_button" onclick=self.link.callback(|_| Msg::Open)>{ "Download" }</button> }
+            }
+            DisplayState::Loading => {
+                html! { <div class="download_status">{ "Loading tracks..." }</div> }
+            }
+            DisplayState::Confirm => html! {
+                <div class="download_form">
+                    <div class="download_status">{ self.estimate() }</div>
+                    <button class="submit_button" onclick=self.link.callback(|_| Msg::Start)>{ "Download" }</button>
+                    <button class="cancel_button" onclick=self.link.callback(|_| Msg::Cancel)>{ "Cancel" }</button>
+                </div>
+            },
+            DisplayState::Downloading(count) => html! {
+                <div class="download_form">
+                    <div class="download_status">{ format!("Downloading {} / {} segments", count, segment_count(self.metadata.duration)) }</div>
+                    <button class="cancel_button" onclick=self.link.callback(|_| Msg::Cancel)>{ "Cancel" }</button>
+                </div>
+            },
+            DisplayState::Done => html! {
+                <div class="download_form">
+                    <div class="download_status">{ "Download complete." }</div>
+                    <button class="cancel_button" onclick=self.link.callback(|_| Msg::Reset)>{ "Close" }</button>
+                </div>
+            },
+            DisplayState::Failed => html! {
+                <div class="download_form">
+                    <div class="download_status">{ "Download failed." }</div>
+                    <button class="submit_button" onclick=self.link.callback(|_| Msg::Open)>{ "Retry" }</button>
+                    <button class="cancel_button" onclick=self.link.callback(|_| Msg::Reset)>{ "Close" }</button>
+                </div>
+            },
+        };
+
+        html! {
+            <div class="download">
+            { content }
+            </div>
+        }
+    }
+
+    fn destroy(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+impl DownloadButton {
+    /// Fetch setup node to find the highest quality track.
+    fn on_open(&mut self) -> bool {
+        let cb = self.link.callback_once(Msg::Setup);
+        let client = self.ipfs.clone();
+        let cid = self.metadata.video.link;
+
+        self.cancel
+            .spawn(async move { cb.emit(client.dag_get(cid, Some(SETUP_PATH)).await) });
+
+        self.state = DisplayState::Loading;
+
+        true
+    }
+
+    fn on_setup(&mut self, result: Result<SetupNode, Error>) -> bool {
+        match result {
+            Ok(setup)
+                if default_audio_track(&setup).is_some()
+                    && highest_video_track(&setup).is_some() =>
+            {
+                self.setup = Some(setup);
+                self.state = DisplayState::Confirm;
+            }
+            Ok(_) => self.state = DisplayState::Failed,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                self.state = DisplayState::Failed;
+            }
+        }
+
+        true
+    }
+
+    /// Size warning based on duration and bitrate.
+    fn estimate(&self) -> String {
+        let setup = match self.setup.as_ref() {
+            Some(setup) => setup,
+            None => return String::new(),
+        };
+
+        let audio = match default_audio_track(setup) {
+            Some(track) => track,
+            None => return String::new(),
+        };
+        let video = match highest_video_track(setup) {
+            Some(track) => track,
+            None => return String::new(),
+        };
+
+        let bits = (audio.bandwidth + video.bandwidth) as f64 * self.metadata.duration;
+        let megabytes = bits / 8.0 / 1_000_000.0;
+
+        format!(
+            "{} ({}) about {:.0} MB",
+            video.name,
+            format_bitrate(video.bandwidth, BitrateUnit::Bits),
+            megabytes
+        )
+    }
+
+    /// Fetch initialization then every media segment in order.
+    fn on_start(&mut self) -> bool {
+        let setup = match self.setup.as_ref() {
+            Some(setup) => setup,
+            None => return false,
+        };
+
+        let audio = match default_audio_track(setup) {
+            Some(track) => track.clone(),
+            None => return false,
+        };
+        let video = match highest_video_track(setup) {
+            Some(track) => track.clone(),
+            None => return false,
+        };
+
+        let root = self.metadata.video.link.to_string();
+        let count = segment_count(self.metadata.duration);
+
+        let progress = self.link.callback(Msg::Progress);
+        let cb = self.link.callback_once(Msg::Done);
+        let client = self.ipfs.clone();
+
+        self.cancel.spawn(async move {
+            let result = download(client, audio, video, root, count, progress).await;
+
+            cb.emit(result)
+        });
+
+        self.state = DisplayState::Downloading(0);
+
+        true
+    }
+
+    fn on_cancel(&mut self) -> bool {
+        self.cancel.cancel();
+        self.cancel = CancelToken::new();
+
+        self.state = DisplayState::Idle;
+
+        true
+    }
+
+    fn on_progress(&mut self, count: usize) -> bool {
+        if let DisplayState::Downloading(_) = self.state {
+            self.state = DisplayState::Downloading(count);
+            return true;
+        }
+
+        false
+    }
+
+    fn on_done(&mut self, result: Result<(Vec<u8>, Vec<u8>), Error>) -> bool {
+        let (audio, video) = match result {
+            Ok(data) => data,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                self.state = DisplayState::Failed;
+                return true;
+            }
+        };
+
+        let title = &self.metadata.title;
+
+        let saved = save_file(&audio, &format!("{}_audio.mp4", title))
+            .and_then(|_| save_file(&video, &format!("{}_video.mp4", title)));
+
+        self.state = match saved {
+            Ok(()) => DisplayState::Done,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                DisplayState::Failed
+            }
+        };
+
+        true
+    }
+}
+
+/// Default audio track, the first one like the player.
+fn default_audio_track(setup: &SetupNode) -> Option<&Track> {
+    setup.tracks.iter().find(|track| track.is_audio())
+}
+
+/// Video tracks are ordered by quality, alternate audio tracks can follow them.
+fn highest_video_track(setup: &SetupNode) -> Option<&Track> {
+    setup.tracks.iter().rev().find(|track| !track.is_audio())
+}
+
+/// Number of 1 second segments covering the full duration.
+fn segment_count(duration: f64) -> usize {
+    duration.max(0.0).ceil() as usize
+}
+
+/// Time tree path of the tracks of the segment starting at this second.
+fn segment_path(root: &str, second: usize) -> String {
+    let (hours, minutes, seconds) = seconds_to_timecode(second as f64);
+
+    format!(
+        "{}/time/hour/{}/minute/{}/second/{}/video/track",
+        root, hours, minutes, seconds
+    )
+}
+
+async fn download(
+    client: IpfsService,
+    audio_track: Track,
+    video_track: Track,
+    root: String,
+    count: usize,
+    progress: Callback<usize>,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let audio_init = audio_track.initialization_segment.link.to_string();
+    let video_init = video_track.initialization_segment.link.to_string();
+
+    let (mut audio, mut video) = client
+        .uncached_double_path_cat(&audio_init, &video_init)
+        .await?;
+
+    for i in 0..count {
+        let path = segment_path(&root, i);
+
+        let audio_path = format!("{}/{}", path, audio_track.name);
+        let video_path = format!("{}/{}", path, video_track.name);
+
+        let (aud_seg, vid_seg) = client
+            .uncached_double_path_cat(&audio_path, &video_path)
+            .await?;
+
+        audio.extend_from_slice(&aud_seg);
+        video.extend_from_slice(&vid_seg);
+
+        progress.emit(i + 1);
+    }
+
+    Ok((audio, video))
+}
+
+/// Prompt the browser to save data as a file.
+fn save_file(data: &[u8], file_name: &str) -> Result<(), wasm_bindgen::JsValue> {
+    let parts = js_sys::Array::new();
+    parts.push(&js_sys::Uint8Array::from(data));
+
+    let mut options = BlobPropertyBag::new();
+    options.type_("video/mp4");
+
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let window = web_sys::window().expect("Can't get window");
+    let document = window.document().expect("Can't get document");
+
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    spawn_local(async move {
+        sleep(REVOKE_DELAY).await;
+
+        if let Err(e) = Url::revoke_object_url(&url) {
+            ConsoleService::error(&format!("{:?}", e));
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(names: &[&str]) -> SetupNode {
+        let tracks = names
+            .iter()
+            .map(|name| Track {
+                name: (*name).to_owned(),
+                codec: String::new(),
+                initialization_segment: Default::default(),
+                bandwidth: 0,
+            })
+            .collect();
+
+        SetupNode { tracks }
+    }
+
+    #[test]
+    fn highest_video_skips_trailing_audio() {
+        let setup = setup(&["audio", "360p30", "720p30", "audio_fr"]);
+
+        assert_eq!(highest_video_track(&setup).unwrap().name, "720p30");
+        assert_eq!(default_audio_track(&setup).unwrap().name, "audio");
+    }
+
+    #[test]
+    fn segments_cover_full_duration() {
+        assert_eq!(segment_count(0.0), 0);
+        assert_eq!(segment_count(-5.0), 0);
+        assert_eq!(segment_count(59.2), 60);
+        assert_eq!(segment_count(3600.0), 3600);
+    }
+
+    #[test]
+    fn segment_paths_follow_time_tree() {
+        let paths: Vec<String> = (0..segment_count(3601.0))
+            .map(|i| segment_path("root", i))
+            .collect();
+
+        assert_eq!(paths.len(), 3601);
+        assert_eq!(paths[0], "root/time/hour/0/minute/0/second/0/video/track");
+        assert_eq!(paths[59], "root/time/hour/0/minute/0/second/59/video/track");
+        assert_eq!(paths[60], "root/time/hour/0/minute/1/second/0/video/track");
+        assert_eq!(
+            paths[3600],
+            "root/time/hour/1/minute/0/second/0/video/track"
+        );
+    }
+
+    #[test]
+    fn audio_only_has_no_video() {
+        let setup = setup(&["audio", "audio_fr"]);
+
+        assert!(highest_video_track(&setup).is_none());
+    }
+}
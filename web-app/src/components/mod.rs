@@ -1,9 +1,13 @@
 mod chat;
+mod download;
 mod navbar;
+mod report;
 mod video_player;
 mod video_thumbnail;
 
 pub use chat::ChatWindow;
+pub use download::DownloadButton;
 pub use navbar::Navbar;
-pub use video_player::{seconds_to_timecode, VideoPlayer};
-pub use video_thumbnail::VideoThumbnail;
+pub use report::ReportButton;
+pub use video_player::{seconds_to_timecode, PlaybackMode, VideoPlayer, SETUP_PATH};
+pub use video_thumbnail::{view_engagement, VideoThumbnail};
@@ -0,0 +1,379 @@
+use crate::app::REPORT_TOPIC;
+use crate::utils::ipfs::IpfsService;
+use crate::utils::local_storage::{get_f64, get_local_storage, set_f64};
+use crate::utils::web3::Web3Service;
+
+use wasm_bindgen_futures::spawn_local;
+
+use web_sys::Storage;
+
+use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::ConsoleService;
+use yew::InputData;
+
+use linked_data::moderation::Report;
+use linked_data::signature::SignedMessage;
+
+use cid::Cid;
+
+use web3::types::Address;
+
+use reqwest::Error;
+
+/// Minimum time between 2 reports from the same address.
+const REPORT_COOLDOWN_MS: f64 = 60_000.0;
+
+/// Longest reason accepted, in characters.
+const MAX_REASON_LENGTH: usize = 500;
+
+enum DisplayState {
+    Idle,
+    Editing,
+    Sending,
+    Sent,
+    RateLimited,
+    Failed,
+}
+
+pub struct ReportButton {
+    link: ComponentLink<Self>,
+
+    ipfs: IpfsService,
+    web3: Web3Service,
+    content: Cid,
+
+    storage: Option<Storage>,
+
+    state: DisplayState,
+
+    reason: String,
+    address: Option<Address>,
+    report: Option<Report>,
+}
+
+pub enum Msg {
+    Open,
+    Cancel,
+    SetReason(String),
+    Submit,
+    Account(Result<Address, web3::Error>),
+    Signed(Result<[u8; 65], web3::Error>),
+    Minted(Result<Cid, Error>),
+    Published(Result<(), Error>),
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub ipfs: IpfsService,
+    pub web3: Web3Service,
+
+    /// Link to the reported content.
+    pub content: Cid,
+}
+
+impl Component for ReportButton {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let Props {
+            ipfs,
+            web3,
+            content,
+        } = props;
+
+        let window = web_sys::window().expect("Can't get window");
+        let storage = get_local_storage(&window);
+
+        Self {
+            link,
+
+            ipfs,
+            web3,
+            content,
+
+            storage,
+
+            state: DisplayState::Idle,
+
+            reason: String::new(),
+            address: None,
+            report: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Open => self.on_open(),
+            Msg::Cancel => self.on_cancel(),
+            Msg::SetReason(reason) => self.on_reason_input(reason),
+            Msg::Submit => self.on_submit(),
+            Msg::Account(res) => self.on_account_connected(res),
+            Msg::Signed(res) => self.on_signature(res),
+            Msg::Minted(res) => self.on_report_minted(res),
+            Msg::Published(res) => self.on_report_published(res),
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let content = match self.state {
+            DisplayState::Idle => {
+                html! { <button class="report_button" onclick=self.link.callback(|_| Msg::Open)>{ "Report" }</button> }
+            }
+            DisplayState::Editing => html! {
+                <div class="report_form">
+                    <input placeholder="Reason" oninput=self.link.callback(|e: InputData| Msg::SetReason(e.value)) />
+                    <button class="submit_button" onclick=self.link.callback(|_| Msg::Submit)>{ "Send" }</button>
+                    <button class="cancel_button" onclick=self.link.callback(|_| Msg::Cancel)>{ "Cancel" }</button>
+                </div>
+            },
+            DisplayState::Sending => {
+                html! { <div class="report_status">{ "Sending report..." }</div> }
+            }
+            DisplayState::Sent => self.view_status("Report sent to moderators."),
+            DisplayState::RateLimited => {
+                self.view_status("Please wait before sending another report.")
+            }
+            DisplayState::Failed => self.view_status("Report failed."),
+        };
+
+        html! {
+            <div class="report">
+            { content }
+            </div>
+        }
+    }
+}
+
+impl ReportButton {
+    /// Final status, closing it allows another report.
+    fn view_status(&self, status: &str) -> Html {
+        html! {
+            <div class="report_status">
+                { status }
+                <button class="cancel_button" onclick=self.link.callback(|_| Msg::Cancel)>{ "Close" }</button>
+            </div>
+        }
+    }
+
+    fn on_open(&mut self) -> bool {
+        self.state = DisplayState::Editing;
+
+        true
+    }
+
+    fn on_cancel(&mut self) -> bool {
+        self.reason.clear();
+        self.address = None;
+        self.report = None;
+        self.state = DisplayState::Idle;
+
+        true
+    }
+
+    fn on_reason_input(&mut self, reason: String) -> bool {
+        self.reason = reason;
+
+        false
+    }
+
+    /// Trigger ethereum request accounts.
+    fn on_submit(&mut self) -> bool {
+        if build_report(self.content, &self.reason).is_none() {
+            return false;
+        }
+
+        let cb = self.link.callback_once(Msg::Account);
+        let web3 = self.web3.clone();
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Get Address");
+
+        spawn_local(async move { cb.emit(web3.get_eth_accounts().await) });
+
+        self.state = DisplayState::Sending;
+
+        true
+    }
+
+    /// Callback with response of request accounts, sign the report if allowed.
+    fn on_account_connected(&mut self, response: Result<Address, web3::Error>) -> bool {
+        let address = match response {
+            Ok(address) => address,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                self.state = DisplayState::Failed;
+                return true;
+            }
+        };
+
+        let last = get_f64(&report_key(&address), self.storage.as_ref());
+
+        if is_rate_limited(last, js_sys::Date::now()) {
+            self.state = DisplayState::RateLimited;
+            return true;
+        }
+
+        let report = match build_report(self.content, &std::mem::take(&mut self.reason)) {
+            Some(report) => report,
+            None => {
+                self.state = DisplayState::Failed;
+                return true;
+            }
+        };
+
+        let cb = self.link.callback_once(Msg::Signed);
+        let web3 = self.web3.clone();
+        let data = report.clone();
+
+        spawn_local(async move { cb.emit(web3.eth_sign(address, data).await) });
+
+        self.address = Some(address);
+        self.report = Some(report);
+
+        false
+    }
+
+    fn on_signature(&mut self, response: Result<[u8; 65], web3::Error>) -> bool {
+        let signature = match response {
+            Ok(sig) => sig.to_vec(),
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                self.state = DisplayState::Failed;
+                return true;
+            }
+        };
+
+        let (address, data) = match (self.address, self.report.take()) {
+            (Some(address), Some(report)) => (address.to_fixed_bytes(), report),
+            _ => {
+                self.state = DisplayState::Failed;
+                return true;
+            }
+        };
+
+        let signed_msg = SignedMessage {
+            address,
+            data,
+            signature,
+        };
+
+        let cb = self.link.callback_once(Msg::Minted);
+        let client = self.ipfs.clone();
+
+        spawn_local(async move { cb.emit(client.dag_put(&signed_msg).await) });
+
+        false
+    }
+
+    fn on_report_minted(&mut self, response: Result<Cid, Error>) -> bool {
+        let cid = match response {
+            Ok(cid) => cid,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                self.state = DisplayState::Failed;
+                return true;
+            }
+        };
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Publish Report");
+
+        let cb = self.link.callback_once(Msg::Published);
+        let client = self.ipfs.clone();
+        let topic = REPORT_TOPIC.to_owned();
+
+        spawn_local(async move { cb.emit(client.pubsub_pub(topic, cid.to_string()).await) });
+
+        false
+    }
+
+    fn on_report_published(&mut self, response: Result<(), Error>) -> bool {
+        if let Err(e) = response {
+            ConsoleService::error(&format!("{:?}", e));
+            self.state = DisplayState::Failed;
+            return true;
+        }
+
+        if let Some(address) = self.address.take() {
+            set_f64(
+                &report_key(&address),
+                js_sys::Date::now(),
+                self.storage.as_ref(),
+            );
+        }
+
+        self.state = DisplayState::Sent;
+
+        true
+    }
+}
+
+fn report_key(address: &Address) -> String {
+    format!("last_report_{:?}", address)
+}
+
+/// Report with a trimmed reason, None if the reason is empty or too long.
+fn build_report(content: Cid, reason: &str) -> Option<Report> {
+    let reason = reason.trim();
+
+    if reason.is_empty() || reason.chars().count() > MAX_REASON_LENGTH {
+        return None;
+    }
+
+    Some(Report {
+        content: content.into(),
+        reason: reason.to_owned(),
+    })
+}
+
+/// True if the last report from this address was sent less than a cooldown ago.
+fn is_rate_limited(last_report: Option<f64>, now: f64) -> bool {
+    match last_report {
+        Some(last) => now - last < REPORT_COOLDOWN_MS,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_links_content_with_trimmed_reason() {
+        let content = Cid::default();
+        let report = build_report(content, "  spam \n").unwrap();
+
+        assert_eq!(report.content.link, content);
+        assert_eq!(report.reason, "spam");
+    }
+
+    #[test]
+    fn invalid_reason_rejected() {
+        assert!(build_report(Cid::default(), "").is_none());
+        assert!(build_report(Cid::default(), " \t ").is_none());
+
+        let long = "a".repeat(MAX_REASON_LENGTH + 1);
+
+        assert!(build_report(Cid::default(), &long).is_none());
+        assert!(build_report(Cid::default(), &long[1..]).is_some());
+    }
+
+    #[test]
+    fn first_report_not_rate_limited() {
+        assert!(!is_rate_limited(None, 1_000.0));
+    }
+
+    #[test]
+    fn report_within_cooldown_rate_limited() {
+        let last = 1_000.0;
+
+        assert!(is_rate_limited(Some(last), last));
+        assert!(is_rate_limited(Some(last), last + REPORT_COOLDOWN_MS - 1.0));
+        assert!(!is_rate_limited(Some(last), last + REPORT_COOLDOWN_MS));
+    }
+}
@@ -1,36 +1,213 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
 use std::rc::Rc;
 use std::str;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-use crate::utils::ema::ExponentialMovingAverage;
+use crate::utils::bitrate::{format_bitrate, BitrateUnit};
+use crate::utils::cancel::CancelToken;
+use crate::utils::ema::{ExponentialMovingAverage, DEFAULT_RESET_THRESHOLD};
 use crate::utils::ipfs::{IpfsService, PubsubSubResponse};
+use crate::utils::local_storage::{
+    get_f64, get_local_bitrate_unit, get_local_storage, get_session_storage, remove_item, set_f64,
+};
+use crate::utils::timer::{sleep, timeout};
+use crate::utils::web3::Web3Service;
+
+use futures::join;
 
 use wasm_bindgen::closure::Closure;
-use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::spawn_local;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
 
-use web_sys::{HtmlMediaElement, MediaSource, MediaSourceReadyState, SourceBuffer, Url, Window};
+use web_sys::{
+    Blob, BlobPropertyBag, HtmlElement, HtmlMediaElement, HtmlTrackElement, HtmlVideoElement,
+    MediaSource, MediaSourceReadyState, SourceBuffer, SourceBufferAppendMode, Storage,
+    TextTrackMode, Url, Window,
+};
 
 use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::interval::{IntervalService, IntervalTask};
 use yew::services::ConsoleService;
+use yew::{Callback, ChangeData};
+
+use linked_data::video::{
+    health_topic, index_chunk, HealthReport, ImageSize, LiveAnnouncement, LiveHeartbeat,
+    MinuteNode, SegmentIndex, SetupNode, Track, VideoMessage, VideoMetadata, VideoNode,
+};
+use linked_data::Address;
 
-use linked_data::video::{SetupNode, Track, VideoMetadata};
+#[cfg(debug_assertions)]
+use linked_data::debug_json;
 
 use cid::Cid;
 
 use reqwest::Error;
 
-const FORWARD_BUFFER_LENGTH: f64 = 16.0;
-const BACK_BUFFER_LENGTH: f64 = 8.0;
+/// Default seconds buffered ahead of current time.
+const DEFAULT_FORWARD_BUFFER: f64 = 16.0;
+
+/// Default seconds kept behind current time.
+const DEFAULT_BACK_BUFFER: f64 = 8.0;
+
+/// Default bytes of video buffered ahead of current time, high bitrates buffer fewer seconds.
+const DEFAULT_FORWARD_BUFFER_BYTES: f64 = 24.0 * 1024.0 * 1024.0;
+
+/// Seconds buffered ahead whatever the bitrate.
+const MIN_FORWARD_BUFFER: f64 = 4.0;
+
+/// Default seconds loaded before current time when the buffer is empty.
+const DEFAULT_PRE_ROLL: f64 = 1.0;
+
+/// Default seconds buffered ahead required before switching to a higher quality.
+const DEFAULT_UPSWITCH_BUFFER: f64 = 4.0;
+
+/// Default shortest time allowed for a segment download before downgrading, in milliseconds.
+const DEFAULT_SEGMENT_DEADLINE: i32 = 2_000;
+
+/// Default largest pubsub message accepted from the streamer, in bytes.
+const DEFAULT_MAX_PUBSUB_PAYLOAD: usize = 8 * 1024;
+
+/// Default seconds during which a repeated live segment is ignored.
+const DEFAULT_DEDUP_WINDOW: f64 = 10.0;
+
+/// Local storage key of the last volume set by the user.
+const VOLUME_KEY: &str = "volume";
+
+/// Document events unmuting a player started muted.
+const UNMUTE_EVENTS: [&str; 2] = ["click", "keydown"];
+
+/// Default seconds without subscription after which queued live segments are dropped.
+const DEFAULT_RECONNECT_GAP: f64 = 10.0;
+
+/// Delay before subscribing again after the live subscription ended, in milliseconds.
+const RESUBSCRIBE_DELAY: i32 = 2_000;
+
+/// Local storage key prefix of VOD playback positions, followed by the video CID.
+const POSITION_KEY_PREFIX: &str = "position_";
+
+/// Delay between playback position saves.
+const POSITION_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Positions this close to the end are not resumed, in seconds.
+const RESUME_END_MARGIN: f64 = 10.0;
+
+/// Maximum number of live segments waiting to be loaded, new ones are dropped when full.
+const LIVE_BUFFER_CAPACITY: usize = 30;
+
+/// Playback rate kept for the session.
+const PLAYBACK_RATE_KEY: &str = "playback_rate";
+
+/// Rates offered to the viewer, others are refused.
+const PLAYBACK_RATES: [f64; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+
+/// Seconds kept behind current time when the source buffer is full.
+const QUOTA_BACK_BUFFER: f64 = 1.0;
+
+/// Share of the estimated connection speed a starting level may use.
+const START_BITRATE_RATIO: f64 = 0.7;
+
+/// Time between health reports, published only if the viewer opted in.
+const HEALTH_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Video element events counted as viewer activity.
+const INTERACTION_EVENTS: [&str; 3] = ["play", "seeking", "volumechange"];
+
+/// Document events fired when entering or leaving element fullscreen.
+const FULLSCREEN_EVENTS: [&str; 2] = ["fullscreenchange", "webkitfullscreenchange"];
+
+/// Video element events fired by iOS native fullscreen.
+const IOS_FULLSCREEN_EVENTS: [&str; 2] = ["webkitbeginfullscreen", "webkitendfullscreen"];
+
+/// Default seconds without heartbeat or segment before the stream is flagged as stalled.
+const DEFAULT_STALL_WINDOW: f64 = 15.0;
+
+/// Delay between stalled stream checks.
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Seconds video is allowed to be buffered past audio when appended independently.
+const MAX_AV_DRIFT: f64 = 2.0;
+
+/// Multicodec of the nodes published by the streamer.
+const DAG_CBOR: u64 = 0x71;
+
+/// Track 0 of the setup node is always audio.
+const AUDIO_LEVEL: usize = 0;
+
+/// Video tracks follow the audio track.
+const MIN_VIDEO_LEVEL: usize = 1;
 
-const SETUP_PATH: &str = "/time/hour/0/minute/0/second/0/video/setup";
+/// Multicodecs of images added to IPFS as raw blocks or files.
+const RAW: u64 = 0x55;
+const DAG_PB: u64 = 0x70;
 
+/// Default inactivity delay before pausing, in minutes.
+const DEFAULT_IDLE_MINUTES: f64 = 30.0;
+
+const DEFAULT_POSTER: &str = "bafkreicovb5qdvrine4vidt77xahhvovahmekvsojbiqewp7ih7pzvnn7i";
+
+/// Default time to wait for metadata nodes like the setup before retrying, in milliseconds.
+const DEFAULT_METADATA_TIMEOUT: i32 = 10_000;
+
+/// Default time to wait for media segments before retrying, in milliseconds.
+const DEFAULT_MEDIA_TIMEOUT: i32 = 20_000;
+
+/// Number of setup fetch attempts before giving up.
+const SETUP_ATTEMPTS: usize = 3;
+
+pub const SETUP_PATH: &str = "/time/hour/0/minute/0/second/0/video/setup";
+
+/// How the video fills its container when aspect ratios differ.
+#[derive(Clone, Copy, PartialEq)]
+pub enum VideoFit {
+    /// Show the whole video with bars.
+    Letterbox,
+
+    /// Fill the container, cropping the video.
+    Crop,
+}
+
+impl Default for VideoFit {
+    fn default() -> Self {
+        VideoFit::Letterbox
+    }
+}
+
+/// How audio and video media segments are appended to their buffers.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AppendOrder {
+    /// Wait for both segments then append audio before video.
+    AudioFirst,
+
+    /// Append each segment as soon as it arrives.
+    Independent,
+}
+
+impl Default for AppendOrder {
+    fn default() -> Self {
+        AppendOrder::AudioFirst
+    }
+}
+
+/// How segments are found and buffered.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlaybackMode {
+    /// Segments from the time tree of the video root.
+    Vod,
+
+    /// Segments announced on the live topic.
+    Live,
+}
+
+#[derive(Debug, PartialEq)]
 enum MachineState {
     Load,
     Switch,
     Flush,
+    FlushAll,
     Timeout,
     AdaptativeBitrate,
     Status,
@@ -38,62 +215,428 @@ enum MachineState {
 
 struct MediaBuffers {
     audio: SourceBuffer,
-    video: SourceBuffer,
 
+    /// None for audio-only videos, the audio track is then the only level.
+    video: Option<SourceBuffer>,
+
+    /// Active audio track first then video tracks.
     tracks: Vec<Track>,
+
+    /// Every supported audio track, in setup order.
+    audio_tracks: Vec<Track>,
+
+    /// Sequence number of the next audio segment requested.
+    audio_requested: u64,
+
+    /// Sequence number of the next audio segment to append.
+    audio_next: u64,
+
+    /// Audio segments downloaded out of order or while the buffer was updating.
+    audio_pending: BTreeMap<u64, Vec<u8>>,
+}
+
+impl MediaBuffers {
+    /// Buffer segments are loaded for, the audio buffer of audio-only videos.
+    fn main(&self) -> &SourceBuffer {
+        self.video.as_ref().unwrap_or(&self.audio)
+    }
+
+    /// Lowest level appended to the main buffer.
+    fn min_level(&self) -> usize {
+        match self.video {
+            Some(_) => MIN_VIDEO_LEVEL,
+            None => AUDIO_LEVEL,
+        }
+    }
+
+    fn updating(&self) -> bool {
+        self.audio.updating() || self.main().updating()
+    }
+
+    /// Remove a time range from every buffer.
+    fn remove(&self, start: f64, end: f64) -> Result<(), JsValue> {
+        self.audio.remove(start, end)?;
+
+        if let Some(video) = self.video.as_ref() {
+            video.remove(start, end)?;
+        }
+
+        Ok(())
+    }
 }
 
 struct LiveStream {
     streamer_peer_id: String,
 
+    /// Address signed messages must come from, announcements are ignored until known.
+    streamer: Option<Address>,
+
     buffer: VecDeque<Cid>,
 
     drop_sig: Rc<AtomicBool>,
+
+    /// Latest timecode to VideoNode index published by the streamer.
+    index: Option<SegmentIndex>,
+
+    /// First VideoNode appended, at media time 0.
+    first_node: Option<Cid>,
+
+    /// Stream timecode of the first VideoNode appended.
+    origin: Option<u64>,
+
+    /// Timestamp offset to set before the next append.
+    pending_offset: Option<f64>,
+
+    /// Setup node was requested, segments are queued until source buffers are added.
+    setup_pending: bool,
+
+    /// Recently received segments with the time they arrived, oldest first.
+    recent: VecDeque<(Cid, f64)>,
+
+    /// Time the subscription ended, cleared by the first message after reconnecting.
+    disconnected_at: Option<f64>,
+
+    /// Time the last message from the streamer arrived.
+    last_activity: f64,
+
+    /// No message from the streamer within the stall window.
+    stalled: bool,
+
+    /// Streamer announced the end of the stream.
+    ended: bool,
+
+    /// Last valid heartbeat, older ones are replays.
+    heartbeat: Option<LiveHeartbeat>,
+
+    /// Last seek was before the retained window and snapped to its start.
+    seek_clamped: bool,
+
+    /// Timecode of the last segment queued by a seek, later ones are queued from the index once loaded.
+    backlog: Option<u64>,
+
+    /// Segments received after the index was published, queued once the backlog is loaded.
+    held: VecDeque<Cid>,
+
+    /// Load the newest queued segment and buffer as little as possible.
+    low_latency: bool,
+
+    /// Queued segments dropped to catch up with the live edge or because the queue was full.
+    skipped: usize,
+
+    /// Log every message rejected because of its sender.
+    audit_senders: bool,
+
+    /// Number of messages rejected per sender peer id.
+    rejected: HashMap<String, usize>,
+
+    /// Topic health reports are published to.
+    health_topic: String,
+
+    /// Publish health reports periodically, only if the viewer opted in.
+    _health_task: Option<IntervalTask>,
+
+    _stall_task: IntervalTask,
 }
 
 pub struct VideoPlayer {
     link: ComponentLink<Self>,
 
     ipfs: IpfsService,
+    web3: Option<Web3Service>,
+    cancel: CancelToken,
+    mode: PlaybackMode,
     metadata: Option<VideoMetadata>,
+
+    /// Root of the VOD time tree, from the metadata or a raw CID.
+    video_root: Option<Cid>,
+
+    /// Unknown without metadata.
+    duration: Option<f64>,
+
     live_stream: Option<LiveStream>,
 
+    /// Timecode to VideoNode index of the chunk being loaded, if published.
+    vod_index: Option<SegmentIndex>,
+
+    /// Chunk of the VOD index fetched or being fetched.
+    vod_index_chunk: Option<u64>,
+
+    /// Time tree path of the VOD segment being loaded.
+    vod_time_path: Option<String>,
+
+    /// VideoNode of the live segment being loaded.
+    live_node: Option<Cid>,
+
+    /// Saved position to seek to once the first segment is buffered.
+    resume_at: Option<f64>,
+    _position_task: Option<IntervalTask>,
+
     window: Window,
+    storage: Option<Storage>,
+    session_storage: Option<Storage>,
+    /// Speed of VOD playback, always 1 for live.
+    playback_rate: f64,
     media_element: Option<HtmlMediaElement>,
-    media_source: MediaSource,
+    /// None if the browser does not support Media Source Extensions.
+    media_source: Option<MediaSource>,
     media_buffers: Option<MediaBuffers>,
-    object_url: String,
+    object_url: Option<String>,
+    fallback_url: Option<String>,
     poster_link: String,
-
-    /// Level >= 1 since 0 is audio
+    title: Option<String>,
+    /// Browser supports Picture-in-Picture and it is not disabled.
+    pip_supported: bool,
+    fullscreen: bool,
+    /// Language and object URL of each WebVTT file loaded.
+    subtitles: Vec<(String, String)>,
+    subtitle: Option<usize>,
+    locked: bool,
+    error: Option<String>,
+    setup_attempts: usize,
+    /// Move keyboard focus to the retry button once rendered.
+    focus_retry: bool,
+    /// A tick came before the video element was attached, run it once attached.
+    tick_pending: bool,
+    bitrate_unit: BitrateUnit,
+
+    /// Video track, only changed via set_video_level().
     level: usize,
+    /// Quality level is chosen by ABR.
+    auto_quality: bool,
+    /// Level chosen by the viewer, applied before the next segment.
+    pinned_level: usize,
     state: MachineState,
+    flush_count: u32,
+    /// Incremented on flush, segments downloaded before are dropped.
+    generation: u32,
+    /// Initialization segment of a quality switch not yet appended.
+    switch_pending: bool,
+    /// Last level whose initialization segment was downloaded.
+    working_level: usize,
+    /// Audio language changed, its initialization segment is appended on next switch.
+    audio_switch_pending: bool,
     ema: ExponentialMovingAverage,
+    /// Download speed reported by the browser, used to choose the first level.
+    start_bitrate: Option<f64>,
+    /// Latest average download speed, displayed with the stats.
+    avg_bitrate: Option<f64>,
+    show_stats: bool,
+
+    pre_roll: f64,
+    forward_buffer: f64,
+    forward_buffer_bytes: f64,
+    back_buffer: f64,
+    upswitch_buffer: f64,
+    segment_deadline: i32,
+    metadata_timeout: i32,
+    media_timeout: i32,
+    max_pubsub_payload: usize,
+    dedup_window: f64,
+    reconnect_gap: f64,
+    stall_window: f64,
+    append_order: AppendOrder,
+    fit: VideoFit,
+
+    seek_preview: bool,
+    previewing: bool,
+    previews: Vec<(f64, f64, String)>,
+    preview_link: Option<String>,
+
+    start_muted: bool,
+    /// Started muted and not yet unmuted by an interaction.
+    unmute_pending: bool,
+
+    auto_pause: bool,
+    idle_minutes: f64,
+    last_interaction: f64,
+    idle: bool,
 
     source_open_closure: Option<Closure<dyn Fn()>>,
+    source_ended_closure: Option<Closure<dyn Fn()>>,
+    source_error_closure: Option<Closure<dyn Fn()>>,
     seeking_closure: Option<Closure<dyn Fn()>>,
+    seeked_closure: Option<Closure<dyn Fn()>>,
     update_end_closure: Option<Closure<dyn Fn()>>,
+    audio_update_end_closure: Option<Closure<dyn Fn()>>,
     timeout_closure: Option<Closure<dyn Fn()>>,
+    interaction_closure: Option<Closure<dyn Fn()>>,
+    volume_closure: Option<Closure<dyn Fn()>>,
+    unmute_closure: Option<Closure<dyn Fn()>>,
+    fullscreen_closure: Option<Closure<dyn Fn()>>,
+    loaded_metadata_closure: Option<Closure<dyn Fn()>>,
     handle: i32,
 }
 
 pub enum Msg {
     SourceOpen,
+    SourceEnded,
+    SourceError,
     Seeking,
+    Seeked,
     UpdateEnd,
     Timeout,
     SetupNode(Result<SetupNode, Error>),
-    Append(Result<(Vec<u8>, Vec<u8>), Error>),
-    AppendVideo(Result<Vec<u8>, Error>),
+    SetupTimeout,
+    RetrySetup,
+    Append(u32, Result<(Vec<u8>, Vec<u8>), Error>),
+    AppendVideo(u32, Result<Vec<u8>, Error>),
+    SwitchInit(u32, usize, Result<Vec<u8>, Error>),
+    AppendAudio(u64, Result<Vec<u8>, Error>),
+    AudioUpdateEnd,
+    SegmentDeadline(u32, Option<Cid>),
+    SegmentTimeout(u32, Option<Cid>),
+    SetLevel(usize),
+    Subtitle((String, Result<Vec<u8>, Error>)),
+    Previews(Result<Vec<u8>, Error>),
+    SetSubtitle(Option<usize>),
+    SetAudioTrack(String),
+    SetPlaybackRate(f64),
+    ReportHealth,
     PubSub(Result<PubsubSubResponse, std::io::Error>),
+    PubSubClosed,
+    CheckStall,
+    SavePosition,
+    SegmentIndex(Result<SegmentIndex, Error>),
+    IndexChunk(u64, Result<SegmentIndex, Error>),
+    TimeNode(u32, Result<bool, Error>),
+    LiveNode(u32, Cid, Result<VideoNode, Error>),
+    LiveSegmentNode(u32, Cid, Result<VideoNode, Error>),
+    SeekBucket(u32, String, u8, Result<MinuteNode, Error>),
+    Access(Result<bool, web3::contract::Error>),
+    Interaction,
+    VolumeChange,
+    Unmute,
+    LoadedMetadata,
+    ToggleFit,
+    TogglePip,
+    ToggleFullscreen,
+    FullscreenChange,
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {
     pub ipfs: IpfsService,
+    pub mode: PlaybackMode,
     pub metadata: Option<VideoMetadata>,
     pub topic: Option<String>,
     pub streamer_peer_id: Option<String>,
+
+    /// Video root played when there is no metadata.
+    #[prop_or_default]
+    pub root_cid: Option<Cid>,
+
+    /// Address of the streamer, signed live messages from other addresses are ignored.
+    #[prop_or_default]
+    pub streamer: Option<Address>,
+
+    /// Played directly when Media Source Extensions are unavailable.
+    #[prop_or_default]
+    pub fallback_url: Option<String>,
+
+    /// Wallet used to check video access requirements.
+    #[prop_or_default]
+    pub web3: Option<Web3Service>,
+
+    /// Seconds loaded before current time when the buffer is empty.
+    #[prop_or(DEFAULT_PRE_ROLL)]
+    pub pre_roll: f64,
+
+    /// Seconds buffered ahead of current time, must be greater than the back buffer.
+    #[prop_or(DEFAULT_FORWARD_BUFFER)]
+    pub forward_buffer: f64,
+
+    /// Bytes of video buffered ahead of current time, limits the seconds buffered at high bitrates.
+    #[prop_or(DEFAULT_FORWARD_BUFFER_BYTES)]
+    pub forward_buffer_bytes: f64,
+
+    /// Seconds kept behind current time before being flushed.
+    #[prop_or(DEFAULT_BACK_BUFFER)]
+    pub back_buffer: f64,
+
+    /// Seconds buffered ahead required before switching to a higher quality.
+    #[prop_or(DEFAULT_UPSWITCH_BUFFER)]
+    pub upswitch_buffer: f64,
+
+    /// Shortest time allowed for a segment download before retrying at lower quality, in milliseconds.
+    #[prop_or(DEFAULT_SEGMENT_DEADLINE)]
+    pub segment_deadline: i32,
+
+    /// Time to wait for metadata nodes like the setup before retrying, in milliseconds.
+    #[prop_or(DEFAULT_METADATA_TIMEOUT)]
+    pub metadata_timeout: i32,
+
+    /// Time to wait for media segments before retrying, in milliseconds.
+    /// A buffer underrun deadline shorter than this still applies.
+    #[prop_or(DEFAULT_MEDIA_TIMEOUT)]
+    pub media_timeout: i32,
+
+    /// Largest live stream message accepted, bigger ones are dropped unparsed, in bytes.
+    #[prop_or(DEFAULT_MAX_PUBSUB_PAYLOAD)]
+    pub max_pubsub_payload: usize,
+
+    /// Seconds during which the same live segment received again is ignored.
+    #[prop_or(DEFAULT_DEDUP_WINDOW)]
+    pub dedup_window: f64,
+
+    /// Seconds disconnected after which queued live segments are dropped to resync with the live edge.
+    #[prop_or(DEFAULT_RECONNECT_GAP)]
+    pub reconnect_gap: f64,
+
+    /// Seconds without heartbeat or segment before warning that the stream may have stopped.
+    #[prop_or(DEFAULT_STALL_WINDOW)]
+    pub stall_window: f64,
+
+    /// Wait for audio before appending video or append both as they arrive.
+    #[prop_or_default]
+    pub append_order: AppendOrder,
+
+    /// Download speed to average ratio that resets bandwidth estimation when sustained.
+    #[prop_or(DEFAULT_RESET_THRESHOLD)]
+    pub ema_reset_threshold: f64,
+
+    /// Weight of each new download speed in the average, higher reacts faster to bandwidth changes.
+    #[prop_or_default]
+    pub ema_alpha: Option<f64>,
+
+    /// Letterbox or crop when the video aspect ratio differs from the player.
+    #[prop_or_default]
+    pub fit: VideoFit,
+
+    /// Show images of the preview track while scrubbing and only reload once the seek completes.
+    #[prop_or_default]
+    pub seek_preview: bool,
+
+    /// Autoplay muted then unmute on the first click or key press, for autoplay policies.
+    #[prop_or_default]
+    pub start_muted: bool,
+
+    /// Pause playback and stop fetching segments after a period without user interaction.
+    #[prop_or_default]
+    pub auto_pause: bool,
+
+    /// Inactivity delay before pausing.
+    #[prop_or(DEFAULT_IDLE_MINUTES)]
+    pub idle_minutes: f64,
+
+    /// Display download speed and current quality over the video.
+    #[prop_or_default]
+    pub show_stats: bool,
+
+    /// Stay near the live edge with a short buffer, skipping queued segments.
+    #[prop_or_default]
+    pub low_latency: bool,
+
+    /// Log messages from peers other than the streamer, in release builds too.
+    #[prop_or_default]
+    pub audit_senders: bool,
+
+    /// Viewer opted in to periodically share playback health with the streamer.
+    #[prop_or_default]
+    pub report_stats: bool,
+
+    /// Increment to remove everything buffered and reload at current time.
+    #[prop_or_default]
+    pub flush_count: u32,
 }
 
 impl Component for VideoPlayer {
@@ -103,104 +646,581 @@ impl Component for VideoPlayer {
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let Props {
             ipfs,
+            mode,
             metadata,
             topic,
             streamer_peer_id,
+            root_cid,
+            streamer,
+            fallback_url,
+            web3,
+            pre_roll,
+            forward_buffer,
+            forward_buffer_bytes,
+            back_buffer,
+            upswitch_buffer,
+            segment_deadline,
+            metadata_timeout,
+            media_timeout,
+            max_pubsub_payload,
+            dedup_window,
+            reconnect_gap,
+            stall_window,
+            append_order,
+            ema_reset_threshold,
+            ema_alpha,
+            fit,
+            seek_preview,
+            start_muted,
+            auto_pause,
+            idle_minutes,
+            show_stats,
+            low_latency,
+            audit_senders,
+            report_stats,
+            flush_count,
         } = props;
 
         let window = web_sys::window().expect("Can't get window");
 
-        let ema = ExponentialMovingAverage::new(&window);
+        let storage = get_local_storage(&window);
+        let session_storage = get_session_storage(&window);
+        let bitrate_unit = get_local_bitrate_unit(storage.as_ref());
+
+        // Inverted lengths would flush data about to be played.
+        let (forward_buffer, back_buffer) = if forward_buffer > back_buffer && back_buffer > 0.0 {
+            (forward_buffer, back_buffer)
+        } else {
+            ConsoleService::warn(&format!(
+                "Invalid buffer lengths forward {}s back {}s, using defaults",
+                forward_buffer, back_buffer
+            ));
 
-        let media_source = MediaSource::new().expect("Can't create media source");
+            (DEFAULT_FORWARD_BUFFER, DEFAULT_BACK_BUFFER)
+        };
 
-        let object_url = Url::create_object_url_with_source(&media_source)
-            .expect("Can't create url from source");
+        let mut ema = match ema_alpha {
+            Some(alpha) => ExponentialMovingAverage::with_alpha(&window, alpha),
+            None => ExponentialMovingAverage::new(&window),
+        };
+        ema.set_reset_threshold(ema_reset_threshold);
 
-        let mut poster_link = String::from("ipfs://");
+        let start_bitrate = connection_bitrate(&window);
 
-        if let Some(md) = metadata.as_ref() {
-            poster_link.push_str(&md.image.link.to_string());
-        } else {
-            //TODO default thumbnail image
-            poster_link.push_str("bafkreicovb5qdvrine4vidt77xahhvovahmekvsojbiqewp7ih7pzvnn7i");
+        if let Some(bitrate) = start_bitrate {
+            ema.seed(bitrate);
         }
 
-        let cb = link.callback(|_| Msg::SourceOpen);
-        let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
-        media_source.set_onsourceopen(Some(closure.as_ref().unchecked_ref()));
-        let source_open_closure = Some(closure);
+        let media_source = create_media_source(&window);
+
+        let object_url = media_source.as_ref().map(|media_source| {
+            Url::create_object_url_with_source(media_source).expect("Can't create url from source")
+        });
+
+        // Without Media Source Extensions only VOD can be played, from the fallback URL.
+        let fallback_url =
+            fallback_url.filter(|_| media_source.is_none() && mode == PlaybackMode::Vod);
+
+        let error = match (media_source.as_ref(), fallback_url.as_ref()) {
+            (None, None) => Some("This browser does not support Media Source Extensions.".into()),
+            _ => None,
+        };
+
+        let poster_link = poster_link(metadata.as_ref().map(|md| md.image_link(ImageSize::Full)));
+
+        let source_open_closure = media_source.as_ref().map(|media_source| {
+            let cb = link.callback(|_| Msg::SourceOpen);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+            media_source.set_onsourceopen(Some(closure.as_ref().unchecked_ref()));
+            closure
+        });
+
+        let source_ended_closure = media_source.as_ref().map(|media_source| {
+            let cb = link.callback(|_| Msg::SourceEnded);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+            media_source.set_onsourceended(Some(closure.as_ref().unchecked_ref()));
+            closure
+        });
+
+        let source_error_closure = media_source.as_ref().map(|media_source| {
+            let cb = link.callback(|_| Msg::SourceError);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+
+            if let Err(e) = media_source
+                .add_event_listener_with_callback("error", closure.as_ref().unchecked_ref())
+            {
+                ConsoleService::error(&format!("{:?}", e));
+            }
+
+            closure
+        });
+
+        // Live streams play at normal speed.
+        let playback_rate = match get_f64(PLAYBACK_RATE_KEY, session_storage.as_ref()) {
+            Some(rate) if mode == PlaybackMode::Vod && PLAYBACK_RATES.contains(&rate) => rate,
+            _ => 1.0,
+        };
+
+        let cancel = CancelToken::new();
+
+        let pip_supported = window
+            .document()
+            .and_then(|document| {
+                js_sys::Reflect::get(&document, &"pictureInPictureEnabled".into()).ok()
+            })
+            .map_or(false, |enabled| enabled.is_truthy());
+
+        let subtitle_count = metadata.as_ref().map_or(0, |md| md.subtitles.len());
+
+        for track in metadata.iter().flat_map(|md| md.subtitles.iter()) {
+            let cb = link.callback_once(Msg::Subtitle);
+            let client = ipfs.clone();
+            let language = track.language.clone();
+            let cid = track.vtt.link;
+
+            cancel.spawn(async move { cb.emit((language, client.cid_cat(cid).await)) });
+        }
+
+        let preview_track = metadata
+            .as_ref()
+            .and_then(|md| md.previews)
+            .filter(|_| seek_preview);
+
+        if let Some(ipld) = preview_track {
+            let cb = link.callback_once(Msg::Previews);
+            let client = ipfs.clone();
+
+            cancel.spawn(async move { cb.emit(client.cid_cat(ipld.link).await) });
+        }
+
+        // Without Media Source Extensions live segments cannot be played.
+        let live_topic = topic.filter(|_| mode == PlaybackMode::Live && media_source.is_some());
+
+        let live_stream = match (live_topic, streamer_peer_id) {
+            (Some(topic), Some(streamer_peer_id)) => {
+                let health_topic = health_topic(&topic);
+                let health_task = if report_stats {
+                    Some(IntervalService::spawn(
+                        HEALTH_REPORT_INTERVAL,
+                        link.callback(|_| Msg::ReportHealth),
+                    ))
+                } else {
+                    None
+                };
 
-        let live_stream = match topic {
-            Some(topic) => {
                 let client = ipfs.clone();
                 let cb = link.callback(Msg::PubSub);
+                let closed_cb = link.callback(|_| Msg::PubSubClosed);
                 let drop_sig = Rc::from(AtomicBool::new(false));
                 let sig = drop_sig.clone();
 
-                spawn_local(async move { client.pubsub_sub(topic, cb, sig).await });
+                // Subscription stream is dropped on teardown even if the node is gone.
+                cancel.spawn(async move {
+                    loop {
+                        client
+                            .pubsub_sub(topic.clone(), cb.clone(), sig.clone())
+                            .await;
+
+                        if sig.load(Ordering::Relaxed) {
+                            return;
+                        }
+
+                        closed_cb.emit(());
+
+                        sleep(RESUBSCRIBE_DELAY).await;
+                    }
+                });
 
                 Some(LiveStream {
-                    streamer_peer_id: streamer_peer_id.unwrap(),
+                    streamer_peer_id,
+                    streamer,
                     buffer: VecDeque::with_capacity(5),
                     drop_sig,
+                    index: None,
+                    first_node: None,
+                    origin: None,
+                    pending_offset: None,
+                    setup_pending: false,
+                    recent: VecDeque::with_capacity(16),
+                    disconnected_at: None,
+                    last_activity: js_sys::Date::now(),
+                    stalled: false,
+                    ended: false,
+                    heartbeat: None,
+                    seek_clamped: false,
+                    backlog: None,
+                    held: VecDeque::new(),
+                    low_latency,
+                    skipped: 0,
+                    audit_senders,
+                    rejected: HashMap::new(),
+                    health_topic,
+                    _health_task: health_task,
+                    _stall_task: IntervalService::spawn(
+                        STALL_CHECK_INTERVAL,
+                        link.callback(|_| Msg::CheckStall),
+                    ),
                 })
             }
-            None => None,
+            (Some(_), None) => {
+                ConsoleService::warn("Live Topic Without Streamer Peer Id");
+                None
+            }
+            (None, _) => None,
         };
 
         Self {
             link,
 
             ipfs,
+            web3,
+            cancel,
+            mode,
+            video_root: metadata.as_ref().map(|md| md.video.link).or(root_cid),
+            duration: metadata.as_ref().map(|md| md.duration),
             metadata,
             live_stream,
 
+            vod_index: None,
+            vod_index_chunk: None,
+            vod_time_path: None,
+            live_node: None,
+
+            resume_at: None,
+            _position_task: None,
+
             window,
+            storage,
+            session_storage,
+            playback_rate,
             media_element: None,
             media_source,
             media_buffers: None,
             object_url,
+            fallback_url,
             poster_link,
-
-            level: 1, // start at 1 since 0 is audio
+            title: None,
+            pip_supported,
+            fullscreen: false,
+            subtitles: Vec::with_capacity(subtitle_count),
+            subtitle: None,
+            locked: false,
+            error,
+            setup_attempts: 0,
+            focus_retry: false,
+            tick_pending: false,
+            bitrate_unit,
+
+            level: MIN_VIDEO_LEVEL,
+            auto_quality: true,
+            pinned_level: MIN_VIDEO_LEVEL,
             state: MachineState::Timeout,
+            flush_count,
+            generation: 0,
+            switch_pending: false,
+            working_level: MIN_VIDEO_LEVEL,
+            audio_switch_pending: false,
             ema,
+            start_bitrate,
+            avg_bitrate: None,
+            show_stats,
+
+            pre_roll,
+            forward_buffer,
+            forward_buffer_bytes,
+            back_buffer,
+            upswitch_buffer,
+            segment_deadline,
+            metadata_timeout,
+            media_timeout,
+            max_pubsub_payload,
+            dedup_window,
+            reconnect_gap,
+            stall_window,
+            append_order,
+            fit,
+
+            seek_preview,
+            previewing: false,
+            previews: Vec::new(),
+            preview_link: None,
+
+            start_muted,
+            unmute_pending: false,
+
+            auto_pause,
+            idle_minutes,
+            last_interaction: js_sys::Date::now(),
+            idle: false,
 
             source_open_closure,
+            source_ended_closure,
+            source_error_closure,
             seeking_closure: None,
+            seeked_closure: None,
             update_end_closure: None,
+            audio_update_end_closure: None,
             timeout_closure: None,
+            interaction_closure: None,
+            volume_closure: None,
+            unmute_closure: None,
+            fullscreen_closure: None,
+            loaded_metadata_closure: None,
             handle: 0,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        let level = self.video_level();
+        let ready = self.media_buffers.is_some();
+        let avg_bitrate = self.avg_bitrate;
+
         match msg {
             Msg::SourceOpen => self.on_source_open(),
-            Msg::Seeking => self.on_seeking(),
+            Msg::SourceEnded => self.on_source_ended(),
+            Msg::SourceError => return self.on_source_error(),
+            Msg::Seeking => return self.on_seeking(),
+            Msg::Seeked => return self.on_seeked(),
             Msg::UpdateEnd => self.on_update_end(),
             Msg::Timeout => self.on_timeout(),
-            Msg::SetupNode(result) => self.add_source_buffer(result),
-            Msg::Append(result) => self.append_buffers(result),
-            Msg::AppendVideo(result) => self.append_video_buffer(result),
-            Msg::PubSub(result) => self.on_pubsub_update(result),
+            Msg::SetupNode(result) => return self.add_source_buffer(result),
+            Msg::SetupTimeout => return self.on_setup_failed("Timed out loading video setup."),
+            Msg::RetrySetup => return self.on_retry_setup(),
+            Msg::Append(generation, result) => return self.append_buffers(generation, result),
+            Msg::AppendVideo(generation, result) => {
+                return self.append_video_buffer(generation, result)
+            }
+            Msg::SwitchInit(generation, level, result) => {
+                return self.on_switch_init(generation, level, result)
+            }
+            Msg::AppendAudio(seq, result) => return self.append_audio_buffer(seq, result),
+            Msg::AudioUpdateEnd => self.append_pending_audio(),
+            Msg::SegmentDeadline(generation, cid) => self.on_segment_deadline(generation, cid),
+            Msg::SegmentTimeout(generation, cid) => self.on_segment_timeout(generation, cid),
+            Msg::SetLevel(level) => return self.set_level(level),
+            Msg::Subtitle((language, result)) => return self.on_subtitle(language, result),
+            Msg::Previews(result) => self.on_previews(result),
+            Msg::SetSubtitle(index) => return self.set_subtitle(index),
+            Msg::SetAudioTrack(language) => return self.set_audio_track(language),
+            Msg::SetPlaybackRate(rate) => return self.set_playback_rate(rate),
+            Msg::ReportHealth => self.report_health(),
+            Msg::PubSub(result) => return self.on_pubsub_update(result),
+            Msg::PubSubClosed => self.on_pubsub_closed(),
+            Msg::CheckStall => return self.check_stall(),
+            Msg::SavePosition => self.save_position(),
+            Msg::SegmentIndex(result) => self.on_segment_index(result),
+            Msg::IndexChunk(chunk, result) => self.on_index_chunk(chunk, result),
+            Msg::TimeNode(generation, result) => self.on_time_node(generation, result),
+            Msg::LiveNode(generation, cid, result) => self.on_live_node(generation, cid, result),
+            Msg::LiveSegmentNode(generation, cid, result) => {
+                self.on_live_segment_node(generation, cid, result)
+            }
+            Msg::SeekBucket(generation, path, second, result) => {
+                self.on_seek_bucket(generation, path, second, result)
+            }
+            Msg::Access(result) => return self.on_access_checked(result),
+            Msg::Interaction => self.on_interaction(),
+            Msg::VolumeChange => self.on_volume_change(),
+            Msg::Unmute => self.on_unmute(),
+            Msg::LoadedMetadata => self.on_loaded_metadata(),
+            Msg::ToggleFit => return self.on_toggle_fit(),
+            Msg::TogglePip => self.on_toggle_pip(),
+            Msg::ToggleFullscreen => self.on_toggle_fullscreen(),
+            Msg::FullscreenChange => return self.on_fullscreen_change(),
         }
 
-        false
+        // Refresh quality label and stats.
+        self.video_level() != level
+            || self.media_buffers.is_some() != ready
+            || (self.show_stats && self.avg_bitrate != avg_bitrate)
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if let Some(live) = self.live_stream.as_mut() {
+            live.streamer = props.streamer;
+        }
+
+        if props.flush_count != self.flush_count {
+            self.flush_count = props.flush_count;
+            self.flush_all();
+        }
+
         false
     }
 
     fn view(&self) -> Html {
         html! {
-            <video class="video_player" id="video_player" autoplay=true controls=true poster=self.poster_link />
+            <>
+                {
+                    match self.title.as_ref() {
+                        Some(title) => html! { <div class="video_title"> { title } </div> },
+                        None => html! {},
+                    }
+                }
+                <video class="video_player" id="video_player" autoplay=true controls=true poster=self.poster_link
+                    aria-label=self.title.as_deref().or_else(|| self.metadata.as_ref().map(|md| md.title.as_str())).unwrap_or("Video player")>
+                    {
+                        for self.subtitles.iter().enumerate().map(|(i, (language, url))| html! {
+                            <track id=format!("subtitle_{}", i) kind="subtitles" srclang=language label=language src=url />
+                        })
+                    }
+                </video>
+                {
+                    if self.subtitles.is_empty() {
+                        html! {}
+                    } else {
+                        html! {
+                            <select class="video_subtitle_select" aria-label="Select subtitles" onchange=self.link.callback(|data| Msg::SetSubtitle(selected_level(data).checked_sub(1)))>
+                                <option value="0" selected=self.subtitle.is_none()> { "Subtitles off" } </option>
+                                {
+                                    for self.subtitles.iter().enumerate().map(|(i, (language, _))| html! {
+                                        <option value=(i + 1).to_string() selected=(self.subtitle == Some(i))> { language } </option>
+                                    })
+                                }
+                            </select>
+                        }
+                    }
+                }
+                {
+                    if self.mode == PlaybackMode::Vod {
+                        html! {
+                            <select class="video_rate_select" aria-label="Select playback speed" onchange=self.link.callback(|data| Msg::SetPlaybackRate(selected_value(data).parse().unwrap_or(1.0)))>
+                                {
+                                    for PLAYBACK_RATES.iter().map(|rate| html! {
+                                        <option value=rate.to_string() selected=(*rate == self.playback_rate)> { format!("{}x", rate) } </option>
+                                    })
+                                }
+                            </select>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    match self.media_buffers.as_ref() {
+                        Some(buffers) if buffers.audio_tracks.len() > 1 => html! {
+                            <select class="video_audio_select" aria-label="Select audio language" onchange=self.link.callback(|data| Msg::SetAudioTrack(selected_value(data)))>
+                                {
+                                    for buffers.audio_tracks.iter().map(|track| html! {
+                                        <option value=track.language().unwrap_or_default().to_owned() selected=(track.name == buffers.tracks[AUDIO_LEVEL].name)> { track.language().unwrap_or("Default") } </option>
+                                    })
+                                }
+                            </select>
+                        },
+                        _ => html! {},
+                    }
+                }
+                {
+                    match self.preview_link.as_ref().filter(|_| self.previewing) {
+                        Some(link) => html! { <img class="seek_preview" src=link.clone() alt="Seek preview" /> },
+                        None => html! {},
+                    }
+                }
+                {
+                    match self.live_stream.as_ref() {
+                        Some(live) => live_status_notice(live.ended, live.stalled, live.seek_clamped),
+                        None => html! {},
+                    }
+                }
+                { locked_notice(self.locked) }
+                <button class="video_fit" aria-label="Toggle video fit" aria-pressed=(self.fit == VideoFit::Crop).to_string()
+                    onclick=self.link.callback(|_| Msg::ToggleFit)>
+                    { match self.fit { VideoFit::Letterbox => "Crop", VideoFit::Crop => "Letterbox" } }
+                </button>
+                {
+                    match (self.show_stats, self.media_buffers.as_ref().and_then(|buffers| buffers.tracks.get(self.video_level()))) {
+                        (true, Some(track)) => html! {
+                            <div class="video_stats" aria-hidden="true">
+                                { format!("{} • Download {}", track.name, self.avg_bitrate.map_or_else(|| "-".to_owned(), |avg| format_bitrate(avg as usize, self.bitrate_unit))) }
+                                {
+                                    match self.ipfs.last_endpoint() {
+                                        Some(endpoint) => html! { <span> { format!(" • Served By {:?}", endpoint) } </span> },
+                                        None => html! {},
+                                    }
+                                }
+                                {
+                                    match self.live_stream.as_ref().map(|live| live.rejected.values().sum::<usize>()) {
+                                        Some(rejected) if rejected > 0 => html! { <span> { format!(" • Rejected {}", rejected) } </span> },
+                                        _ => html! {},
+                                    }
+                                }
+                            </div>
+                        },
+                        _ => html! {},
+                    }
+                }
+                <button class="video_fullscreen" aria-label="Toggle fullscreen" aria-pressed=self.fullscreen.to_string()
+                    onclick=self.link.callback(|_| Msg::ToggleFullscreen)>
+                    { if self.fullscreen { "Exit Fullscreen" } else { "Fullscreen" } }
+                </button>
+                {
+                    if self.pip_supported {
+                        html! { <button class="video_pip" aria-label="Toggle picture-in-picture" onclick=self.link.callback(|_| Msg::TogglePip)> { "PiP" } </button> }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    match self.media_buffers.as_ref().and_then(|buffers| buffers.tracks.get(self.video_level())) {
+                        Some(track) => html! {
+                            <div class="video_quality" role="status" aria-live="polite" aria-label="Video quality"> { format!("{} • {}", quality_label(&track.name, self.auto_quality), format_bitrate(track.bandwidth, self.bitrate_unit)) } </div>
+                        },
+                        None => html! {},
+                    }
+                }
+                {
+                    match self.media_buffers.as_ref() {
+                        Some(buffers) => html! {
+                            <select class="video_quality_select" aria-label="Select video quality" onchange=self.link.callback(|data| Msg::SetLevel(selected_level(data)))>
+                                <option value="0" selected=self.auto_quality> { "Auto" } </option>
+                                {
+                                    for buffers.tracks.iter().enumerate().skip(1).map(|(level, track)| html! {
+                                        <option value=level.to_string() selected=(!self.auto_quality && self.pinned_level == level)> { &track.name } </option>
+                                    })
+                                }
+                            </select>
+                        },
+                        None => html! {},
+                    }
+                }
+                {
+                    match self.error.as_ref() {
+                        Some(error) => html! {
+                            <div class="video_error" role="alert">
+                                { error }
+                                {
+                                    if !should_retry_setup(self.setup_attempts) {
+                                        html! { <button id="video_retry" aria-label="Retry loading video" onclick=self.link.callback(|_| Msg::RetrySetup)> { "Retry" } </button> }
+                                    } else {
+                                        html! {}
+                                    }
+                                }
+                            </div>
+                        },
+                        None => html! {},
+                    }
+                }
+            </>
         }
     }
 
     fn rendered(&mut self, first_render: bool) {
+        if self.focus_retry {
+            self.focus_retry = false;
+
+            let retry = self
+                .window
+                .document()
+                .and_then(|document| document.get_element_by_id("video_retry"))
+                .and_then(|element| element.dyn_into::<HtmlElement>().ok());
+
+            if let Some(retry) = retry {
+                if let Err(e) = retry.focus() {
+                    ConsoleService::error(&format!("{:?}", e));
+                }
+            }
+        }
+
         if first_render {
             let document = self.window.document().expect("Can't get document");
 
@@ -210,20 +1230,109 @@ impl Component for VideoPlayer {
                 .dyn_into()
                 .expect("Not Media Element");
 
-            media_element.set_src(&self.object_url);
+            match (self.object_url.as_ref(), self.fallback_url.as_ref()) {
+                (Some(url), _) => media_element.set_src(url),
+                (None, Some(url)) => media_element.set_src(url),
+                (None, None) => {}
+            }
+
+            let cb = self.link.callback(|_| Msg::Seeking);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+            media_element.set_onseeking(Some(closure.as_ref().unchecked_ref()));
 
-            self.seeking_closure = match self.metadata.as_ref() {
-                Some(_) => {
-                    let cb = self.link.callback(|_| Msg::Seeking);
-                    let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
-                    media_element.set_onseeking(Some(closure.as_ref().unchecked_ref()));
+            self.seeking_closure = Some(closure);
 
-                    Some(closure)
+            let cb = self.link.callback(|_| Msg::Seeked);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+            media_element.set_onseeked(Some(closure.as_ref().unchecked_ref()));
+
+            self.seeked_closure = Some(closure);
+
+            let cb = self.link.callback(|_| Msg::LoadedMetadata);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+            media_element.set_onloadedmetadata(Some(closure.as_ref().unchecked_ref()));
+
+            self.loaded_metadata_closure = Some(closure);
+
+            if let Some(volume) = get_f64(VOLUME_KEY, self.storage.as_ref()) {
+                media_element.set_volume(volume);
+            }
+
+            // Reset to default on every load, both are set.
+            media_element.set_default_playback_rate(self.playback_rate);
+            media_element.set_playback_rate(self.playback_rate);
+
+            let cb = self.link.callback(|_| Msg::VolumeChange);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+
+            if let Err(e) = media_element
+                .add_event_listener_with_callback("volumechange", closure.as_ref().unchecked_ref())
+            {
+                ConsoleService::error(&format!("{:?}", e));
+            }
+
+            self.volume_closure = Some(closure);
+
+            if self.start_muted {
+                media_element.set_muted(true);
+                self.unmute_pending = true;
+
+                let cb = self.link.callback(|_| Msg::Unmute);
+                let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+
+                for event in UNMUTE_EVENTS.iter() {
+                    if let Err(e) = document
+                        .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+                    {
+                        ConsoleService::error(&format!("{:?}", e));
+                    }
                 }
-                None => None,
-            };
+
+                self.unmute_closure = Some(closure);
+            }
+
+            if self.auto_pause {
+                let cb = self.link.callback(|_| Msg::Interaction);
+                let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+
+                for event in INTERACTION_EVENTS.iter() {
+                    if let Err(e) = media_element
+                        .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+                    {
+                        ConsoleService::error(&format!("{:?}", e));
+                    }
+                }
+
+                self.interaction_closure = Some(closure);
+            }
+
+            let cb = self.link.callback(|_| Msg::FullscreenChange);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+
+            for event in FULLSCREEN_EVENTS.iter() {
+                if let Err(e) = document
+                    .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+                {
+                    ConsoleService::error(&format!("{:?}", e));
+                }
+            }
+
+            for event in IOS_FULLSCREEN_EVENTS.iter() {
+                if let Err(e) = media_element
+                    .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+                {
+                    ConsoleService::error(&format!("{:?}", e));
+                }
+            }
+
+            self.fullscreen_closure = Some(closure);
 
             self.media_element = Some(media_element);
+
+            if self.tick_pending {
+                self.tick_pending = false;
+                self.tick();
+            }
         }
     }
 
@@ -235,9 +1344,27 @@ impl Component for VideoPlayer {
             live.drop_sig.store(true, Ordering::Relaxed);
         }
 
+        self.cancel.cancel();
+
         if self.handle != 0 {
             self.window.clear_timeout_with_handle(self.handle);
         }
+
+        self.remove_unmute_listeners();
+        self.remove_fullscreen_listeners();
+        self.remove_source_listeners();
+        self.remove_media_listeners();
+
+        debug_assert_eq!(self.active_closures(), 0, "Closures left after teardown");
+
+        // Failures are logged, teardown continues.
+        let subtitle_urls = self.subtitles.iter().map(|(_, url)| url);
+
+        for url in self.object_url.iter().chain(subtitle_urls) {
+            if let Err(e) = Url::revoke_object_url(url) {
+                ConsoleService::error(&format!("{:?}", e));
+            }
+        }
     }
 }
 
@@ -247,27 +1374,132 @@ impl VideoPlayer {
         #[cfg(debug_assertions)]
         ConsoleService::info("On Source Open");
 
-        self.media_source.set_onsourceopen(None);
+        let media_source = self.media_source.as_ref().unwrap();
+
+        media_source.set_onsourceopen(None);
         self.source_open_closure = None;
 
-        if let Some(metadata) = self.metadata.as_ref() {
-            self.media_source.set_duration(metadata.duration);
+        let video_root = match self.video_root {
+            Some(cid) => cid,
+            None => return,
+        };
 
-            let cb = self.link.callback_once(Msg::SetupNode);
-            let client = self.ipfs.clone();
-            let cid = metadata.video.link;
+        // Otherwise the duration grows as segments are appended.
+        if let Some(duration) = self.duration {
+            media_source.set_duration(duration);
+        }
+
+        if self.mode == PlaybackMode::Vod {
+            let key = format!("{}{}", POSITION_KEY_PREFIX, video_root);
+
+            // Always resumed when the duration is unknown.
+            let end = self.duration.unwrap_or(f64::INFINITY);
+
+            match get_f64(&key, self.storage.as_ref()) {
+                Some(position) if position < end - RESUME_END_MARGIN => {
+                    self.resume_at = Some(position)
+                }
+                // Past the end or the video was re-encoded shorter.
+                Some(_) => remove_item(&key, self.storage.as_ref()),
+                None => {}
+            }
 
-            spawn_local(async move { cb.emit(client.dag_get(cid, Some(SETUP_PATH)).await) });
+            self._position_task = Some(IntervalService::spawn(
+                POSITION_SAVE_INTERVAL,
+                self.link.callback(|_| Msg::SavePosition),
+            ));
         }
+
+        let access = match self.metadata.as_ref().and_then(|md| md.access.clone()) {
+            Some(access) => access,
+            None => return self.load_setup(),
+        };
+
+        let web3 = match self.web3.clone() {
+            Some(web3) => web3,
+            None => return self.link.send_message(Msg::Access(Ok(false))),
+        };
+
+        let cb = self.link.callback_once(Msg::Access);
+
+        self.cancel
+            .spawn(async move { cb.emit(web3.is_eligible(access).await) });
     }
 
-    /// Callback when GossipSub receive an update.
-    fn on_pubsub_update(&mut self, result: Result<PubsubSubResponse, std::io::Error>) {
-        let res = match result {
-            Ok(res) => res,
+    /// Callback when wallet eligibility was checked.
+    fn on_access_checked(&mut self, result: Result<bool, web3::contract::Error>) -> bool {
+        let eligible = match result {
+            Ok(eligible) => eligible,
             Err(e) => {
                 ConsoleService::error(&format!("{:?}", e));
-                return;
+                false
+            }
+        };
+
+        if eligible {
+            self.locked = false;
+            self.load_setup();
+        } else {
+            self.locked = true;
+        }
+
+        true
+    }
+
+    /// Fetch VOD setup node.
+    fn load_setup(&mut self) {
+        let cid = match self.video_root {
+            Some(cid) => cid,
+            None => return,
+        };
+
+        let cb = self.link.callback_once(Msg::SetupNode);
+        let timeout_cb = self.link.callback_once(|_| Msg::SetupTimeout);
+        let client = self.ipfs.clone();
+        let metadata_timeout = self.metadata_timeout;
+
+        self.setup_attempts += 1;
+
+        self.cancel.spawn(async move {
+            match timeout(metadata_timeout, client.dag_get(cid, Some(SETUP_PATH))).await {
+                Some(result) => cb.emit(result),
+                None => timeout_cb.emit(()),
+            }
+        });
+    }
+
+    /// Retry loading VOD setup or show the error.
+    fn on_setup_failed(&mut self, error: &str) -> bool {
+        ConsoleService::error(error);
+
+        if should_retry_setup(self.setup_attempts) {
+            self.load_setup();
+            return false;
+        }
+
+        self.error = Some(error.to_owned());
+        self.focus_retry = true;
+
+        true
+    }
+
+    /// Callback when the user ask to retry loading VOD setup.
+    fn on_retry_setup(&mut self) -> bool {
+        self.error = None;
+        self.setup_attempts = 0;
+
+        self.load_setup();
+
+        true
+    }
+
+    /// Callback when GossipSub receive an update.
+    fn on_pubsub_update(&mut self, result: Result<PubsubSubResponse, std::io::Error>) -> bool {
+        let res = match result {
+            Ok(res) => res,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return false;
             }
         };
 
@@ -276,569 +1508,4502 @@ impl VideoPlayer {
 
         let PubsubSubResponse { from, data } = res;
 
-        let live = self.live_stream.as_mut().unwrap();
+        let live = match self.live_stream.as_mut() {
+            Some(live) => live,
+            None => {
+                ConsoleService::warn("PubSub Message Without Live Stream");
+                return false;
+            }
+        };
 
         #[cfg(debug_assertions)]
         ConsoleService::info(&format!("Sender => {}", from));
 
+        // Messages are signed by the sender's peer, only the streamer is trusted.
         if from != live.streamer_peer_id {
-            #[cfg(debug_assertions)]
-            ConsoleService::warn("Unauthorized Sender");
-            return;
+            let count = live.rejected.entry(from.clone()).or_insert(0);
+            *count += 1;
+
+            if live.audit_senders || cfg!(debug_assertions) {
+                ConsoleService::warn(&format!(
+                    "Unauthorized Sender {} Rejected {} Times",
+                    from, count
+                ));
+            }
+
+            return self.show_stats;
         }
 
-        let data = match str::from_utf8(&data) {
-            Ok(data) => data,
-            Err(e) => {
+        let now = js_sys::Date::now();
+
+        // Queued segments are too old after a long gap, resync with the live edge.
+        // After a short gap they are retained and chained.
+        if let Some(disconnected_at) = live.disconnected_at.take() {
+            if now - disconnected_at > self.reconnect_gap * 1000.0 {
                 #[cfg(debug_assertions)]
-                ConsoleService::error(&format!("{:?}", e));
-                return;
+                ConsoleService::info("Reconnected After Long Gap, Resync");
+
+                live.buffer.clear();
+                live.recent.clear();
+                live.backlog = None;
+                live.held.clear();
             }
-        };
+        }
+
+        if let Some(msg) = check_payload(&data, self.max_pubsub_payload) {
+            ConsoleService::warn(&msg);
+            return false;
+        }
 
         #[cfg(debug_assertions)]
-        ConsoleService::info(&format!("Message => {}", data));
+        ConsoleService::info(&format!("Message => {}", String::from_utf8_lossy(&data)));
 
-        let cid = match Cid::from_str(data) {
-            Ok(cid) => cid,
+        let msg = match decode_video_message(&data) {
+            Ok(msg) => msg,
             Err(e) => {
-                #[cfg(debug_assertions)]
-                ConsoleService::error(&format!("{:?}", e));
-                return;
+                ConsoleService::warn(&e);
+                return false;
             }
         };
 
-        live.buffer.push_back(cid);
+        if !signed_by_streamer(&msg, live.streamer.as_ref()) {
+            ConsoleService::warn("Video Message Not Signed By Streamer");
+            return false;
+        }
 
-        if self.media_buffers.is_none() {
-            let cb = self.link.callback_once(Msg::SetupNode);
-            let client = self.ipfs.clone();
+        if let VideoMessage::Heartbeat(signed_msg) = &msg {
+            if !is_newer_heartbeat(live.heartbeat.as_ref(), &signed_msg.data) {
+                ConsoleService::warn("Replayed Heartbeat Ignored");
+                return false;
+            }
 
-            spawn_local(async move { cb.emit(client.dag_get(cid, Some("/setup/")).await) });
+            live.heartbeat = Some(signed_msg.data);
         }
-    }
 
-    /// Callback when source buffer is done updating.
-    fn on_update_end(&mut self) {
-        #[cfg(debug_assertions)]
-        ConsoleService::info("On Update End");
+        live.last_activity = js_sys::Date::now();
 
-        self.tick()
-    }
+        let cid = match msg {
+            VideoMessage::Link(link) => link.link,
+            VideoMessage::Signed(signed_msg) => signed_msg.data.link,
+            VideoMessage::Announcement(signed_msg) => {
+                let LiveAnnouncement { title, poster } = signed_msg.data;
 
-    /// Callback when video element has seeked.
-    fn on_seeking(&mut self) {
-        #[cfg(debug_assertions)]
-        ConsoleService::info("On Seeking");
+                self.title = Some(title);
 
-        self.state = MachineState::Flush;
-    }
+                if let Some(poster) = poster {
+                    self.poster_link = poster_link(Some(poster.link));
+                }
 
-    /// Callback when 1 second has passed.
-    fn on_timeout(&mut self) {
-        #[cfg(debug_assertions)]
-        ConsoleService::info("On Timeout");
+                return true;
+            }
+            VideoMessage::Index(link) => {
+                if let Some(msg) = check_link(&link.link) {
+                    ConsoleService::warn(&msg);
+                    return false;
+                }
 
-        self.timeout_closure = None;
-        self.handle = 0;
+                let cb = self.link.callback_once(Msg::SegmentIndex);
+                let client = self.ipfs.clone();
 
-        self.tick()
-    }
+                self.cancel.spawn(async move {
+                    cb.emit(client.dag_get(link.link, Option::<String>::None).await)
+                });
 
-    /// Update state machine.
-    fn tick(&mut self) {
-        match self.state {
-            MachineState::Load => self.load_segment(),
-            MachineState::Switch => self.switch_quality(),
-            MachineState::Flush => self.flush_buffer(),
-            MachineState::Timeout => self.set_timeout(),
-            MachineState::Status => self.check_status(),
-            MachineState::AdaptativeBitrate => self.check_abr(),
+                return false;
+            }
+            VideoMessage::Heartbeat(_) => return false,
+            VideoMessage::End => {
+                ConsoleService::info("Live Stream Ended");
+
+                live.ended = true;
+                live.stalled = false;
+                return true;
+            }
+        };
+
+        if let Some(msg) = check_link(&cid) {
+            ConsoleService::warn(&msg);
+            return false;
         }
-    }
 
-    /// Set 1 second timeout.
-    fn set_timeout(&mut self) {
-        if self.timeout_closure.is_some() {
-            return;
+        let now = js_sys::Date::now();
+
+        if is_duplicate_segment(&mut live.recent, cid, now, self.dedup_window) {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Duplicate Segment Ignored");
+            return false;
         }
 
-        let cb = self.link.callback_once(|_| Msg::Timeout);
+        // Segments pile up if they cannot be loaded, the next one due is kept.
+        if !queue_live_segment(&mut live.buffer, &mut live.held, live.backlog, cid) {
+            ConsoleService::warn("Live Buffer Full, New Segment Dropped");
+            live.skipped += 1;
+        }
 
-        let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+        // Requested once until source buffers are added.
+        if self.media_buffers.is_none() && !live.setup_pending {
+            live.setup_pending = true;
 
-        match self
-            .window
-            .set_timeout_with_callback_and_timeout_and_arguments_0(
-                closure.as_ref().unchecked_ref(),
-                1000,
-            ) {
-            Ok(handle) => self.handle = handle,
-            Err(e) => ConsoleService::error(&format!("{:?}", e)),
+            let cb = self.link.callback_once(Msg::SetupNode);
+            let client = self.ipfs.clone();
+
+            self.cancel
+                .spawn(async move { cb.emit(client.dag_get(cid, Some("/setup/")).await) });
         }
 
-        self.timeout_closure = Some(closure);
+        false
     }
 
-    /// Create source buffer then load initialization segment.
-    fn add_source_buffer(&mut self, setup_node: Result<SetupNode, Error>) {
-        let setup_node = match setup_node {
-            Ok(n) => n,
-            Err(e) => {
-                ConsoleService::error(&format!("{:?}", e));
-                return;
-            }
+    /// Save VOD playback position, cleared once the end is reached.
+    fn save_position(&mut self) {
+        let (video_root, media_element) = match (self.video_root, self.media_element.as_ref()) {
+            (Some(video_root), Some(media_element)) => (video_root, media_element),
+            _ => return,
         };
 
-        #[cfg(debug_assertions)]
-        ConsoleService::info("Adding Source Buffer");
-
-        if self.media_source.ready_state() != MediaSourceReadyState::Open {
-            #[cfg(debug_assertions)]
-            ConsoleService::info("Media Source Not Open");
+        // Not started or still waiting to resume.
+        if self.resume_at.is_some() || media_element.current_time() <= 0.0 {
             return;
         }
 
-        #[cfg(debug_assertions)]
-        ConsoleService::info(&format!(
-            "Setup Node \n {}",
-            &serde_json::to_string_pretty(&setup_node).expect("Can't print")
-        ));
-
-        #[cfg(debug_assertions)]
-        ConsoleService::info("Listing Tracks");
+        let key = format!("{}{}", POSITION_KEY_PREFIX, video_root);
+        let position = media_element.current_time();
 
-        let mut audio_buffer = None;
-        let mut video_buffer = None;
+        let end = self.duration.unwrap_or(f64::INFINITY);
 
-        for (level, track) in setup_node.tracks.iter().enumerate() {
-            if !MediaSource::is_type_supported(&track.codec) {
-                ConsoleService::error(&format!("MIME Type {:?} unsupported", &track.codec));
-                continue;
-            }
+        if media_element.ended() || position >= end - RESUME_END_MARGIN {
+            return remove_item(&key, self.storage.as_ref());
+        }
 
-            #[cfg(debug_assertions)]
-            ConsoleService::info(&format!(
-                "Level {} Name {} Codec {} Bandwidth {}",
-                level, track.name, track.codec, track.bandwidth
-            ));
+        set_f64(&key, position, self.storage.as_ref());
+    }
 
-            if video_buffer.is_some() {
-                continue;
-            }
+    /// Flag the stream when the streamer went silent without ending it.
+    fn check_stall(&mut self) -> bool {
+        let live = match self.live_stream.as_mut() {
+            Some(live) => live,
+            None => return false,
+        };
 
-            if track.name == "audio" && audio_buffer.is_some() {
-                continue;
-            }
+        let silence = js_sys::Date::now() - live.last_activity;
+        let stalled = is_stalled(live.ended, silence, self.stall_window);
 
-            let source_buffer = match self.media_source.add_source_buffer(&track.codec) {
-                Ok(sb) => sb,
-                Err(e) => {
-                    ConsoleService::error(&format!("{:?}", e));
-                    return;
-                }
-            };
+        if stalled == live.stalled {
+            return false;
+        }
 
-            if track.name == "audio" {
-                audio_buffer = Some(source_buffer);
-            } else {
-                video_buffer = Some(source_buffer);
-            }
+        if stalled {
+            ConsoleService::warn(&format!("No Message From Streamer For {:.0}ms", silence));
         }
 
-        let media_buffer = MediaBuffers {
-            audio: audio_buffer.unwrap(),
-            video: video_buffer.unwrap(),
-            tracks: setup_node.tracks,
+        live.stalled = stalled;
+        true
+    }
+
+    /// Callback when the live subscription ended, it is renewed shortly after.
+    fn on_pubsub_closed(&mut self) {
+        let live = match self.live_stream.as_mut() {
+            Some(live) => live,
+            None => return,
         };
 
-        let cb = self.link.callback(|_| Msg::UpdateEnd);
-        let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
-        media_buffer
-            .video
-            .set_onupdateend(Some(closure.as_ref().unchecked_ref()));
+        ConsoleService::warn("Live Subscription Closed, Reconnecting");
 
-        self.update_end_closure = Some(closure);
+        if live.disconnected_at.is_none() {
+            live.disconnected_at = Some(js_sys::Date::now());
+        }
+    }
 
-        let audio_path = media_buffer.tracks[0]
-            .initialization_segment
-            .link
-            .to_string();
-        let video_path = media_buffer.tracks[1]
-            .initialization_segment
-            .link
-            .to_string();
+    /// Callback when IPFS dag get returns SegmentIndex node.
+    fn on_segment_index(&mut self, result: Result<SegmentIndex, Error>) {
+        let index = match result {
+            Ok(index) => index,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return;
+            }
+        };
 
-        self.media_buffers = Some(media_buffer);
-        self.state = MachineState::Load;
+        let live = match self.live_stream.as_mut() {
+            Some(live) => live,
+            None => {
+                ConsoleService::warn("No Live Stream");
+                return;
+            }
+        };
 
-        let cb = self.link.callback_once(Msg::Append);
-        let client = self.ipfs.clone();
+        if live.origin.is_none() {
+            live.origin = live
+                .first_node
+                .as_ref()
+                .and_then(|cid| index.timecode_of(cid));
+        }
 
-        spawn_local(async move { cb.emit(client.double_path_cat(audio_path, video_path).await) });
+        live.index = Some(index);
     }
 
-    /// Load either live or VOD segment.
-    fn load_segment(&mut self) {
-        if self.metadata.is_some() {
-            return self.load_vod_segment();
-        }
+    /// Fetch one chunk of the VOD index.
+    fn request_index_chunk(&self, cid: Cid, chunk: u64) {
+        let cb = self
+            .link
+            .callback_once(move |result| Msg::IndexChunk(chunk, result));
+        let client = self.ipfs.clone();
+        let path = format!("/index/chunk/{}", chunk);
 
-        self.load_live_segment()
+        self.cancel
+            .spawn(async move { cb.emit(client.dag_get(cid, Some(path)).await) });
     }
 
-    /// Try get cid from live buffer then fetch video data from ipfs.
-    fn load_live_segment(&mut self) {
-        let live = self.live_stream.as_mut().unwrap();
+    /// Callback when IPFS dag get returns a chunk of the VOD index, older videos have none.
+    fn on_index_chunk(&mut self, chunk: u64, result: Result<SegmentIndex, Error>) {
+        if self.vod_index_chunk != Some(chunk) {
+            return;
+        }
 
-        let cid = match live.buffer.pop_front() {
-            Some(cid) => cid,
-            None => return self.set_timeout(),
-        };
+        match result {
+            Ok(index) => self.vod_index = Some(index),
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                ConsoleService::info(&format!("No Segment Index {:?}", _e));
+            }
+        }
+    }
 
+    /// Callback when source buffer is done updating.
+    fn on_update_end(&mut self) {
         #[cfg(debug_assertions)]
-        ConsoleService::info("Loading Live Media Segments");
+        ConsoleService::info("On Update End");
 
-        let track_name = &self.media_buffers.as_ref().unwrap().tracks[self.level].name;
+        self.tick()
+    }
 
-        let audio_path = format!("{}/track/audio", cid.to_string());
-        let video_path = format!("{}/track/{}", cid.to_string(), track_name);
+    /// Callback when the media source ended, after the last segment or on a decode error.
+    /// No further segment is loaded until a seek flushes the buffers.
+    fn on_source_ended(&mut self) {
+        ConsoleService::error("Media Source Ended");
 
-        self.state = MachineState::AdaptativeBitrate;
-        self.ema.start_timer();
+        // Ticks wait instead of loading, the pending one is cancelled.
+        self.state = MachineState::Timeout;
 
-        let cb = self.link.callback_once(Msg::Append);
-        let client = self.ipfs.clone();
+        if self.handle != 0 {
+            self.window.clear_timeout_with_handle(self.handle);
 
-        spawn_local(async move { cb.emit(client.double_path_cat(audio_path, video_path).await) });
+            self.timeout_closure = None;
+            self.handle = 0;
+        }
     }
 
-    /// Get CID from timecode then fetch video data from ipfs.
-    fn load_vod_segment(&mut self) {
-        let metadata = self.metadata.as_ref().unwrap();
-        let buffers = self.media_buffers.as_ref().unwrap();
-
-        let track_name = &buffers.tracks[self.level].name;
+    /// Callback when the media source failed, playback cannot continue.
+    fn on_source_error(&mut self) -> bool {
+        let error = "Playback failed, the media source reported an error.";
+        ConsoleService::error(error);
+        self.error = Some(error.to_owned());
+        true
+    }
 
-        let time_ranges = match buffers.video.buffered() {
-            Ok(tm) => tm,
-            Err(_) => {
-                #[cfg(debug_assertions)]
-                ConsoleService::info("Buffer empty");
-                return;
-            }
-        };
+    /// Remove most of the back buffer when the browser refuses more data.
+    /// The failed segment is loaded again, usually from the segment cache.
+    fn on_quota_exceeded(&mut self) {
+        ConsoleService::error("Source Buffer Quota Exceeded");
 
-        let mut buff_end = 0.0;
+        let (buffers, media_element) =
+            match (self.media_buffers.as_ref(), self.media_element.as_ref()) {
+                (Some(buffers), Some(media_element)) => (buffers, media_element),
+                _ => return,
+            };
 
-        let count = time_ranges.length();
+        let end = media_element.current_time() - QUOTA_BACK_BUFFER;
 
-        if count > 0 {
-            if let Ok(end) = time_ranges.end(count - 1) {
-                buff_end = end;
-            }
+        if end <= 0.0 || buffers.updating() {
+            self.state = MachineState::Load;
+            return self.set_timeout();
         }
 
-        //if buffer is empty load at current time
-        if buff_end <= 0.0 {
-            let current_time = match self.media_element.as_ref() {
-                Some(media_element) => media_element.current_time(),
-                None => {
-                    #[cfg(debug_assertions)]
-                    ConsoleService::info("No Media Element");
-                    return;
-                }
-            };
-
-            if current_time > 1.0 {
-                buff_end = current_time - 1.0;
-            }
+        if let Err(e) = buffers.remove(0.0, end) {
+            ConsoleService::error(&format!("{:?}", e));
         }
 
-        let (hours, minutes, seconds) = seconds_to_timecode(buff_end);
+        self.state = MachineState::Load;
+    }
 
+    /// Callback when video element has seeked.
+    fn on_seeking(&mut self) -> bool {
         #[cfg(debug_assertions)]
-        ConsoleService::info(&format!(
-            "Loading Media Segments at timecode {}:{}:{}",
-            hours, minutes, seconds
-        ));
-
-        let audio_path = format!(
-            "{}/time/hour/{}/minute/{}/second/{}/video/track/audio",
-            metadata.video.link.to_string(),
-            hours,
-            minutes,
-            seconds,
-        );
-
-        let video_path = format!(
-            "{}/time/hour/{}/minute/{}/second/{}/video/track/{}",
-            metadata.video.link.to_string(),
-            hours,
-            minutes,
-            seconds,
-            track_name,
-        );
-
-        self.state = MachineState::AdaptativeBitrate;
-        self.ema.start_timer();
+        ConsoleService::info("On Seeking");
 
-        let cb = self.link.callback_once(Msg::Append);
-        let client = self.ipfs.clone();
+        match self.mode {
+            PlaybackMode::Vod if self.seek_preview => return self.preview_seek(),
+            PlaybackMode::Vod => self.request_flush(),
+            PlaybackMode::Live => {
+                self.seek_live();
+                return true;
+            }
+        }
 
-        spawn_local(async move { cb.emit(client.double_path_cat(audio_path, video_path).await) });
+        false
     }
 
-    /// Recalculate download speed then set quality level.
-    fn check_abr(&mut self) {
-        let buffers = self.media_buffers.as_ref().unwrap();
+    /// Show the preview of the seek target, the reload waits for the seek to complete.
+    fn preview_seek(&mut self) -> bool {
+        let media_element = match self.media_element.as_ref() {
+            Some(media_element) => media_element,
+            None => return false,
+        };
 
-        let bandwidth = buffers.tracks[self.level].bandwidth as f64;
+        let target = media_element.current_time();
 
-        let avg_bitrate = match self.ema.recalculate_average_speed(bandwidth) {
-            Some(at) => at,
-            None => {
-                self.state = MachineState::Status;
-                return self.tick();
-            }
+        let target_buffered = match self.media_buffers.as_ref() {
+            Some(buffers) => is_buffered(buffers.main(), target),
+            None => false,
         };
 
-        let mut next_level = 1; // start at 1 since 0 is audio
-        while let Some(next_bitrate) = buffers.tracks.get(next_level + 1).map(|t| t.bandwidth) {
-            if avg_bitrate <= next_bitrate as f64 {
-                break;
-            }
+        // The browser completes a seek outside the buffer only once data is appended there.
+        self.previewing = target_buffered;
 
-            next_level += 1;
+        if !target_buffered {
+            self.request_flush();
         }
 
-        if next_level == self.level {
-            self.state = MachineState::Status;
-            return self.tick();
-        }
+        self.preview_link = preview_at(&self.previews, target).map(str::to_owned);
 
-        self.level = next_level;
-        self.state = MachineState::Switch;
-        self.tick()
+        true
     }
 
-    /// Check buffers and current time then trigger new action.
-    fn check_status(&mut self) {
-        let buffers = self.media_buffers.as_ref().unwrap();
+    /// Callback when video element has seeked.
+    fn on_seeked(&mut self) -> bool {
+        #[cfg(debug_assertions)]
+        ConsoleService::info("On Seeked");
 
-        let time_ranges = match buffers.video.buffered() {
-            Ok(tm) => tm,
-            Err(_) => {
-                #[cfg(debug_assertions)]
-                ConsoleService::info("Buffer empty");
-                return self.set_timeout();
-            }
-        };
+        // Seeked without a preview, from a normal seek, is ignored.
+        let reload = std::mem::replace(&mut self.previewing, false);
 
-        let count = time_ranges.length();
+        if reload {
+            self.request_flush();
+        }
 
-        let mut buff_start = 0.0;
-        let mut buff_end = 0.0;
+        reload
+    }
 
-        for i in 0..count {
-            if let Ok(start) = time_ranges.start(i) {
-                buff_start = start;
+    /// Callback when the preview track is downloaded, invalid files are left out.
+    fn on_previews(&mut self, result: Result<Vec<u8>, Error>) {
+        let data = match result {
+            Ok(data) => data,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return;
             }
+        };
 
-            if let Ok(end) = time_ranges.end(i) {
-                buff_end = end;
+        match str::from_utf8(&data) {
+            Ok(text) if text.trim_start_matches('\u{feff}').starts_with("WEBVTT") => {
+                self.previews = parse_previews(text)
             }
+            _ => ConsoleService::warn("Invalid WebVTT Preview Track"),
+        }
+    }
 
-            #[cfg(debug_assertions)]
-            ConsoleService::info(&format!(
-                "Time Range {} buffers {}s to {}s",
-                i, buff_start, buff_end
-            ));
+    /// Reload the live buffer from the segment index if seeking outside buffered range.
+    fn seek_live(&mut self) {
+        let (buffers, media_element) =
+            match (self.media_buffers.as_ref(), self.media_element.as_ref()) {
+                (Some(buffers), Some(media_element)) => (buffers, media_element),
+                _ => return,
+            };
+
+        let current_time = media_element.current_time();
+
+        if let Ok(time_ranges) = buffers.main().buffered() {
+            for i in 0..time_ranges.length() {
+                if let (Ok(start), Ok(end)) = (time_ranges.start(i), time_ranges.end(i)) {
+                    if current_time >= start && current_time <= end {
+                        return;
+                    }
+                }
+            }
         }
 
-        let current_time = match self.media_element.as_ref() {
-            Some(media_element) => media_element.current_time(),
+        let live = match self.live_stream.as_mut() {
+            Some(live) => live,
             None => {
-                #[cfg(debug_assertions)]
-                ConsoleService::info("No Media Element");
-                return self.set_timeout();
+                ConsoleService::warn("No Live Stream");
+                return;
             }
         };
 
-        if current_time < buff_start {
-            let new_time = buff_start + ((buff_end - buff_start) / 2.0);
+        let (index, origin) = match (live.index.as_ref(), live.origin) {
+            (Some(index), Some(origin)) => (index, origin),
+            _ => return,
+        };
 
-            #[cfg(debug_assertions)]
-            ConsoleService::info(&format!("Forward To {}s", new_time));
+        let target = origin + current_time.max(0.0) as u64;
 
-            self.media_element
-                .as_ref()
-                .unwrap()
-                .set_current_time(new_time);
-        }
+        let (timecode, cid, clamped) = match live_seek_segment(index, target) {
+            Some(res) => res,
+            None => return,
+        };
 
-        if current_time > buff_start + BACK_BUFFER_LENGTH {
-            #[cfg(debug_assertions)]
-            ConsoleService::info("Back Buffer Full");
-            return self.flush_buffer();
-        }
+        // Snapping seeks again exactly to this segment, the message is kept.
+        if clamped {
+            ConsoleService::warn(&format!(
+                "Live Seek To {} Before Retained Window, Clamped To {}",
+                target, timecode
+            ));
 
-        if self.metadata.is_some() && buff_end >= self.metadata.as_ref().unwrap().duration {
-            #[cfg(debug_assertions)]
-            ConsoleService::info("End Of Video");
-            return;
+            live.seek_clamped = true;
+            media_element.set_current_time(timecode.saturating_sub(origin) as f64);
+        } else if target > timecode {
+            live.seek_clamped = false;
         }
 
-        if self.metadata.is_some() && current_time + FORWARD_BUFFER_LENGTH < buff_end {
-            #[cfg(debug_assertions)]
-            ConsoleService::info("Forward Buffer Full");
-            return self.set_timeout();
-        }
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("Live Seek To Segment {} => {}", timecode, cid));
 
-        self.load_segment()
+        live.backlog = seek_segments(index, timecode, &mut live.buffer, &mut live.held);
+        live.pending_offset = Some(timecode as f64 - origin as f64);
+
+        self.request_flush();
     }
 
-    /// Flush everything or just back buffer.
-    fn flush_buffer(&mut self) {
+    /// Callback when 1 second has passed.
+    fn on_timeout(&mut self) {
         #[cfg(debug_assertions)]
-        ConsoleService::info("Flushing Buffer");
-
-        let buffers = self.media_buffers.as_ref().unwrap();
-
-        let time_ranges = match buffers.video.buffered() {
-            Ok(tm) => tm,
-            Err(_) => {
-                #[cfg(debug_assertions)]
-                ConsoleService::info("Buffer empty");
-                return;
-            }
-        };
+        ConsoleService::info("On Timeout");
 
-        let count = time_ranges.length();
+        self.timeout_closure = None;
+        self.handle = 0;
 
-        let mut buff_start = 0.0;
-        let mut buff_end = 0.0;
+        self.tick()
+    }
 
-        if let Ok(start) = time_ranges.start(0) {
-            buff_start = start;
-        }
+    /// Callback when the user interacted with the video element.
+    fn on_interaction(&mut self) {
+        self.last_interaction = js_sys::Date::now();
 
-        if let Ok(end) = time_ranges.end(count - 1) {
-            buff_end = end;
+        if !self.idle {
+            return;
         }
 
-        let current_time = match self.media_element.as_ref() {
-            Some(media_element) => media_element.current_time(),
-            None => {
-                #[cfg(debug_assertions)]
-                ConsoleService::info("No Media Element");
-                return;
-            }
-        };
-
-        let back_buffer_start = current_time - BACK_BUFFER_LENGTH;
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Resume From Idle");
 
-        //full flush except if back buffer flush is possible
-        if buff_start < back_buffer_start {
-            buff_end = back_buffer_start
-        }
+        self.idle = false;
+        self.tick()
+    }
 
-        if let Err(e) = buffers.audio.remove(buff_start, buff_end) {
-            ConsoleService::error(&format!("{:?}", e));
-            return;
-        }
+    /// Callback when volume or muted changed, remember the volume chosen by the user.
+    fn on_volume_change(&mut self) {
+        let media_element = match self.media_element.as_ref() {
+            Some(media_element) => media_element,
+            None => return,
+        };
 
-        if let Err(e) = buffers.video.remove(buff_start, buff_end) {
-            ConsoleService::error(&format!("{:?}", e));
+        if media_element.muted() {
             return;
         }
 
-        self.state = MachineState::Load;
+        set_f64(VOLUME_KEY, media_element.volume(), self.storage.as_ref());
     }
 
-    /// Switch source buffer codec then load initialization segment.
-    fn switch_quality(&mut self) {
-        #[cfg(debug_assertions)]
-        ConsoleService::info("Switching Quality");
-
-        let buffers = self.media_buffers.as_ref().unwrap();
+    /// Callback on the first interaction of a player started muted.
+    fn on_unmute(&mut self) {
+        self.remove_unmute_listeners();
 
-        let track = match buffers.tracks.get(self.level) {
-            Some(track) => track,
+        let media_element = match self.media_element.as_ref() {
+            Some(media_element) => media_element,
             None => return,
         };
 
-        if let Err(e) = buffers.video.change_type(&track.codec) {
-            ConsoleService::error(&format!("{:?}", e));
+        if !self.unmute_pending {
             return;
         }
 
+        self.unmute_pending = false;
+
+        let volume = get_f64(VOLUME_KEY, self.storage.as_ref()).unwrap_or(media_element.volume());
+
         #[cfg(debug_assertions)]
-        ConsoleService::info(&format!(
-            "Level {} Name {} Codec {} Bandwidth {}",
-            self.level, track.name, track.codec, track.bandwidth
-        ));
+        ConsoleService::info("Unmute On Interaction");
 
-        let cid = track.initialization_segment.link;
+        media_element.set_volume(volume);
+        media_element.set_muted(false);
+    }
 
-        self.state = MachineState::Load;
+    fn remove_unmute_listeners(&mut self) {
+        let closure = match self.unmute_closure.take() {
+            Some(closure) => closure,
+            None => return,
+        };
 
-        let cb = self.link.callback_once(Msg::AppendVideo);
-        let client = self.ipfs.clone();
+        let document = match self.window.document() {
+            Some(document) => document,
+            None => return,
+        };
 
-        spawn_local(async move { cb.emit(client.cid_cat(cid).await) });
+        for event in UNMUTE_EVENTS.iter() {
+            if let Err(e) = document
+                .remove_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            {
+                ConsoleService::error(&format!("{:?}", e));
+            }
+        }
     }
 
-    /// Append audio and video segments to the buffers.
-    fn append_buffers(&self, response: Result<(Vec<u8>, Vec<u8>), Error>) {
-        let (mut aud_seg, mut vid_seg) = match response {
-            Ok((a, v)) => (a, v),
-            Err(e) => {
+    fn remove_source_listeners(&mut self) {
+        let media_source = match self.media_source.as_ref() {
+            Some(media_source) => media_source,
+            None => return,
+        };
+
+        media_source.set_onsourceended(None);
+        self.source_ended_closure = None;
+
+        if let Some(closure) = self.source_error_closure.take() {
+            if let Err(e) = media_source
+                .remove_event_listener_with_callback("error", closure.as_ref().unchecked_ref())
+            {
                 ConsoleService::error(&format!("{:?}", e));
-                return;
             }
+        }
+    }
+
+    fn remove_fullscreen_listeners(&mut self) {
+        let closure = match self.fullscreen_closure.take() {
+            Some(closure) => closure,
+            None => return,
         };
 
-        let buffers = self.media_buffers.as_ref().unwrap();
+        let document = match self.window.document() {
+            Some(document) => document,
+            None => return,
+        };
 
-        if let Err(e) = buffers.audio.append_buffer_with_u8_array(&mut aud_seg) {
-            ConsoleService::warn(&format!("{:#?}", e));
+        for event in FULLSCREEN_EVENTS.iter() {
+            if let Err(e) = document
+                .remove_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            {
+                ConsoleService::error(&format!("{:?}", e));
+            }
         }
 
-        if let Err(e) = buffers.video.append_buffer_with_u8_array(&mut vid_seg) {
-            ConsoleService::warn(&format!("{:#?}", e));
+        let media_element = match self.media_element.as_ref() {
+            Some(media_element) => media_element,
+            None => return,
+        };
+
+        for event in IOS_FULLSCREEN_EVENTS.iter() {
+            if let Err(e) = media_element
+                .remove_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            {
+                ConsoleService::error(&format!("{:?}", e));
+            }
         }
     }
 
-    /// Append video segments to the buffer.
-    fn append_video_buffer(&self, response: Result<Vec<u8>, Error>) {
-        let mut vid_seg = match response {
-            Ok(d) => d,
-            Err(e) => {
-                ConsoleService::error(&format!("{:?}", e));
-                return;
+    /// Unset media element, source and buffer handlers then drop their closures.
+    fn remove_media_listeners(&mut self) {
+        if let Some(media_element) = self.media_element.as_ref() {
+            media_element.set_onseeking(None);
+            media_element.set_onseeked(None);
+            media_element.set_onloadedmetadata(None);
+
+            if let Some(closure) = self.volume_closure.as_ref() {
+                if let Err(e) = media_element.remove_event_listener_with_callback(
+                    "volumechange",
+                    closure.as_ref().unchecked_ref(),
+                ) {
+                    ConsoleService::error(&format!("{:?}", e));
+                }
+            }
+
+            if let Some(closure) = self.interaction_closure.as_ref() {
+                for event in INTERACTION_EVENTS.iter() {
+                    if let Err(e) = media_element.remove_event_listener_with_callback(
+                        event,
+                        closure.as_ref().unchecked_ref(),
+                    ) {
+                        ConsoleService::error(&format!("{:?}", e));
+                    }
+                }
+            }
+        }
+
+        if let Some(media_source) = self.media_source.as_ref() {
+            media_source.set_onsourceopen(None);
+        }
+
+        if let Some(buffers) = self.media_buffers.as_ref() {
+            if let Some(video) = buffers.video.as_ref() {
+                video.set_onupdateend(None);
             }
+
+            buffers.audio.set_onupdateend(None);
+        }
+
+        self.seeking_closure = None;
+        self.seeked_closure = None;
+        self.loaded_metadata_closure = None;
+        self.volume_closure = None;
+        self.interaction_closure = None;
+        self.source_open_closure = None;
+        self.update_end_closure = None;
+        self.audio_update_end_closure = None;
+        self.timeout_closure = None;
+    }
+
+    /// Number of closures still held, zero after teardown.
+    fn active_closures(&self) -> usize {
+        [
+            &self.source_open_closure,
+            &self.source_ended_closure,
+            &self.source_error_closure,
+            &self.seeking_closure,
+            &self.seeked_closure,
+            &self.update_end_closure,
+            &self.audio_update_end_closure,
+            &self.timeout_closure,
+            &self.interaction_closure,
+            &self.volume_closure,
+            &self.unmute_closure,
+            &self.fullscreen_closure,
+            &self.loaded_metadata_closure,
+        ]
+        .iter()
+        .filter(|closure| closure.is_some())
+        .count()
+    }
+
+    /// Callback when video dimensions are known.
+    fn on_loaded_metadata(&mut self) {
+        if let (Some(position), Some(media_element)) =
+            (self.resume_at.take(), self.media_element.as_ref())
+        {
+            #[cfg(debug_assertions)]
+            ConsoleService::info(&format!("Resume At {}s", position));
+
+            media_element.set_current_time(position);
+        }
+
+        let video_element = match self
+            .media_element
+            .as_ref()
+            .and_then(|me| me.dyn_ref::<HtmlVideoElement>())
+        {
+            Some(ve) => ve,
+            None => return,
         };
 
-        let buffers = self.media_buffers.as_ref().unwrap();
+        let style = fit_style(
+            self.fit,
+            video_element.video_width(),
+            video_element.video_height(),
+        );
 
-        if let Err(e) = buffers.video.append_buffer_with_u8_array(&mut vid_seg) {
-            ConsoleService::warn(&format!("{:#?}", e));
+        if let Err(e) = video_element.set_attribute("style", &style) {
+            ConsoleService::error(&format!("{:?}", e));
         }
     }
-}
 
-/// Translate total number of seconds to timecode.
-pub fn seconds_to_timecode(seconds: f64) -> (u8, u8, u8) {
-    let rem_seconds = seconds.round();
+    /// Callback when the user switch between letterbox and crop.
+    fn on_toggle_fit(&mut self) -> bool {
+        self.fit = match self.fit {
+            VideoFit::Letterbox => VideoFit::Crop,
+            VideoFit::Crop => VideoFit::Letterbox,
+        };
 
-    let hours = (rem_seconds / 3600.0) as u8;
-    let rem_seconds = rem_seconds.rem_euclid(3600.0);
+        self.on_loaded_metadata();
 
-    let minutes = (rem_seconds / 60.0) as u8;
-    let rem_seconds = rem_seconds.rem_euclid(60.0);
+        true
+    }
 
-    let seconds = rem_seconds as u8;
+    /// Pause playback if the user has been inactive for too long.
+    fn check_idle(&mut self) -> bool {
+        if self.idle {
+            return true;
+        }
 
-    (hours, minutes, seconds)
+        let now = js_sys::Date::now();
+
+        if !self.auto_pause || now - self.last_interaction < self.idle_minutes * 60_000.0 {
+            return false;
+        }
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Idle Auto Pause");
+
+        if let Some(media_element) = self.media_element.as_ref() {
+            if let Err(e) = media_element.pause() {
+                ConsoleService::error(&format!("{:?}", e));
+            }
+        }
+
+        self.idle = true;
+
+        true
+    }
+
+    /// Update state machine.
+    fn tick(&mut self) {
+        if self.check_idle() {
+            return;
+        }
+
+        if self.media_element.is_none() {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Tick Deferred, No Media Element");
+
+            self.tick_pending = true;
+            return;
+        }
+
+        self.clamp_level();
+
+        match self.state {
+            MachineState::Load => self.load_segment(),
+            MachineState::Switch => self.switch_quality(),
+            MachineState::Flush => self.flush_buffer(),
+            MachineState::FlushAll => self.flush_all(),
+            MachineState::Timeout => self.set_timeout(),
+            MachineState::Status => self.check_status(),
+            MachineState::AdaptativeBitrate => self.check_abr(),
+        }
+    }
+
+    /// Enter or leave Picture-in-Picture, playback continues either way.
+    fn on_toggle_pip(&mut self) {
+        let (document, media_element) = match (self.window.document(), self.media_element.as_ref())
+        {
+            (Some(document), Some(media_element)) => (document, media_element),
+            _ => return,
+        };
+
+        let active = js_sys::Reflect::get(&document, &"pictureInPictureElement".into())
+            .map_or(false, |element| {
+                !element.is_null() && !element.is_undefined()
+            });
+
+        let (target, method): (JsValue, &str) = if active {
+            (document.into(), "exitPictureInPicture")
+        } else {
+            (media_element.clone().into(), "requestPictureInPicture")
+        };
+
+        let promise = js_sys::Reflect::get(&target, &method.into())
+            .and_then(|function| {
+                function
+                    .dyn_into::<js_sys::Function>()
+                    .map_err(JsValue::from)
+            })
+            .and_then(|function| function.call0(&target))
+            .and_then(|promise| promise.dyn_into::<js_sys::Promise>().map_err(JsValue::from));
+
+        let promise = match promise {
+            Ok(promise) => promise,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return;
+            }
+        };
+
+        // Rejected when blocked by the browser, for example without user activation.
+        self.cancel.spawn(async move {
+            if let Err(e) = JsFuture::from(promise).await {
+                ConsoleService::warn(&format!("Picture-in-Picture Failed {:?}", e));
+            }
+        });
+    }
+
+    /// Enter or leave fullscreen with the player overlays, iOS only supports the video element.
+    fn on_toggle_fullscreen(&mut self) {
+        let (document, media_element) = match (self.window.document(), self.media_element.as_ref())
+        {
+            (Some(document), Some(media_element)) => (document, media_element),
+            _ => return,
+        };
+
+        let (target, method): (JsValue, &str) = if self.fullscreen {
+            if has_method(&document, "exitFullscreen") {
+                (document.into(), "exitFullscreen")
+            } else {
+                (media_element.clone().into(), "webkitExitFullscreen")
+            }
+        } else {
+            match media_element.parent_element() {
+                Some(container) if has_method(&container, "requestFullscreen") => {
+                    (container.into(), "requestFullscreen")
+                }
+                _ => (media_element.clone().into(), "webkitEnterFullscreen"),
+            }
+        };
+
+        let result = js_sys::Reflect::get(&target, &method.into())
+            .and_then(|function| {
+                function
+                    .dyn_into::<js_sys::Function>()
+                    .map_err(JsValue::from)
+            })
+            .and_then(|function| function.call0(&target));
+
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return;
+            }
+        };
+
+        // Only the standard API returns a promise, rejected without user activation.
+        if let Ok(promise) = result.dyn_into::<js_sys::Promise>() {
+            self.cancel.spawn(async move {
+                if let Err(e) = JsFuture::from(promise).await {
+                    ConsoleService::warn(&format!("Fullscreen Failed {:?}", e));
+                }
+            });
+        }
+    }
+
+    /// Keep the fullscreen button in sync, playback and ABR timers are left running.
+    fn on_fullscreen_change(&mut self) -> bool {
+        let element_fullscreen = self
+            .window
+            .document()
+            .and_then(|document| js_sys::Reflect::get(&document, &"fullscreenElement".into()).ok())
+            .map_or(false, |element| {
+                !element.is_null() && !element.is_undefined()
+            });
+
+        let native_fullscreen = self
+            .media_element
+            .as_ref()
+            .and_then(|element| {
+                js_sys::Reflect::get(element, &"webkitDisplayingFullscreen".into()).ok()
+            })
+            .map_or(false, |displaying| displaying.is_truthy());
+
+        let fullscreen = element_fullscreen || native_fullscreen;
+
+        if self.fullscreen == fullscreen {
+            return false;
+        }
+
+        self.fullscreen = fullscreen;
+        true
+    }
+
+    /// Callback when a WebVTT file is downloaded, invalid files are left out.
+    fn on_subtitle(&mut self, language: String, result: Result<Vec<u8>, Error>) -> bool {
+        let data = match result {
+            Ok(data) => data,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return false;
+            }
+        };
+
+        let is_vtt = str::from_utf8(&data)
+            .map(|text| text.trim_start_matches('\u{feff}').starts_with("WEBVTT"))
+            .unwrap_or(false);
+
+        if !is_vtt {
+            ConsoleService::warn(&format!("Invalid WebVTT Subtitles For {}", language));
+            return false;
+        }
+
+        let parts = js_sys::Array::new();
+        parts.push(&js_sys::Uint8Array::from(data.as_slice()));
+
+        let mut options = BlobPropertyBag::new();
+        options.type_("text/vtt");
+
+        let url = match Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+            .and_then(|blob| Url::create_object_url_with_blob(&blob))
+        {
+            Ok(url) => url,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return false;
+            }
+        };
+
+        self.subtitles.push((language, url));
+
+        true
+    }
+
+    /// Show subtitles in one language or hide them all.
+    fn set_subtitle(&mut self, index: Option<usize>) -> bool {
+        self.subtitle = index;
+
+        let document = match self.window.document() {
+            Some(document) => document,
+            None => return true,
+        };
+
+        for i in 0..self.subtitles.len() {
+            let track = document
+                .get_element_by_id(&format!("subtitle_{}", i))
+                .and_then(|element| element.dyn_into::<HtmlTrackElement>().ok())
+                .and_then(|element| element.track());
+
+            if let Some(track) = track {
+                if index == Some(i) {
+                    track.set_mode(TextTrackMode::Showing);
+                } else {
+                    track.set_mode(TextTrackMode::Disabled);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Change audio language, the first audio track is used if the language is absent.
+    fn set_audio_track(&mut self, language: String) -> bool {
+        let buffers = match self.media_buffers.as_mut() {
+            Some(buffers) => buffers,
+            None => return false,
+        };
+
+        let track = buffers
+            .audio_tracks
+            .iter()
+            .find(|track| track.language() == Some(language.as_str()))
+            .or_else(|| buffers.audio_tracks.first());
+
+        let track = match track {
+            Some(track) if track.name != buffers.tracks[AUDIO_LEVEL].name => track.clone(),
+            _ => return false,
+        };
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("Audio Track {}", track.name));
+
+        buffers.tracks[AUDIO_LEVEL] = track;
+
+        // Buffered audio is in the old language, reload from the current time.
+        self.audio_switch_pending = true;
+        self.switch_pending = true;
+        self.flush_all();
+
+        true
+    }
+
+    /// Publish current quality, dropped segments and buffer length for the streamer.
+    fn report_health(&mut self) {
+        let (live, buffers) = match (self.live_stream.as_ref(), self.media_buffers.as_ref()) {
+            (Some(live), Some(buffers)) => (live, buffers),
+            _ => return,
+        };
+
+        let report = HealthReport {
+            track: buffers.tracks[self.video_level()].name.clone(),
+            skipped: live.skipped,
+            buffered: self.buffered_ahead(),
+        };
+
+        let json = match serde_json::to_string(&report) {
+            Ok(json) => json,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return;
+            }
+        };
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("Health Report {}", json));
+
+        let client = self.ipfs.clone();
+        let topic = live.health_topic.clone();
+
+        self.cancel.spawn(async move {
+            if let Err(e) = client.pubsub_pub(topic, json).await {
+                ConsoleService::warn(&format!("Health Report Failed {:?}", e));
+            }
+        });
+    }
+
+    /// Change VOD playback speed, kept for the session.
+    fn set_playback_rate(&mut self, rate: f64) -> bool {
+        if self.mode == PlaybackMode::Live {
+            return false;
+        }
+
+        if !PLAYBACK_RATES.contains(&rate) {
+            ConsoleService::warn(&format!("Unsupported Playback Rate {}", rate));
+            return false;
+        }
+
+        if let Some(media_element) = self.media_element.as_ref() {
+            media_element.set_default_playback_rate(rate);
+            media_element.set_playback_rate(rate);
+        }
+
+        set_f64(PLAYBACK_RATE_KEY, rate, self.session_storage.as_ref());
+
+        self.playback_rate = rate;
+        true
+    }
+
+    /// Pin a quality level or resume ABR with 0.
+    pub fn set_level(&mut self, level: usize) -> bool {
+        if level == 0 {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Automatic Quality");
+
+            self.auto_quality = true;
+            return true;
+        }
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("Quality Pinned To Level {}", level));
+
+        // Switched on next load so that appends and codec changes in flight complete first.
+        self.auto_quality = false;
+        self.pinned_level = level;
+        true
+    }
+
+    /// Current video track level.
+    fn video_level(&self) -> usize {
+        self.level
+    }
+
+    /// Set the video track level, never the audio track unless the video is audio-only.
+    fn set_video_level(&mut self, level: usize) {
+        let (track_count, min_level) = self.level_bounds();
+
+        if level < min_level {
+            ConsoleService::warn(&format!("Level {} is not a video track", level));
+        }
+
+        self.level = clamp_to_tracks(level, track_count, min_level);
+    }
+
+    /// Number of tracks and lowest video level, unbounded until the setup is loaded.
+    fn level_bounds(&self) -> (usize, usize) {
+        match self.media_buffers.as_ref() {
+            Some(buffers) => (buffers.tracks.len(), buffers.min_level()),
+            None => (usize::MAX, MIN_VIDEO_LEVEL),
+        }
+    }
+
+    /// Keep current and pinned quality levels within the available tracks.
+    fn clamp_level(&mut self) {
+        if self.media_buffers.is_none() {
+            return;
+        }
+
+        let (track_count, min_level) = self.level_bounds();
+
+        self.pinned_level = clamp_to_tracks(self.pinned_level, track_count, min_level);
+
+        let level = clamp_to_tracks(self.video_level(), track_count, min_level);
+
+        if level == self.video_level() {
+            return;
+        }
+
+        #[cfg(debug_assertions)]
+        ConsoleService::warn(&format!(
+            "Level {} out of range, clamped to {}",
+            self.video_level(),
+            level
+        ));
+
+        self.level = level;
+
+        // Video buffer codec must match the new level.
+        self.state = MachineState::Switch;
+    }
+
+    /// Set 1 second timeout.
+    fn set_timeout(&mut self) {
+        if self.timeout_closure.is_some() {
+            return;
+        }
+
+        let cb = self.link.callback_once(|_| Msg::Timeout);
+
+        let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+
+        match self
+            .window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                closure.as_ref().unchecked_ref(),
+                1000,
+            ) {
+            Ok(handle) => self.handle = handle,
+            Err(e) => ConsoleService::error(&format!("{:?}", e)),
+        }
+
+        self.timeout_closure = Some(closure);
+    }
+
+    /// Create source buffer then load initialization segment.
+    fn add_source_buffer(&mut self, setup_node: Result<SetupNode, Error>) -> bool {
+        if let Some(live) = self.live_stream.as_mut() {
+            live.setup_pending = false;
+        }
+
+        let setup_node = match setup_node {
+            Ok(n) => n,
+            Err(e) if self.mode == PlaybackMode::Vod => {
+                return self.on_setup_failed(&format!(
+                    "Not a playable video, setup node not found. {}",
+                    e
+                ));
+            }
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                self.error = Some(format!("Not a playable video, setup node not found. {}", e));
+                return true;
+            }
+        };
+
+        if self.media_buffers.is_some() {
+            return false;
+        }
+
+        if let Some(error) = check_setup(&setup_node) {
+            ConsoleService::error(&error);
+            self.error = Some(error);
+            return true;
+        }
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Adding Source Buffer");
+
+        let ready_state = self.media_source.as_ref().map(|ms| ms.ready_state());
+
+        if ready_state != Some(MediaSourceReadyState::Open) {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Media Source Not Open");
+            return false;
+        }
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("Setup Node \n {}", &debug_json(&setup_node)));
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Listing Tracks");
+
+        let mut audio_buffer = None;
+        let mut video_buffer = None;
+
+        let supported = supported_tracks(&setup_node);
+
+        for warning in check_codec_ladder(&setup_node.tracks) {
+            ConsoleService::warn(&warning);
+        }
+
+        let (audio_level, video_level) = select_tracks(&setup_node.tracks, &supported);
+
+        for (level, track) in setup_node.tracks.iter().enumerate() {
+            if !supported[level] {
+                ConsoleService::error(&format!("MIME Type {:?} unsupported", &track.codec));
+                continue;
+            }
+
+            #[cfg(debug_assertions)]
+            ConsoleService::info(&format!(
+                "Level {} Name {} Codec {} Bandwidth {}",
+                level, track.name, track.codec, track.bandwidth
+            ));
+
+            if Some(level) != audio_level && Some(level) != video_level {
+                #[cfg(debug_assertions)]
+                ConsoleService::info(&format!("Level {} no source buffer added", level));
+                continue;
+            }
+
+            let source_buffer = match self
+                .media_source
+                .as_ref()
+                .unwrap()
+                .add_source_buffer(&track.codec)
+            {
+                Ok(sb) => sb,
+                Err(e) => {
+                    ConsoleService::error(&format!("{:?}", e));
+                    return false;
+                }
+            };
+
+            // Live timestamps can restart after a discontinuity, append in sequence instead.
+            if self.mode == PlaybackMode::Live {
+                source_buffer.set_mode(SourceBufferAppendMode::Sequence);
+            }
+
+            if track.is_audio() {
+                audio_buffer = Some(source_buffer);
+            } else {
+                video_buffer = Some(source_buffer);
+            }
+        }
+
+        let has_video = setup_node.tracks.iter().any(|track| !track.is_audio());
+
+        let (audio, video) = match (audio_buffer, video_buffer) {
+            (Some(audio), Some(video)) => (audio, Some(video)),
+            (Some(audio), None) if !has_video => (audio, None),
+            (audio, video) => {
+                let missing = match (audio, video) {
+                    (None, None) => "audio or video",
+                    (None, _) => "audio",
+                    _ => "video",
+                };
+
+                let unsupported: Vec<&str> = setup_node
+                    .tracks
+                    .iter()
+                    .zip(supported.iter())
+                    .filter(|(_, supported)| !**supported)
+                    .map(|(track, _)| track.codec.as_str())
+                    .collect();
+
+                let error = format!(
+                    "Your browser can't play the {} of this video. Unsupported codecs: {}",
+                    missing,
+                    unsupported.join(", ")
+                );
+                ConsoleService::error(&error);
+                self.error = Some(error);
+                return true;
+            }
+        };
+
+        let (tracks, audio_tracks) = level_tracks(setup_node.tracks, &supported, audio_level);
+
+        let media_buffer = MediaBuffers {
+            audio,
+            video,
+            tracks,
+            audio_tracks,
+            audio_requested: 0,
+            audio_next: 0,
+            audio_pending: BTreeMap::new(),
+        };
+
+        let cb = self.link.callback(|_| Msg::UpdateEnd);
+        let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+        media_buffer
+            .main()
+            .set_onupdateend(Some(closure.as_ref().unchecked_ref()));
+
+        self.update_end_closure = Some(closure);
+
+        // Audio-only segments are appended like video segments, in order.
+        if media_buffer.video.is_some() {
+            let cb = self.link.callback(|_| Msg::AudioUpdateEnd);
+            let closure = Closure::wrap(Box::new(move || cb.emit(())) as Box<dyn Fn()>);
+            media_buffer
+                .audio
+                .set_onupdateend(Some(closure.as_ref().unchecked_ref()));
+
+            self.audio_update_end_closure = Some(closure);
+        }
+
+        let audio_path = media_buffer.tracks[AUDIO_LEVEL]
+            .initialization_segment
+            .link
+            .to_string();
+        let video_path = media_buffer.tracks[media_buffer.min_level()]
+            .initialization_segment
+            .link
+            .to_string();
+
+        let audio_only = media_buffer.video.is_none();
+
+        self.media_buffers = Some(media_buffer);
+        self.state = MachineState::Load;
+
+        let generation = self.generation;
+        let cb = self
+            .link
+            .callback_once(move |result| Msg::Append(generation, result));
+        let client = self.ipfs.clone();
+
+        if audio_only {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Audio Only");
+
+            self.set_video_level(AUDIO_LEVEL);
+            self.pinned_level = AUDIO_LEVEL;
+            self.working_level = AUDIO_LEVEL;
+
+            self.cancel.spawn(async move {
+                let result = client.path_cat(video_path).await;
+
+                cb.emit(result.map(|data| (Vec::new(), data)))
+            });
+
+            return true;
+        }
+
+        // Switched to once the first initialization segments are appended.
+        if let Some(bitrate) = self.start_bitrate.take() {
+            let level = start_level(&self.media_buffers.as_ref().unwrap().tracks, bitrate);
+
+            if level > MIN_VIDEO_LEVEL {
+                #[cfg(debug_assertions)]
+                ConsoleService::info(&format!("Starting At Level {}", level));
+
+                self.set_video_level(level);
+                self.state = MachineState::Switch;
+            }
+        }
+
+        self.cancel
+            .spawn(async move { cb.emit(client.double_path_cat(audio_path, video_path).await) });
+
+        true
+    }
+
+    /// Load either live or VOD segment.
+    fn load_segment(&mut self) {
+        if !self.auto_quality && self.video_level() != self.pinned_level {
+            self.set_video_level(self.pinned_level);
+            self.state = MachineState::Switch;
+            return self.tick();
+        }
+
+        match self.mode {
+            PlaybackMode::Vod => self.load_vod_segment(),
+            PlaybackMode::Live => self.load_live_segment(),
+        }
+    }
+
+    /// Try get cid from live buffer then fetch video data from ipfs.
+    fn load_live_segment(&mut self) {
+        let live = match self.live_stream.as_mut() {
+            Some(live) => live,
+            None => {
+                ConsoleService::warn("No Live Stream");
+                return;
+            }
+        };
+
+        // Older segments would only add latency.
+        if live.low_latency && live.buffer.len() > 1 {
+            let skip = live.buffer.len() - 1;
+            live.buffer.drain(..skip);
+            live.skipped += skip;
+
+            ConsoleService::info(&format!(
+                "Low Latency Skipped {} Segments, {} Total",
+                skip, live.skipped
+            ));
+        }
+
+        // Continue with the segments a seek could not queue at once.
+        if let (true, Some(backlog), Some(index)) =
+            (live.buffer.is_empty(), live.backlog, live.index.as_ref())
+        {
+            live.backlog =
+                queue_indexed_segments(index, Excluded(backlog), &mut live.buffer, &mut live.held);
+        }
+
+        let cid = match live.buffer.pop_front() {
+            Some(cid) => cid,
+            None => return self.set_timeout(),
+        };
+
+        if live.first_node.is_none() {
+            live.first_node = Some(cid);
+        }
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Loading Live Video Node");
+
+        self.live_node = Some(cid);
+
+        // Ticks wait for the node instead of loading the next segment.
+        self.state = MachineState::Timeout;
+
+        // The node tells if media timestamps restart before the segments are appended.
+        let generation = self.generation;
+        let cb = self
+            .link
+            .callback_once(move |result| Msg::LiveSegmentNode(generation, cid, result));
+        let client = self.ipfs.clone();
+
+        self.cancel
+            .spawn(async move { cb.emit(client.dag_get(cid, Option::<String>::None).await) });
+    }
+
+    /// Callback with the VideoNode of the next live segment, fetch its tracks.
+    fn on_live_segment_node(
+        &mut self,
+        generation: u32,
+        cid: Cid,
+        result: Result<VideoNode, Error>,
+    ) {
+        // Flushed while fetching, the segment is no longer due.
+        if generation != self.generation {
+            return;
+        }
+
+        let node = match result {
+            Ok(node) => node,
+            Err(e) => {
+                ConsoleService::warn(&format!("Live Segment Unavailable, Skipped {:?}", e));
+
+                self.state = MachineState::Load;
+                return self.set_timeout();
+            }
+        };
+
+        let buffers = match self.media_buffers.as_ref() {
+            Some(buffers) => buffers,
+            None => {
+                ConsoleService::warn("Live Segment Without Media Buffers");
+                return;
+            }
+        };
+
+        if let Some(live) = self.live_stream.as_mut() {
+            if node.discontinuity {
+                ConsoleService::warn("Live Discontinuity, Timestamps Restarted");
+            }
+
+            live.pending_offset = discontinuity_offset(
+                live.pending_offset,
+                node.discontinuity,
+                buffered_end(&buffers.audio),
+                buffered_end(buffers.main()),
+            );
+        }
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Loading Live Media Segments");
+
+        let audio_path = format!("{}/track/{}", cid, buffers.tracks[AUDIO_LEVEL].name);
+        let video_path = format!("{}/track/{}", cid, buffers.tracks[self.video_level()].name);
+
+        self.fetch_segment(audio_path, video_path, Some(cid));
+    }
+
+    /// Get CID from timecode then fetch video data from ipfs.
+    fn load_vod_segment(&mut self) {
+        let video_root = self.video_root.unwrap();
+        let buffers = self.media_buffers.as_ref().unwrap();
+
+        let audio_name = &buffers.tracks[AUDIO_LEVEL].name;
+        let track_name = &buffers.tracks[self.video_level()].name;
+
+        let time_ranges = match buffers.main().buffered() {
+            Ok(tm) => tm,
+            Err(_) => {
+                #[cfg(debug_assertions)]
+                ConsoleService::info("Buffer empty");
+                return;
+            }
+        };
+
+        let mut buff_end = 0.0;
+
+        let count = time_ranges.length();
+
+        if count > 0 {
+            if let Ok(end) = time_ranges.end(count - 1) {
+                buff_end = end;
+            }
+        }
+
+        let empty = buff_end <= 0.0;
+
+        //if buffer is empty load at current time or seek target
+        if empty {
+            let media_element = match self.media_element.as_ref() {
+                Some(media_element) => media_element,
+                None => {
+                    #[cfg(debug_assertions)]
+                    ConsoleService::info("No Media Element");
+                    return;
+                }
+            };
+
+            buff_end = first_load_time(
+                media_element.current_time(),
+                media_element.seeking(),
+                self.pre_roll,
+            );
+        }
+
+        let timecode = buff_end.round() as u64;
+        let chunk = index_chunk(timecode);
+
+        // Until the chunk arrives, segments are found with the time tree.
+        if self.vod_index_chunk != Some(chunk) {
+            self.vod_index_chunk = Some(chunk);
+            self.vod_index = None;
+
+            self.request_index_chunk(video_root, chunk);
+        }
+
+        if let Some(index) = self.vod_index.as_ref() {
+            // Seek snaps to the nearest segment, otherwise continue with the next one.
+            let segment = if empty {
+                index.nearest(timecode)
+            } else {
+                index
+                    .segments
+                    .range(timecode..)
+                    .next()
+                    .map(|(time, ipld)| (*time, ipld.link))
+            };
+
+            if let Some((_timecode, cid)) = segment {
+                #[cfg(debug_assertions)]
+                ConsoleService::info(&format!("Loading Indexed Segment {} => {}", _timecode, cid));
+
+                let audio_path = format!("{}/track/{}", cid.to_string(), audio_name);
+                let video_path = format!("{}/track/{}", cid.to_string(), track_name);
+
+                return self.fetch_segment(audio_path, video_path, None);
+            }
+        }
+
+        let (hours, minutes, seconds) = seconds_to_timecode(buff_end);
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!(
+            "Loading Media Segments at timecode {}:{}:{}",
+            hours, minutes, seconds
+        ));
+
+        // The exact second may have no node, resolve it with the minute bucket.
+        if empty {
+            return self.resolve_seek(hours, minutes, seconds);
+        }
+
+        // Checked if the segments fail to load, in case the video ended.
+        self.vod_time_path = Some(format!(
+            "/time/hour/{}/minute/{}/second/{}",
+            hours, minutes, seconds
+        ));
+
+        let audio_path = format!(
+            "{}/time/hour/{}/minute/{}/second/{}/video/track/{}",
+            video_root.to_string(),
+            hours,
+            minutes,
+            seconds,
+            audio_name,
+        );
+
+        let video_path = format!(
+            "{}/time/hour/{}/minute/{}/second/{}/video/track/{}",
+            video_root.to_string(),
+            hours,
+            minutes,
+            seconds,
+            track_name,
+        );
+
+        self.fetch_segment(audio_path, video_path, None);
+    }
+
+    /// Fetch audio and video data, giving up at the deadline if a lower quality is available.
+    fn fetch_segment(&mut self, audio_path: String, video_path: String, cid: Option<Cid>) {
+        // With a single video track there is no other level to switch to.
+        if self.media_buffers.as_ref().unwrap().tracks.len() > 2 {
+            self.state = MachineState::AdaptativeBitrate;
+            self.ema.start_timer();
+        } else {
+            self.state = MachineState::Status;
+        }
+
+        let client = self.ipfs.clone();
+
+        // The audio track is the only level, downloaded once and appended to the main buffer.
+        if self.media_buffers.as_ref().unwrap().video.is_none() {
+            let generation = self.generation;
+            let cb = self
+                .link
+                .callback_once(move |result: Result<Vec<u8>, Error>| {
+                    Msg::Append(generation, result.map(|data| (Vec::new(), data)))
+                });
+            let fetch = async move { client.path_cat(video_path).await };
+
+            return self.spawn_with_deadline(fetch, cb, cid);
+        }
+
+        // Timestamp offset must be set on both buffers before either segment is appended.
+        let offset_pending = self
+            .live_stream
+            .as_ref()
+            .map_or(false, |live| live.pending_offset.is_some());
+
+        // Audio and video segments fetched together then appended audio first.
+        if self.append_order == AppendOrder::AudioFirst || offset_pending {
+            let generation = self.generation;
+            let cb = self
+                .link
+                .callback_once(move |result| Msg::Append(generation, result));
+
+            if self.mode == PlaybackMode::Live {
+                let fetch = live_double_cat(client, audio_path, video_path);
+
+                return self.spawn_with_deadline(fetch, cb, cid);
+            }
+
+            let fetch = async move { client.double_path_cat(audio_path, video_path).await };
+
+            return self.spawn_with_deadline(fetch, cb, cid);
+        }
+
+        let buffers = self.media_buffers.as_mut().unwrap();
+        let seq = buffers.audio_requested;
+        buffers.audio_requested += 1;
+
+        let audio_cb = self
+            .link
+            .callback_once(move |result| Msg::AppendAudio(seq, result));
+        let audio_client = client.clone();
+
+        self.cancel
+            .spawn(async move { audio_cb.emit(audio_client.path_cat(audio_path).await) });
+
+        let generation = self.generation;
+        let cb = self
+            .link
+            .callback_once(move |result| Msg::AppendVideo(generation, result));
+        let fetch = async move { client.path_cat(video_path).await };
+
+        self.spawn_with_deadline(fetch, cb, cid);
+    }
+
+    /// Download then emit, giving up and lowering quality if the segment deadline passes first.
+    fn spawn_with_deadline<F, T>(&mut self, fetch: F, cb: Callback<T>, cid: Option<Cid>)
+    where
+        F: Future<Output = T> + 'static,
+        T: 'static,
+    {
+        let generation = self.generation;
+
+        let (deadline, deadline_cb) =
+            match fetch_deadline(self.segment_deadline(), self.media_timeout) {
+                FetchDeadline::Downgrade(deadline) => (
+                    deadline,
+                    self.link
+                        .callback_once(move |_| Msg::SegmentDeadline(generation, cid)),
+                ),
+                FetchDeadline::Timeout(deadline) => (
+                    deadline,
+                    self.link
+                        .callback_once(move |_| Msg::SegmentTimeout(generation, cid)),
+                ),
+            };
+
+        self.cancel.spawn(async move {
+            match timeout(deadline, fetch).await {
+                Some(result) => cb.emit(result),
+                None => deadline_cb.emit(()),
+            }
+        });
+    }
+
+    /// Deadline of the current segment download, None if quality cannot be lowered.
+    fn segment_deadline(&self) -> Option<i32> {
+        let buffers = self.media_buffers.as_ref()?;
+
+        let level = self.video_level();
+        let lower_bandwidth = buffers.tracks.get(level.saturating_sub(1))?.bandwidth;
+
+        downgrade_deadline(
+            level,
+            self.auto_quality,
+            lower_bandwidth,
+            self.ema.average(),
+            self.buffered_ahead(),
+            self.segment_deadline,
+        )
+    }
+
+    /// Check whether the time tree has a node at this path.
+    fn check_time_node(&mut self, path: String) {
+        let cid = match self.video_root {
+            Some(cid) => cid,
+            None => return,
+        };
+
+        let generation = self.generation;
+        let cb = self
+            .link
+            .callback_once(move |result| Msg::TimeNode(generation, result));
+        let client = self.ipfs.clone();
+
+        self.cancel
+            .spawn(async move { cb.emit(client.dag_path_exists(cid, path).await) });
+    }
+
+    /// Check which tracks a live segment that failed to load has.
+    fn check_live_node(&mut self, cid: Cid) {
+        let generation = self.generation;
+        let cb = self
+            .link
+            .callback_once(move |result| Msg::LiveNode(generation, cid, result));
+        let client = self.ipfs.clone();
+
+        self.cancel
+            .spawn(async move { cb.emit(client.dag_get(cid, Option::<String>::None).await) });
+    }
+
+    /// Get the minute node containing a seek target.
+    fn resolve_seek(&mut self, hours: u32, minutes: u8, seconds: u8) {
+        let cid = match self.video_root {
+            Some(cid) => cid,
+            None => return,
+        };
+
+        let path = format!("/time/hour/{}/minute/{}", hours, minutes);
+
+        let generation = self.generation;
+        let bucket = path.clone();
+        let cb = self
+            .link
+            .callback_once(move |result| Msg::SeekBucket(generation, bucket, seconds, result));
+        let client = self.ipfs.clone();
+
+        self.cancel
+            .spawn(async move { cb.emit(client.dag_get(cid, Some(path)).await) });
+    }
+
+    /// Callback with the minute node of a seek target, load the segment covering it.
+    fn on_seek_bucket(
+        &mut self,
+        generation: u32,
+        path: String,
+        second: u8,
+        result: Result<MinuteNode, Error>,
+    ) {
+        // Seeked again while resolving.
+        if generation != self.generation {
+            return;
+        }
+
+        // Without a minute node the target is past the end, unless the request failed.
+        let node = match result {
+            Ok(node) => node,
+            Err(e) => {
+                ConsoleService::warn(&format!("Seek Bucket Unavailable {:?}", e));
+                return self.check_time_node(path);
+            }
+        };
+
+        let link = match covering_second(&node, second) {
+            Some(link) => link,
+            None => {
+                ConsoleService::warn("Empty Minute Node");
+                return self.set_timeout();
+            }
+        };
+
+        #[cfg(debug_assertions)]
+        if second as usize >= node.links_to_seconds.len() {
+            ConsoleService::info(&format!(
+                "Seek Rounded To Second {}",
+                node.links_to_seconds.len().saturating_sub(1)
+            ));
+        }
+
+        let (audio_path, video_path) = match self.media_buffers.as_ref() {
+            Some(buffers) => (
+                format!("{}/video/track/{}", link, buffers.tracks[AUDIO_LEVEL].name),
+                format!(
+                    "{}/video/track/{}",
+                    link,
+                    buffers.tracks[self.video_level()].name
+                ),
+            ),
+            None => return,
+        };
+
+        self.fetch_segment(audio_path, video_path, None);
+    }
+
+    /// Callback when checking whether the time tree has a node after the last one buffered.
+    fn on_time_node(&mut self, generation: u32, result: Result<bool, Error>) {
+        // Seeked while checking, the segment that failed is no longer due.
+        if generation != self.generation {
+            return;
+        }
+
+        match result {
+            Ok(false) => {}
+            Ok(true) => {
+                ConsoleService::warn("Segment Failed To Load, Retrying");
+
+                self.state = MachineState::Load;
+                return self.set_timeout();
+            }
+            Err(e) => {
+                ConsoleService::warn(&format!("Time Tree Check Failed, Retrying {:?}", e));
+
+                self.state = MachineState::Load;
+                return self.set_timeout();
+            }
+        }
+
+        // The duration may be unknown or wrong, the time tree is authoritative.
+        #[cfg(debug_assertions)]
+        ConsoleService::info("No Further Segment, End Of Video");
+
+        let media_source = match self.media_source.as_ref() {
+            Some(media_source) => media_source,
+            None => return,
+        };
+
+        if media_source.ready_state() != MediaSourceReadyState::Open {
+            return;
+        }
+
+        if let Err(e) = media_source.end_of_stream() {
+            ConsoleService::error(&format!("{:?}", e));
+        }
+    }
+
+    /// Callback with the VideoNode of a live segment that failed to load.
+    fn on_live_node(&mut self, generation: u32, cid: Cid, result: Result<VideoNode, Error>) {
+        if generation != self.generation {
+            return;
+        }
+
+        let node = match result {
+            Ok(node) => node,
+            Err(e) => {
+                ConsoleService::warn(&format!("Live Segment Unavailable, Skipped {:?}", e));
+
+                self.state = MachineState::Load;
+                return self.set_timeout();
+            }
+        };
+
+        let buffers = match self.media_buffers.as_ref() {
+            Some(buffers) => buffers,
+            None => return,
+        };
+
+        let level = self.video_level();
+
+        // The track exists, the download itself failed.
+        if node.tracks.contains_key(&buffers.tracks[level].name) {
+            ConsoleService::warn("Live Segment Failed To Load, Skipped");
+
+            self.state = MachineState::Load;
+            return self.set_timeout();
+        }
+
+        let fallback = (MIN_VIDEO_LEVEL..buffers.tracks.len())
+            .rev()
+            .find(|l| node.tracks.contains_key(&buffers.tracks[*l].name));
+
+        let fallback = match fallback {
+            Some(fallback) => fallback,
+            None => {
+                ConsoleService::warn("Live Segment Without Known Video Track, Skipped");
+
+                self.state = MachineState::Load;
+                return self.set_timeout();
+            }
+        };
+
+        ConsoleService::warn(&format!(
+            "Live Segment Without {} Track, Falling Back To {}",
+            buffers.tracks[level].name, buffers.tracks[fallback].name
+        ));
+
+        self.set_video_level(fallback);
+
+        // Not applied again by the next load.
+        self.pinned_level = fallback;
+
+        if let Some(live) = self.live_stream.as_mut() {
+            live.buffer.push_front(cid);
+        }
+
+        self.state = MachineState::Switch;
+        self.set_timeout();
+    }
+
+    /// Callback when a segment download was too slow, retry at lower quality.
+    fn on_segment_deadline(&mut self, generation: u32, cid: Option<Cid>) {
+        // The segment was flushed, a newer download is already running.
+        if generation != self.generation {
+            return;
+        }
+
+        ConsoleService::warn("Segment Deadline Reached");
+
+        let bandwidth = self.media_buffers.as_ref().unwrap().tracks[self.video_level()].bandwidth;
+
+        // Elapsed time is a lower bound of the download time.
+        self.ema.recalculate_average_speed(bandwidth as f64);
+
+        self.requeue_segment(cid);
+
+        self.set_video_level(self.video_level() - 1);
+        self.state = MachineState::Switch;
+        self.tick()
+    }
+
+    /// Callback when a segment download timed out, retry the same segment later.
+    fn on_segment_timeout(&mut self, generation: u32, cid: Option<Cid>) {
+        if generation != self.generation {
+            return;
+        }
+
+        ConsoleService::warn("Segment Download Timed Out");
+
+        self.requeue_segment(cid);
+
+        self.state = MachineState::Load;
+        self.set_timeout()
+    }
+
+    /// Put back a segment whose download was abandoned.
+    fn requeue_segment(&mut self, cid: Option<Cid>) {
+        if let (Some(cid), Some(live)) = (cid, self.live_stream.as_mut()) {
+            live.buffer.push_front(cid);
+        }
+
+        // Audio still downloading is fetched again with the retry, skip it.
+        if self.append_order == AppendOrder::Independent {
+            let buffers = self.media_buffers.as_mut().unwrap();
+
+            if let Some(seq) = buffers.audio_requested.checked_sub(1) {
+                if seq >= buffers.audio_next {
+                    buffers.audio_pending.insert(seq, Vec::new());
+                }
+            }
+        }
+    }
+
+    /// Recalculate download speed then set quality level.
+    fn check_abr(&mut self) {
+        let buffers = self.media_buffers.as_ref().unwrap();
+
+        let bandwidth = buffers.tracks[self.video_level()].bandwidth as f64;
+
+        let avg_bitrate = self.ema.recalculate_average_speed(bandwidth);
+
+        if avg_bitrate.is_some() {
+            self.avg_bitrate = avg_bitrate;
+        }
+
+        // Average is kept current so that ABR resumes from it.
+        let avg_bitrate = match avg_bitrate {
+            Some(at) if self.auto_quality => at,
+            _ => {
+                self.state = MachineState::Status;
+                return self.tick();
+            }
+        };
+
+        let current_level = self.video_level();
+        let buffered_ahead = self.buffered_ahead();
+
+        let next_level = abr_level(
+            &buffers.tracks,
+            avg_bitrate,
+            current_level,
+            buffered_ahead,
+            self.upswitch_buffer,
+        );
+
+        #[cfg(debug_assertions)]
+        {
+            let upswitch = match buffers.tracks.get(current_level + 1) {
+                Some(next) => avg_bitrate > next.bandwidth as f64,
+                None => false,
+            };
+
+            if upswitch && buffered_ahead < self.upswitch_buffer {
+                ConsoleService::info("Buffer Too Short For Up-Switch");
+            }
+        }
+
+        if next_level == current_level {
+            self.state = MachineState::Status;
+            return self.tick();
+        }
+
+        self.set_video_level(next_level);
+        self.state = MachineState::Switch;
+        self.tick()
+    }
+
+    /// Seconds to buffer ahead at the current level, fewer at high bitrates.
+    fn forward_target(&self) -> f64 {
+        if self
+            .live_stream
+            .as_ref()
+            .map_or(false, |live| live.low_latency)
+        {
+            return MIN_FORWARD_BUFFER;
+        }
+
+        let bandwidth = match self.media_buffers.as_ref() {
+            Some(buffers) => buffers.tracks[self.video_level()].bandwidth as f64,
+            None => return self.forward_buffer,
+        };
+
+        if bandwidth <= 0.0 {
+            return self.forward_buffer;
+        }
+
+        // Seconds of this bandwidth fitting the bytes budget.
+        let budget = self.forward_buffer_bytes * 8.0 / bandwidth;
+
+        budget.min(self.forward_buffer).max(MIN_FORWARD_BUFFER)
+    }
+
+    /// Seconds of video buffered after current time.
+    fn buffered_ahead(&self) -> f64 {
+        let (buffers, media_element) =
+            match (self.media_buffers.as_ref(), self.media_element.as_ref()) {
+                (Some(buffers), Some(media_element)) => (buffers, media_element),
+                _ => return 0.0,
+            };
+
+        let time_ranges = match buffers.main().buffered() {
+            Ok(tm) => tm,
+            Err(_) => return 0.0,
+        };
+
+        let current_time = media_element.current_time();
+
+        for i in 0..time_ranges.length() {
+            if let (Ok(start), Ok(end)) = (time_ranges.start(i), time_ranges.end(i)) {
+                if current_time >= start && current_time <= end {
+                    return end - current_time;
+                }
+            }
+        }
+
+        0.0
+    }
+
+    /// Check buffers and current time then trigger new action.
+    fn check_status(&mut self) {
+        // Segments are fetched once the seek completes.
+        if self.previewing {
+            return self.set_timeout();
+        }
+
+        let buffers = self.media_buffers.as_ref().unwrap();
+
+        #[cfg(debug_assertions)]
+        log_time_ranges("Audio", &buffers.audio);
+
+        let time_ranges = match buffers.main().buffered() {
+            Ok(tm) => tm,
+            Err(_) => {
+                #[cfg(debug_assertions)]
+                ConsoleService::info("Buffer empty");
+                return self.set_timeout();
+            }
+        };
+
+        let count = time_ranges.length();
+
+        let mut buff_start = 0.0;
+        let mut buff_end = 0.0;
+
+        for i in 0..count {
+            if let Ok(start) = time_ranges.start(i) {
+                buff_start = start;
+            }
+
+            if let Ok(end) = time_ranges.end(i) {
+                buff_end = end;
+            }
+
+            #[cfg(debug_assertions)]
+            ConsoleService::info(&format!(
+                "Time Range {} buffers {}s to {}s",
+                i, buff_start, buff_end
+            ));
+        }
+
+        let current_time = match self.media_element.as_ref() {
+            Some(media_element) => media_element.current_time(),
+            None => {
+                #[cfg(debug_assertions)]
+                ConsoleService::info("No Media Element");
+                return self.set_timeout();
+            }
+        };
+
+        if current_time < buff_start {
+            let new_time = buff_start + ((buff_end - buff_start) / 2.0);
+
+            #[cfg(debug_assertions)]
+            ConsoleService::info(&format!("Forward To {}s", new_time));
+
+            self.media_element
+                .as_ref()
+                .unwrap()
+                .set_current_time(new_time);
+        }
+
+        if current_time > buff_start + self.back_buffer {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Back Buffer Full");
+            return self.flush_buffer();
+        }
+
+        if self.mode == PlaybackMode::Vod {
+            if let Some(duration) = self.duration {
+                if buff_end >= duration {
+                    #[cfg(debug_assertions)]
+                    ConsoleService::info("End Of Video");
+                    return;
+                }
+            }
+
+            // Faster playback drains the buffer sooner.
+            if current_time + self.forward_target() * self.playback_rate < buff_end {
+                #[cfg(debug_assertions)]
+                ConsoleService::info("Forward Buffer Full");
+                return self.set_timeout();
+            }
+        }
+
+        let audio_end = buffered_end(&self.media_buffers.as_ref().unwrap().audio);
+
+        // Video appended independently must not be buffered too far past audio.
+        if self.append_order == AppendOrder::Independent && buff_end - audio_end > MAX_AV_DRIFT {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Video Ahead Of Audio");
+            return self.set_timeout();
+        }
+
+        self.load_segment()
+    }
+
+    /// Flush on next tick, downloads and checks still running belong to the old position.
+    fn request_flush(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+
+        if let Some(buffers) = self.media_buffers.as_mut() {
+            buffers.audio_next = buffers.audio_requested;
+            buffers.audio_pending.clear();
+        }
+
+        self.state = MachineState::Flush;
+    }
+
+    /// Flush everything or just back buffer.
+    fn flush_buffer(&mut self) {
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Flushing Buffer");
+
+        let buffers = self.media_buffers.as_ref().unwrap();
+
+        let time_ranges = match buffers.main().buffered() {
+            Ok(tm) => tm,
+            Err(_) => {
+                #[cfg(debug_assertions)]
+                ConsoleService::info("Buffer empty");
+                return;
+            }
+        };
+
+        let count = time_ranges.length();
+
+        let mut buff_start = 0.0;
+        let mut buff_end = 0.0;
+
+        if let Ok(start) = time_ranges.start(0) {
+            buff_start = start;
+        }
+
+        if let Ok(end) = time_ranges.end(count - 1) {
+            buff_end = end;
+        }
+
+        let current_time = match self.media_element.as_ref() {
+            Some(media_element) => media_element.current_time(),
+            None => {
+                #[cfg(debug_assertions)]
+                ConsoleService::info("No Media Element");
+                return;
+            }
+        };
+
+        let back_buffer_start = current_time - self.back_buffer;
+
+        //full flush except if back buffer flush is possible
+        if buff_start < back_buffer_start {
+            buff_end = back_buffer_start
+        }
+
+        if let Err(e) = buffers.remove(buff_start, buff_end) {
+            ConsoleService::error(&format!("{:?}", e));
+            return;
+        }
+
+        self.state = state_after_flush(self.switch_pending);
+    }
+
+    /// Remove everything from both buffers then load at current time.
+    pub fn flush_all(&mut self) {
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Flushing All Buffers");
+
+        let buffers = match self.media_buffers.as_ref() {
+            Some(buffers) => buffers,
+            None => return,
+        };
+
+        // Retry on next update end.
+        if buffers.updating() {
+            self.state = MachineState::FlushAll;
+            return;
+        }
+
+        if let Err(e) = buffers.remove(0.0, f64::INFINITY) {
+            ConsoleService::error(&format!("{:?}", e));
+            return;
+        }
+
+        // Audio segments still downloading belong to the old position.
+        let buffers = self.media_buffers.as_mut().unwrap();
+        buffers.audio_next = buffers.audio_requested;
+        buffers.audio_pending.clear();
+
+        self.generation = self.generation.wrapping_add(1);
+
+        self.state = state_after_flush(self.switch_pending);
+    }
+
+    /// Switch source buffer codec then load initialization segment.
+    fn switch_quality(&mut self) {
+        #[cfg(debug_assertions)]
+        ConsoleService::info("Switching Quality");
+
+        let buffers = self.media_buffers.as_ref().unwrap();
+
+        let track = match buffers.tracks.get(self.video_level()) {
+            Some(track) => track,
+            None => return,
+        };
+
+        // Audio is left untouched, its ranges should stay continuous across the switch.
+        #[cfg(debug_assertions)]
+        log_time_ranges("Audio Before Switch", &buffers.audio);
+
+        if let Err(e) = buffers.main().change_type(&track.codec) {
+            ConsoleService::error(&format!("{:?}", e));
+            return;
+        }
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!(
+            "Level {} Name {} Codec {} Bandwidth {}",
+            self.video_level(),
+            track.name,
+            track.codec,
+            track.bandwidth
+        ));
+
+        let cid = track.initialization_segment.link;
+
+        self.state = MachineState::Load;
+        self.switch_pending = true;
+
+        let generation = self.generation;
+        let client = self.ipfs.clone();
+
+        // Without video the audio track is the level switched to.
+        if buffers.video.is_none() {
+            self.audio_switch_pending = false;
+        }
+
+        if self.audio_switch_pending {
+            let audio_track = &buffers.tracks[AUDIO_LEVEL];
+
+            if let Err(e) = buffers.audio.change_type(&audio_track.codec) {
+                ConsoleService::error(&format!("{:?}", e));
+                return;
+            }
+
+            let audio_path = audio_track.initialization_segment.link.to_string();
+            let video_path = cid.to_string();
+
+            self.audio_switch_pending = false;
+
+            let cb = self
+                .link
+                .callback_once(move |result| Msg::Append(generation, result));
+
+            self.cancel.spawn(async move {
+                cb.emit(client.double_path_cat(audio_path, video_path).await)
+            });
+
+            return;
+        }
+
+        let level = self.video_level();
+        let cb = self
+            .link
+            .callback_once(move |result| Msg::SwitchInit(generation, level, result));
+
+        self.cancel
+            .spawn(async move { cb.emit(client.cid_cat(cid).await) });
+    }
+
+    /// Callback with the initialization segment of a switch.
+    /// On failure, switch back to the last level that worked.
+    fn on_switch_init(
+        &mut self,
+        generation: u32,
+        level: usize,
+        response: Result<Vec<u8>, Error>,
+    ) -> bool {
+        let e = match response {
+            Ok(data) => {
+                if generation == self.generation {
+                    self.working_level = level;
+                }
+
+                return self.append_video_buffer(generation, Ok(data));
+            }
+            Err(e) => e,
+        };
+
+        ConsoleService::error(&format!("{:?}", e));
+
+        if generation != self.generation {
+            return false;
+        }
+
+        if level != self.working_level {
+            ConsoleService::warn(&format!(
+                "Level {} Unavailable, Back To Level {}",
+                level, self.working_level
+            ));
+
+            self.set_video_level(self.working_level);
+
+            // Not applied again by the next load.
+            self.pinned_level = self.working_level;
+        }
+
+        // Retry the switch, codec and initialization segment, in a moment.
+        self.state = MachineState::Switch;
+        self.set_timeout();
+
+        true
+    }
+
+    /// Append audio and video segments to the buffers.
+    fn append_buffers(
+        &mut self,
+        generation: u32,
+        response: Result<(Vec<u8>, Vec<u8>), Error>,
+    ) -> bool {
+        if generation != self.generation {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Flushed Segments Dropped");
+            return false;
+        }
+
+        let time_path = self.vod_time_path.take();
+        let live_node = self.live_node.take();
+
+        let (mut aud_seg, mut vid_seg) = match response {
+            Ok((a, v)) => (a, v),
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+
+                if let Some(path) = time_path {
+                    self.check_time_node(path);
+                }
+
+                if let Some(cid) = live_node {
+                    self.check_live_node(cid);
+                }
+
+                return false;
+            }
+        };
+
+        let buffers = self.media_buffers.as_ref().unwrap();
+
+        let container_error = check_container(&aud_seg, &buffers.tracks[AUDIO_LEVEL].codec)
+            .or_else(|| check_container(&vid_seg, &buffers.tracks[self.video_level()].codec));
+
+        if let Some(error) = container_error {
+            ConsoleService::error(&error);
+            self.error = Some(error);
+            return true;
+        }
+
+        if let Some(offset) = self
+            .live_stream
+            .as_mut()
+            .and_then(|live| live.pending_offset.take())
+        {
+            if let Err(e) = buffers.audio.set_timestamp_offset(offset) {
+                ConsoleService::error(&format!("{:?}", e));
+            }
+
+            if let Some(video) = buffers.video.as_ref() {
+                if let Err(e) = video.set_timestamp_offset(offset) {
+                    ConsoleService::error(&format!("{:?}", e));
+                }
+            }
+        }
+
+        // Empty if the live node had no audio track.
+        if !aud_seg.is_empty() {
+            if let Err(e) = buffers.audio.append_buffer_with_u8_array(&mut aud_seg) {
+                if is_quota_exceeded(&e) {
+                    self.on_quota_exceeded();
+                    return false;
+                }
+
+                ConsoleService::warn(&format!("{:#?}", e));
+            }
+        }
+
+        if let Err(e) = buffers.main().append_buffer_with_u8_array(&mut vid_seg) {
+            if is_quota_exceeded(&e) {
+                self.on_quota_exceeded();
+                return false;
+            }
+
+            ConsoleService::warn(&format!("{:#?}", e));
+            return false;
+        }
+
+        // Initialization segments of an audio track change are appended together.
+        self.switch_pending = false;
+
+        false
+    }
+
+    /// Queue audio segments then append them in the order they were requested.
+    fn append_audio_buffer(&mut self, seq: u64, response: Result<Vec<u8>, Error>) -> bool {
+        let buffers = self.media_buffers.as_mut().unwrap();
+
+        // Flushed or skipped while downloading.
+        if seq < buffers.audio_next || buffers.audio_pending.contains_key(&seq) {
+            return false;
+        }
+
+        let aud_seg = match response {
+            Ok(d) => d,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                Vec::new()
+            }
+        };
+
+        if let Some(error) = check_container(&aud_seg, &buffers.tracks[AUDIO_LEVEL].codec) {
+            ConsoleService::error(&error);
+            self.error = Some(error);
+            return true;
+        }
+
+        buffers.audio_pending.insert(seq, aud_seg);
+
+        self.append_pending_audio();
+
+        false
+    }
+
+    /// Append the next queued audio segment once the buffer is ready.
+    fn append_pending_audio(&mut self) {
+        let buffers = match self.media_buffers.as_mut() {
+            Some(buffers) => buffers,
+            None => return,
+        };
+
+        while !buffers.audio.updating() {
+            let mut aud_seg =
+                match next_audio_segment(&mut buffers.audio_pending, &mut buffers.audio_next) {
+                    Some(data) => data,
+                    None => return,
+                };
+
+            if let Err(e) = buffers.audio.append_buffer_with_u8_array(&mut aud_seg) {
+                if is_quota_exceeded(&e) {
+                    return self.on_quota_exceeded();
+                }
+
+                ConsoleService::warn(&format!("{:#?}", e));
+            }
+        }
+    }
+
+    /// Append video segments to the buffer.
+    fn append_video_buffer(&mut self, generation: u32, response: Result<Vec<u8>, Error>) -> bool {
+        if generation != self.generation {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("Flushed Segment Dropped");
+            return false;
+        }
+
+        let time_path = self.vod_time_path.take();
+        let live_node = self.live_node.take();
+
+        let mut vid_seg = match response {
+            Ok(d) => d,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+
+                if let Some(path) = time_path {
+                    self.check_time_node(path);
+                }
+
+                if let Some(cid) = live_node {
+                    self.check_live_node(cid);
+                }
+
+                return false;
+            }
+        };
+
+        let buffers = self.media_buffers.as_ref().unwrap();
+
+        if let Some(error) = check_container(&vid_seg, &buffers.tracks[self.video_level()].codec) {
+            ConsoleService::error(&error);
+            self.error = Some(error);
+            return true;
+        }
+
+        if let Err(e) = buffers.main().append_buffer_with_u8_array(&mut vid_seg) {
+            if is_quota_exceeded(&e) {
+                self.on_quota_exceeded();
+                return false;
+            }
+
+            ConsoleService::warn(&format!("{:#?}", e));
+            return false;
+        }
+
+        // The switch is complete once its initialization segment is in.
+        self.switch_pending = false;
+
+        false
+    }
+}
+
+/// Segment container formats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Mp4,
+    MpegTs,
+}
+
+/// Detect segment container from its first bytes.
+fn detect_container(data: &[u8]) -> Option<Container> {
+    // ISO BMFF boxes; size then type.
+    if let Some(box_type) = data.get(4..8) {
+        if [b"ftyp", b"styp", b"moof", b"moov", b"sidx"]
+            .iter()
+            .any(|t| t[..] == *box_type)
+        {
+            return Some(Container::Mp4);
+        }
+    }
+
+    // MPEG-TS packets of 188 bytes starting with sync byte.
+    if data.first() == Some(&0x47) && (data.len() < 189 || data[188] == 0x47) {
+        return Some(Container::MpegTs);
+    }
+
+    None
+}
+
+/// Returns an error message if segment container and declared MIME type mismatch.
+fn check_container(data: &[u8], mime_type: &str) -> Option<String> {
+    let declared = if mime_type.contains("mp2t") {
+        Container::MpegTs
+    } else {
+        Container::Mp4
+    };
+
+    let detected = detect_container(data)?;
+
+    if detected == declared {
+        return None;
+    }
+
+    let msg = match detected {
+        Container::MpegTs => format!(
+            "MPEG-TS segments are not supported, stream must use fragmented MP4 (declared {})",
+            mime_type
+        ),
+        Container::Mp4 => format!(
+            "Segment container is MP4 but declared MIME type is {}",
+            mime_type
+        ),
+    };
+
+    Some(msg)
+}
+
+/// Log every buffered time range of a source buffer.
+#[cfg(debug_assertions)]
+fn log_time_ranges(name: &str, buffer: &SourceBuffer) {
+    let time_ranges = match buffer.buffered() {
+        Ok(tm) => tm,
+        Err(_) => return,
+    };
+
+    for i in 0..time_ranges.length() {
+        if let (Ok(start), Ok(end)) = (time_ranges.start(i), time_ranges.end(i)) {
+            ConsoleService::info(&format!(
+                "{} Time Range {} buffers {}s to {}s",
+                name, i, start, end
+            ));
+        }
+    }
+}
+
+/// Download both live segments, video is still returned if the node has no audio track.
+async fn live_double_cat(
+    client: IpfsService,
+    audio_path: String,
+    video_path: String,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let (audio, video) = join!(client.path_cat(audio_path), client.path_cat(video_path));
+
+    match (audio, video) {
+        (Ok(audio), Ok(video)) => Ok((audio, video)),
+        (Err(e), Ok(video)) => {
+            ConsoleService::warn(&format!("Live Segment Without Audio, Video Only {:?}", e));
+            Ok((Vec::new(), video))
+        }
+        (_, Err(e)) => Err(e),
+    }
+}
+
+/// Highest level the average bitrate allows.
+/// Up-switching with a shallow buffer risks a stall, the current level is kept.
+fn abr_level(
+    tracks: &[Track],
+    avg_bitrate: f64,
+    current_level: usize,
+    buffered_ahead: f64,
+    upswitch_buffer: f64,
+) -> usize {
+    let mut next_level = MIN_VIDEO_LEVEL;
+    while let Some(next_bitrate) = tracks.get(next_level + 1).map(|t| t.bandwidth) {
+        if avg_bitrate <= next_bitrate as f64 {
+            break;
+        }
+
+        next_level += 1;
+    }
+
+    if next_level > current_level && buffered_ahead < upswitch_buffer {
+        return current_level;
+    }
+
+    next_level
+}
+
+/// Cues of a WebVTT preview track, each one is an image link shown from start to end.
+fn parse_previews(text: &str) -> Vec<(f64, f64, String)> {
+    let mut previews = Vec::new();
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let mut times = line.split("-->");
+
+        let (start, end) = match (times.next(), times.next()) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+
+        // Cue settings follow the end time.
+        let end = end.split_whitespace().next().unwrap_or_default();
+
+        let (start, end) = match (parse_vtt_time(start), parse_vtt_time(end)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+
+        let link = match lines.next().map(str::trim).filter(|link| !link.is_empty()) {
+            Some(link) if link.contains("://") => link.to_owned(),
+            Some(link) => format!("ipfs://{}", link),
+            None => continue,
+        };
+
+        previews.push((start, end, link));
+    }
+
+    previews
+}
+
+/// Seconds of a WebVTT timestamp, hours are optional.
+fn parse_vtt_time(timestamp: &str) -> Option<f64> {
+    timestamp
+        .trim()
+        .rsplit(':')
+        .enumerate()
+        .try_fold(0.0, |total, (i, part)| {
+            if i > 2 {
+                return None;
+            }
+
+            let value: f64 = part.parse().ok()?;
+
+            Some(total + value * 60f64.powi(i as i32))
+        })
+}
+
+/// Image link of the preview at this time, if any.
+fn preview_at(previews: &[(f64, f64, String)], time: f64) -> Option<&str> {
+    previews
+        .iter()
+        .find(|(start, end, _)| time >= *start && time < *end)
+        .map(|(_, _, link)| link.as_str())
+}
+
+/// True if this time is within a buffered range.
+fn is_buffered(buffer: &SourceBuffer, time: f64) -> bool {
+    let time_ranges = match buffer.buffered() {
+        Ok(tm) => tm,
+        Err(_) => return false,
+    };
+
+    (0..time_ranges.length()).any(|i| match (time_ranges.start(i), time_ranges.end(i)) {
+        (Ok(start), Ok(end)) => time >= start && time <= end,
+        _ => false,
+    })
+}
+
+/// End of the last buffered range or 0 if empty.
+fn buffered_end(buffer: &SourceBuffer) -> f64 {
+    let time_ranges = match buffer.buffered() {
+        Ok(tm) => tm,
+        Err(_) => return 0.0,
+    };
+
+    match time_ranges.length() {
+        0 => 0.0,
+        count => time_ranges.end(count - 1).unwrap_or_default(),
+    }
+}
+
+/// Timestamp offset to set before appending a live segment.
+/// After a discontinuity both tracks restart together where the longest one ends.
+/// An offset already pending, from a seek, is kept.
+fn discontinuity_offset(
+    pending: Option<f64>,
+    discontinuity: bool,
+    audio_end: f64,
+    video_end: f64,
+) -> Option<f64> {
+    if pending.is_some() || !discontinuity {
+        return pending;
+    }
+
+    Some(audio_end.max(video_end))
+}
+
+/// Decode live video message, either a raw CID or a versioned message.
+/// Returns an error message if malformed or wrongly signed.
+fn decode_video_message(data: &[u8]) -> Result<VideoMessage, String> {
+    if let Ok(text) = str::from_utf8(data) {
+        if let Ok(cid) = Cid::from_str(text) {
+            return Ok(VideoMessage::Link(cid.into()));
+        }
+    }
+
+    let msg: VideoMessage = match serde_json::from_slice(data) {
+        Ok(msg) => msg,
+        Err(e) => return Err(format!("Malformed Video Message {:?}", e)),
+    };
+
+    let verified = match &msg {
+        VideoMessage::Signed(signed_msg) => signed_msg.verify(),
+        VideoMessage::Announcement(signed_msg) => signed_msg.verify(),
+        VideoMessage::Heartbeat(signed_msg) => signed_msg.verify(),
+        _ => true,
+    };
+
+    if !verified {
+        return Err("Video Message Signature Invalid".to_owned());
+    }
+
+    Ok(msg)
+}
+
+/// An interrupted switch must append its initialization segment before any media.
+fn state_after_flush(switch_pending: bool) -> MachineState {
+    if switch_pending {
+        MachineState::Switch
+    } else {
+        MachineState::Load
+    }
+}
+
+/// Queue a segment received from the streamer, returns false if dropped because the queue is full.
+/// Segments are held while a seek backlog is loaded from the index.
+fn queue_live_segment(
+    buffer: &mut VecDeque<Cid>,
+    held: &mut VecDeque<Cid>,
+    backlog: Option<u64>,
+    cid: Cid,
+) -> bool {
+    if backlog.is_some() {
+        // The oldest held segments are the most likely to be in the next index.
+        if held.len() >= LIVE_BUFFER_CAPACITY {
+            held.pop_front();
+        }
+
+        held.push_back(cid);
+        return true;
+    }
+
+    if buffer.len() >= LIVE_BUFFER_CAPACITY {
+        return false;
+    }
+
+    buffer.push_back(cid);
+
+    true
+}
+
+/// Returns true if the segment was already received within the window, otherwise records it.
+fn is_duplicate_segment(
+    recent: &mut VecDeque<(Cid, f64)>,
+    cid: Cid,
+    now: f64,
+    window: f64,
+) -> bool {
+    let window_start = now - window * 1000.0;
+
+    while let Some((_, time)) = recent.front() {
+        if *time >= window_start {
+            break;
+        }
+
+        recent.pop_front();
+    }
+
+    if recent.iter().any(|(recent, _)| *recent == cid) {
+        return true;
+    }
+
+    recent.push_back((cid, now));
+
+    false
+}
+
+/// Next queued audio segment in request order, failed or skipped downloads are left empty and passed.
+fn next_audio_segment(pending: &mut BTreeMap<u64, Vec<u8>>, next: &mut u64) -> Option<Vec<u8>> {
+    loop {
+        let data = pending.remove(next)?;
+
+        *next += 1;
+
+        if !data.is_empty() {
+            return Some(data);
+        }
+    }
+}
+
+/// Timecode and CID of the segment a live seek reloads from, true if clamped to the retained window.
+/// Nearest indexed segment at or before target in case of gaps, the earliest one if older segments are no longer retained.
+fn live_seek_segment(index: &SegmentIndex, target: u64) -> Option<(u64, Cid, bool)> {
+    let (timecode, cid) = index.lookup(target)?;
+
+    Some((timecode, cid, target < timecode))
+}
+
+/// Node of the second covering a seek target in this minute, the last one past a partial final minute.
+fn covering_second(minute: &MinuteNode, second: u8) -> Option<Cid> {
+    let seconds = &minute.links_to_seconds;
+
+    seconds
+        .get(second as usize)
+        .or_else(|| seconds.last())
+        .map(|link| link.link)
+}
+
+/// Replace queued segments with the indexed ones starting at this timecode.
+/// Queued segments newer than the index are held to be loaded after them.
+fn seek_segments(
+    index: &SegmentIndex,
+    timecode: u64,
+    buffer: &mut VecDeque<Cid>,
+    held: &mut VecDeque<Cid>,
+) -> Option<u64> {
+    held.extend(
+        buffer
+            .drain(..)
+            .filter(|cid| index.timecode_of(cid).is_none()),
+    );
+
+    queue_indexed_segments(index, Included(timecode), buffer, held)
+}
+
+/// Queue indexed segments starting from this timecode until the queue is full,
+/// then the held segments the index does not have yet.
+/// Returns the timecode of the last segment queued if the index has more.
+fn queue_indexed_segments(
+    index: &SegmentIndex,
+    start: Bound<u64>,
+    buffer: &mut VecDeque<Cid>,
+    held: &mut VecDeque<Cid>,
+) -> Option<u64> {
+    let mut last = None;
+
+    for (timecode, ipld) in index.segments.range((start, Unbounded)) {
+        if buffer.len() >= LIVE_BUFFER_CAPACITY {
+            return last;
+        }
+
+        buffer.push_back(ipld.link);
+        last = Some(*timecode);
+    }
+
+    buffer.extend(
+        held.drain(..)
+            .filter(|cid| index.timecode_of(cid).is_none()),
+    );
+
+    None
+}
+
+/// Signed messages must come from the streamer address once it is known.
+/// Announcements are displayed as stream info so they also require a known streamer.
+fn signed_by_streamer(msg: &VideoMessage, streamer: Option<&Address>) -> bool {
+    let signer = match msg {
+        VideoMessage::Signed(signed_msg) => &signed_msg.address,
+        VideoMessage::Announcement(signed_msg) => &signed_msg.address,
+        VideoMessage::Heartbeat(signed_msg) => &signed_msg.address,
+        _ => return true,
+    };
+
+    match streamer {
+        Some(streamer) => streamer == signer,
+        None => !matches!(msg, VideoMessage::Announcement(_)),
+    }
+}
+
+/// Heartbeats must be newer than the last one, the sequence restarts with the streamer.
+fn is_newer_heartbeat(last: Option<&LiveHeartbeat>, heartbeat: &LiveHeartbeat) -> bool {
+    match last {
+        Some(last) => heartbeat.sequence > last.sequence || heartbeat.timestamp > last.timestamp,
+        None => true,
+    }
+}
+
+/// No message for longer than the window, in seconds, unless the stream ended.
+fn is_stalled(ended: bool, silence_ms: f64, stall_window: f64) -> bool {
+    !ended && silence_ms > stall_window * 1000.0
+}
+
+/// Returns None if Media Source Extensions are unavailable.
+fn create_media_source(window: &Window) -> Option<MediaSource> {
+    match js_sys::Reflect::has(window, &"MediaSource".into()) {
+        Ok(true) => {}
+        _ => {
+            ConsoleService::warn("Media Source Extensions Unsupported");
+            return None;
+        }
+    }
+
+    match MediaSource::new() {
+        Ok(media_source) => Some(media_source),
+        Err(e) => {
+            ConsoleService::error(&format!("{:?}", e));
+            None
+        }
+    }
+}
+
+/// Live stream state announced to screen readers, the most important first.
+fn live_status_notice(ended: bool, stalled: bool, seek_clamped: bool) -> Html {
+    let status = match (ended, stalled, seek_clamped) {
+        (true, _, _) => "Stream ended.",
+        (_, true, _) => "Stream may have stopped.",
+        (_, _, true) => "Beginning of available stream.",
+        _ => return html! {},
+    };
+
+    html! { <div class="video_live_status" role="status"> { status } </div> }
+}
+
+/// Alert shown instead of playback when the wallet does not meet the access requirement.
+fn locked_notice(locked: bool) -> Html {
+    if locked {
+        html! { <div class="video_locked" role="alert"> { "🔒 Locked, your wallet does not meet this video's access requirement." } </div> }
+    } else {
+        html! {}
+    }
+}
+
+/// Level of the selected option, 0 for automatic.
+fn selected_level(data: ChangeData) -> usize {
+    selected_value(data).parse().unwrap_or(0)
+}
+
+fn selected_value(data: ChangeData) -> String {
+    match data {
+        ChangeData::Select(select) => select.value(),
+        ChangeData::Value(value) => value,
+        ChangeData::Files(_) => String::new(),
+    }
+}
+
+/// Current track name, prefixed by Auto when ABR chooses the level.
+fn quality_label(track_name: &str, auto: bool) -> String {
+    if auto {
+        format!("Auto ({})", track_name)
+    } else {
+        track_name.to_owned()
+    }
+}
+
+/// Image link if it can be displayed, otherwise the default poster.
+/// The browser hides the poster once playback starts.
+fn poster_link(image: Option<Cid>) -> String {
+    match image {
+        Some(cid) if cid.codec() == RAW || cid.codec() == DAG_PB => format!("ipfs://{}", cid),
+        Some(cid) => {
+            ConsoleService::warn(&format!("Unsupported Poster Codec {:#x}", cid.codec()));
+            format!("ipfs://{}", DEFAULT_POSTER)
+        }
+        None => format!("ipfs://{}", DEFAULT_POSTER),
+    }
+}
+
+/// Returns an error message if the link points to a node the player cannot decode.
+fn check_link(cid: &Cid) -> Option<String> {
+    if cid.codec() != DAG_CBOR {
+        return Some(format!("Unsupported Codec {:#x}", cid.codec()));
+    }
+
+    None
+}
+
+/// Returns an error message if the message is too big to be parsed.
+fn check_payload(data: &[u8], max_size: usize) -> Option<String> {
+    if data.len() > max_size {
+        return Some(format!("Oversized Video Message {} bytes", data.len()));
+    }
+
+    None
+}
+
+/// Returns an error message if the setup node has nothing to play, as when truncated or malformed.
+fn check_setup(setup: &SetupNode) -> Option<String> {
+    if setup.tracks.is_empty() {
+        return Some("No playable tracks, the video setup is empty.".to_owned());
+    }
+
+    None
+}
+
+/// Returns for each track of this setup whether the browser can play its codec.
+pub fn supported_tracks(setup: &SetupNode) -> Vec<bool> {
+    tracks_supported_by(&setup.tracks, MediaSource::is_type_supported)
+}
+
+fn tracks_supported_by<F>(tracks: &[Track], is_type_supported: F) -> Vec<bool>
+where
+    F: Fn(&str) -> bool,
+{
+    tracks
+        .iter()
+        .map(|track| is_type_supported(&track.codec))
+        .collect()
+}
+
+/// Returns the levels of the first supported audio track and first supported video track.
+/// Other tracks share these source buffers.
+fn select_tracks(tracks: &[Track], supported: &[bool]) -> (Option<usize>, Option<usize>) {
+    let mut audio = None;
+    let mut video = None;
+
+    for (level, track) in tracks.iter().enumerate() {
+        if !supported[level] {
+            continue;
+        }
+
+        if track.is_audio() {
+            audio = audio.or(Some(level));
+        } else {
+            video = video.or(Some(level));
+        }
+    }
+
+    (audio, video)
+}
+
+/// Quality levels, the selected audio track at AUDIO_LEVEL then video tracks, and supported audio tracks.
+/// Alternate languages share the audio source buffer, only the selected one is a level.
+fn level_tracks(
+    setup_tracks: Vec<Track>,
+    supported: &[bool],
+    audio_level: Option<usize>,
+) -> (Vec<Track>, Vec<Track>) {
+    let mut tracks = Vec::with_capacity(setup_tracks.len());
+    let mut audio_tracks = Vec::new();
+    let mut selected_audio = None;
+
+    for (level, track) in setup_tracks.into_iter().enumerate() {
+        if !track.is_audio() {
+            tracks.push(track);
+            continue;
+        }
+
+        if supported[level] {
+            audio_tracks.push(track.clone());
+        }
+
+        if Some(level) == audio_level {
+            selected_audio = Some(track);
+        }
+    }
+
+    // Setup order is not guaranteed, audio could come after the video tracks.
+    if let Some(track) = selected_audio {
+        tracks.insert(AUDIO_LEVEL, track);
+    }
+
+    (tracks, audio_tracks)
+}
+
+/// H.264 profile and level from a MIME type like `video/mp4; codecs="avc1.64001F"`.
+fn avc_profile_level(mime_type: &str) -> Option<(u8, u8)> {
+    let codecs = mime_type
+        .split("codecs=")
+        .nth(1)?
+        .trim_matches(|c| c == '"' || c == '\'');
+
+    let avc = codecs
+        .split(',')
+        .map(str::trim)
+        .find(|codec| codec.starts_with("avc1.") || codec.starts_with("avc3."))?;
+
+    let hex = avc.get(5..11)?;
+
+    let profile = u8::from_str_radix(&hex[0..2], 16).ok()?;
+    let level = u8::from_str_radix(&hex[4..6], 16).ok()?;
+
+    Some((profile, level))
+}
+
+/// Order H.264 profiles by quality, baseline being the lowest.
+fn avc_profile_rank(profile: u8) -> u8 {
+    match profile {
+        66 => 0,      // Baseline
+        77 | 88 => 1, // Main, Extended
+        100 => 2,     // High
+        _ => 3,       // High 10, 4:2:2, 4:4:4
+    }
+}
+
+/// Warn when a higher bandwidth variant has a lower profile or level than the one below it.
+/// Tracks are sorted by bandwidth, variants with unknown codecs are skipped.
+fn check_codec_ladder(tracks: &[Track]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut previous: Option<(&Track, u8, u8)> = None;
+
+    for track in tracks.iter().filter(|track| !track.is_audio()) {
+        let (profile, level) = match avc_profile_level(&track.codec) {
+            Some(res) => res,
+            None => continue,
+        };
+
+        if let Some((prev, prev_profile, prev_level)) = previous {
+            if avc_profile_rank(profile) < avc_profile_rank(prev_profile) {
+                warnings.push(format!(
+                    "Variant {} has a lower profile ({}) than {} ({})",
+                    track.name, profile, prev.name, prev_profile
+                ));
+            }
+
+            if level < prev_level {
+                warnings.push(format!(
+                    "Variant {} has a lower level ({}) than {} ({})",
+                    track.name, level, prev.name, prev_level
+                ));
+            }
+        }
+
+        previous = Some((track, profile, level));
+    }
+
+    warnings
+}
+
+/// Video element style respecting source aspect ratio.
+fn fit_style(fit: VideoFit, width: u32, height: u32) -> String {
+    let object_fit = match fit {
+        VideoFit::Letterbox => "contain",
+        VideoFit::Crop => "cover",
+    };
+
+    if width == 0 || height == 0 {
+        return format!("object-fit: {};", object_fit);
+    }
+
+    format!(
+        "object-fit: {}; aspect-ratio: {} / {};",
+        object_fit, width, height
+    )
+}
+
+/// Time of the first segment to load into an empty buffer.
+/// Seek targets load the segment containing them, otherwise pre-roll before current time.
+fn first_load_time(current_time: f64, seeking: bool, pre_roll: f64) -> f64 {
+    if seeking {
+        return current_time.floor();
+    }
+
+    (current_time - pre_roll).max(0.0)
+}
+
+/// What happens when a segment download takes too long.
+#[derive(Debug, PartialEq)]
+enum FetchDeadline {
+    /// Abandon the download and retry at a lower level, after this many milliseconds.
+    Downgrade(i32),
+
+    /// Retry the download at the same level, after this many milliseconds.
+    Timeout(i32),
+}
+
+/// Downgrade if the segment deadline comes before the media timeout.
+fn fetch_deadline(segment_deadline: Option<i32>, media_timeout: i32) -> FetchDeadline {
+    match segment_deadline {
+        Some(deadline) if deadline < media_timeout => FetchDeadline::Downgrade(deadline),
+        _ => FetchDeadline::Timeout(media_timeout),
+    }
+}
+
+/// Time left before the buffer runs out minus the expected download time at the lower level, in milliseconds.
+/// Never shorter than the minimum deadline. None at the lowest level or when quality is pinned.
+fn downgrade_deadline(
+    level: usize,
+    auto_quality: bool,
+    lower_bandwidth: usize,
+    avg_speed: Option<f64>,
+    buffered_ahead: f64,
+    min_deadline: i32,
+) -> Option<i32> {
+    // Pinned quality is never lowered.
+    if level <= MIN_VIDEO_LEVEL || !auto_quality {
+        return None;
+    }
+
+    let expected = match avg_speed {
+        Some(avg) => lower_bandwidth as f64 / avg * 1000.0,
+        None => 0.0,
+    };
+
+    let deadline = buffered_ahead * 1000.0 - expected;
+
+    Some((deadline as i32).max(min_deadline))
+}
+
+/// True if the setup fetch failed fewer times than allowed.
+fn should_retry_setup(attempts: usize) -> bool {
+    attempts < SETUP_ATTEMPTS
+}
+
+/// Level within the tracks, never the audio track unless the video is audio-only.
+fn clamp_to_tracks(level: usize, track_count: usize, min_level: usize) -> usize {
+    // At least 1 video track is expected, except for audio-only videos.
+    let max_level = track_count.saturating_sub(1).max(min_level);
+
+    level.max(min_level).min(max_level)
+}
+
+/// Translate total number of seconds to timecode.
+/// Hours are not bounded, a u8 would saturate past 255 and build a wrong path.
+pub fn seconds_to_timecode(seconds: f64) -> (u32, u8, u8) {
+    let rem_seconds = seconds.round();
+
+    let hours = (rem_seconds / 3600.0) as u32;
+    let rem_seconds = rem_seconds.rem_euclid(3600.0);
+
+    let minutes = (rem_seconds / 60.0) as u8;
+    let rem_seconds = rem_seconds.rem_euclid(60.0);
+
+    let seconds = rem_seconds as u8;
+
+    (hours, minutes, seconds)
+}
+
+/// True if the JS object has a callable property with this name.
+fn has_method(target: &JsValue, name: &str) -> bool {
+    js_sys::Reflect::get(target, &name.into()).map_or(false, |value| value.is_function())
+}
+
+/// True if a source buffer append failed because the buffer is full.
+fn is_quota_exceeded(error: &JsValue) -> bool {
+    js_sys::Reflect::get(error, &"name".into())
+        .ok()
+        .and_then(|name| name.as_string())
+        .map_or(false, |name| name == "QuotaExceededError")
+}
+
+/// Download speed in bits per second from the Network Information API, if supported.
+/// Downlink is preferred, the effective type is a coarse fallback.
+fn connection_bitrate(window: &Window) -> Option<f64> {
+    let connection = js_sys::Reflect::get(&window.navigator(), &"connection".into()).ok()?;
+
+    if connection.is_undefined() || connection.is_null() {
+        return None;
+    }
+
+    let downlink = js_sys::Reflect::get(&connection, &"downlink".into())
+        .ok()
+        .and_then(|downlink| downlink.as_f64())
+        .filter(|downlink| *downlink > 0.0);
+
+    if let Some(mbps) = downlink {
+        return Some(mbps * 1_000_000.0);
+    }
+
+    let effective_type = js_sys::Reflect::get(&connection, &"effectiveType".into())
+        .ok()
+        .and_then(|effective_type| effective_type.as_string())?;
+
+    effective_type_bitrate(&effective_type)
+}
+
+/// Typical download speed of a connection effective type.
+fn effective_type_bitrate(effective_type: &str) -> Option<f64> {
+    match effective_type {
+        "slow-2g" => Some(50_000.0),
+        "2g" => Some(70_000.0),
+        "3g" => Some(700_000.0),
+        "4g" => Some(4_000_000.0),
+        _ => None,
+    }
+}
+
+/// Highest video level fitting the bitrate, the lowest if none do.
+fn start_level(tracks: &[Track], bitrate: f64) -> usize {
+    let mut level = MIN_VIDEO_LEVEL;
+
+    while let Some(track) = tracks.get(level + 1) {
+        if track.bandwidth as f64 > bitrate * START_BITRATE_RATIO {
+            break;
+        }
+
+        level += 1;
+    }
+
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use linked_data::signature::{SecretKey, SignedMessage};
+    use linked_data::IPLDLink;
+
+    use cid::multihash::{Code, MultihashDigest};
+
+    fn track(name: &str) -> Track {
+        Track {
+            name: name.to_owned(),
+            codec: String::new(),
+            initialization_segment: Default::default(),
+            bandwidth: 0,
+        }
+    }
+
+    fn names(tracks: &[Track]) -> Vec<&str> {
+        tracks.iter().map(|track| track.name.as_str()).collect()
+    }
+
+    #[test]
+    fn selected_audio_is_first_level() {
+        let setup = vec![
+            track("audio"),
+            track("360p30"),
+            track("720p30"),
+            track("audio_fr"),
+        ];
+        let supported = [false, true, true, true];
+
+        let (audio, _) = select_tracks(&setup, &supported);
+        let (tracks, audio_tracks) = level_tracks(setup, &supported, audio);
+
+        assert_eq!(names(&tracks), ["audio_fr", "360p30", "720p30"]);
+        assert_eq!(names(&audio_tracks), ["audio_fr"]);
+    }
+
+    #[test]
+    fn alternate_audio_is_not_a_level() {
+        let setup = vec![track("audio_en"), track("audio_fr"), track("360p30")];
+        let supported = [true, true, true];
+
+        let (audio, _) = select_tracks(&setup, &supported);
+        let (tracks, audio_tracks) = level_tracks(setup, &supported, audio);
+
+        assert_eq!(names(&tracks), ["audio_en", "360p30"]);
+        assert_eq!(names(&audio_tracks), ["audio_en", "audio_fr"]);
+    }
+
+    #[test]
+    fn duplicate_audio_selects_one_buffer_each() {
+        let setup = vec![
+            track("audio"),
+            track("audio"),
+            track("360p30"),
+            track("720p30"),
+        ];
+        let supported = [true, true, true, true];
+
+        assert_eq!(select_tracks(&setup, &supported), (Some(0), Some(2)));
+    }
+
+    #[test]
+    fn audio_only_has_no_video_buffer() {
+        let setup = vec![track("audio_en"), track("audio_fr")];
+        let supported = [true, true];
+
+        let (audio, video) = select_tracks(&setup, &supported);
+        let (tracks, audio_tracks) = level_tracks(setup, &supported, audio);
+
+        assert_eq!(video, None);
+        assert_eq!(names(&tracks), ["audio_en"]);
+        assert_eq!(names(&audio_tracks), ["audio_en", "audio_fr"]);
+    }
+
+    fn video(name: &str, codec: &str) -> Track {
+        Track {
+            codec: format!("video/mp4; codecs=\"{}\"", codec),
+            ..track(name)
+        }
+    }
+
+    #[test]
+    fn consistent_codec_ladder_no_warning() {
+        let ladder = vec![
+            track("audio"),
+            video("360p30", "avc1.42E01E"),
+            video("720p30", "avc1.4D401F"),
+            video("1080p60", "avc1.64002A"),
+        ];
+
+        assert!(check_codec_ladder(&ladder).is_empty());
+    }
+
+    #[test]
+    fn inconsistent_codec_ladder_warns() {
+        let ladder = vec![
+            video("360p30", "avc1.64001F"),
+            video("720p30", "avc1.42E01E"),
+        ];
+
+        // Lower profile and lower level than the variant below.
+        let warnings = check_codec_ladder(&ladder);
+
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("profile"));
+        assert!(warnings[1].contains("level"));
+    }
+
+    #[test]
+    fn unknown_codec_skipped_in_ladder() {
+        let ladder = vec![
+            video("360p30", "avc1.64001F"),
+            video("720p30", "vp09.00.10.08"),
+            video("1080p30", "avc1.640028"),
+        ];
+
+        assert!(check_codec_ladder(&ladder).is_empty());
+        assert_eq!(avc_profile_level(&ladder[1].codec), None);
+        assert_eq!(avc_profile_level(&ladder[2].codec), Some((100, 40)));
+    }
+
+    fn ladder() -> Vec<Track> {
+        [
+            ("audio", 128_000),
+            ("360p30", 600_000),
+            ("720p30", 2_500_000),
+            ("1080p60", 5_000_000),
+        ]
+        .iter()
+        .map(|(name, bandwidth)| Track {
+            bandwidth: *bandwidth,
+            ..track(name)
+        })
+        .collect()
+    }
+
+    fn effective_type_level(effective_type: &str) -> Option<usize> {
+        effective_type_bitrate(effective_type).map(|bitrate| start_level(&ladder(), bitrate))
+    }
+
+    #[test]
+    fn fast_connection_starts_higher() {
+        assert_eq!(effective_type_level("4g"), Some(2));
+    }
+
+    #[test]
+    fn slow_connection_starts_lowest() {
+        assert_eq!(effective_type_level("3g"), Some(MIN_VIDEO_LEVEL));
+        assert_eq!(effective_type_level("2g"), Some(MIN_VIDEO_LEVEL));
+        assert_eq!(effective_type_level("slow-2g"), Some(MIN_VIDEO_LEVEL));
+    }
+
+    #[test]
+    fn unknown_effective_type_ignored() {
+        assert_eq!(effective_type_level("5g"), None);
+    }
+
+    fn heartbeat(sequence: u64, timestamp: u64) -> LiveHeartbeat {
+        LiveHeartbeat {
+            sequence,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn locked_video_shows_notice() {
+        assert!(locked_notice(true) != html! {});
+    }
+
+    #[test]
+    fn unlocked_video_shows_nothing() {
+        assert!(locked_notice(false) == html! {});
+    }
+
+    #[test]
+    fn timecode_of_zero() {
+        assert_eq!(seconds_to_timecode(0.0), (0, 0, 0));
+    }
+
+    #[test]
+    fn timecode_rounds_to_next_hour() {
+        assert_eq!(seconds_to_timecode(3599.4), (0, 59, 59));
+        assert_eq!(seconds_to_timecode(3599.6), (1, 0, 0));
+    }
+
+    #[test]
+    fn level_within_tracks_unchanged() {
+        assert_eq!(clamp_to_tracks(2, 4, MIN_VIDEO_LEVEL), 2);
+        assert_eq!(clamp_to_tracks(3, 4, MIN_VIDEO_LEVEL), 3);
+    }
+
+    #[test]
+    fn level_clamped_to_tracks() {
+        // Audio and 2 video tracks.
+        assert_eq!(clamp_to_tracks(5, 3, MIN_VIDEO_LEVEL), 2);
+        assert_eq!(
+            clamp_to_tracks(AUDIO_LEVEL, 3, MIN_VIDEO_LEVEL),
+            MIN_VIDEO_LEVEL
+        );
+    }
+
+    #[test]
+    fn audio_only_level_clamped_to_audio() {
+        assert_eq!(clamp_to_tracks(3, 1, AUDIO_LEVEL), AUDIO_LEVEL);
+        assert_eq!(clamp_to_tracks(AUDIO_LEVEL, 1, AUDIO_LEVEL), AUDIO_LEVEL);
+    }
+
+    #[test]
+    fn timecode_exactly_one_hour() {
+        assert_eq!(seconds_to_timecode(3600.0), (1, 0, 0));
+        assert_eq!(seconds_to_timecode(3601.0), (1, 0, 1));
+    }
+
+    #[test]
+    fn timecode_rounds_to_next_minute() {
+        assert_eq!(seconds_to_timecode(59.4), (0, 0, 59));
+        assert_eq!(seconds_to_timecode(59.9), (0, 1, 0));
+    }
+
+    #[test]
+    fn timecode_past_255_hours() {
+        assert_eq!(seconds_to_timecode(255.0 * 3600.0), (255, 0, 0));
+        assert_eq!(seconds_to_timecode(256.0 * 3600.0 + 61.2), (256, 1, 1));
+        assert_eq!(
+            seconds_to_timecode(1000.0 * 3600.0 + 3599.0),
+            (1000, 59, 59)
+        );
+    }
+
+    fn announcement(address: Address) -> VideoMessage {
+        VideoMessage::Announcement(SignedMessage {
+            address,
+            data: LiveAnnouncement {
+                title: "Live".to_owned(),
+                poster: None,
+            },
+            signature: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn announcement_from_streamer_trusted() {
+        let streamer = [1u8; 20];
+
+        assert!(signed_by_streamer(&announcement(streamer), Some(&streamer)));
+    }
+
+    #[test]
+    fn announcement_from_other_signer_ignored() {
+        let streamer = [1u8; 20];
+
+        assert!(!signed_by_streamer(
+            &announcement([2u8; 20]),
+            Some(&streamer)
+        ));
+        assert!(!signed_by_streamer(&announcement(streamer), None));
+    }
+
+    #[test]
+    fn unsigned_link_accepted() {
+        let msg = VideoMessage::Link(Cid::default().into());
+
+        assert!(signed_by_streamer(&msg, Some(&[1u8; 20])));
+    }
+
+    #[test]
+    fn seek_during_switch_redoes_switch() {
+        // Initialization segment requested, then a seek flushes before it arrives.
+        let switch_generation = 7u32;
+        let generation = switch_generation.wrapping_add(1);
+
+        assert_ne!(switch_generation, generation);
+        assert_eq!(state_after_flush(true), MachineState::Switch);
+    }
+
+    #[test]
+    fn flush_without_switch_loads() {
+        assert_eq!(state_after_flush(false), MachineState::Load);
+    }
+
+    #[test]
+    fn discontinuity_realigns_tracks() {
+        assert_eq!(discontinuity_offset(None, true, 61.9, 62.0), Some(62.0));
+        assert_eq!(discontinuity_offset(None, true, 62.1, 62.0), Some(62.1));
+    }
+
+    #[test]
+    fn continuous_segment_keeps_timestamps() {
+        assert_eq!(discontinuity_offset(None, false, 61.9, 62.0), None);
+    }
+
+    #[test]
+    fn seek_offset_kept_on_discontinuity() {
+        assert_eq!(discontinuity_offset(Some(30.0), true, 0.0, 0.0), Some(30.0));
+        assert_eq!(
+            discontinuity_offset(Some(30.0), false, 0.0, 0.0),
+            Some(30.0)
+        );
+    }
+
+    fn segment(n: u64) -> Cid {
+        Cid::new_v1(DAG_CBOR, Code::Sha2_256.digest(&n.to_be_bytes()))
+    }
+
+    fn segment_index(timecodes: impl Iterator<Item = u64>) -> SegmentIndex {
+        SegmentIndex {
+            segments: timecodes.map(|t| (t, segment(t).into())).collect(),
+        }
+    }
+
+    #[test]
+    fn seek_queues_at_most_capacity() {
+        let index = segment_index(0..100);
+        let mut buffer = VecDeque::new();
+
+        let backlog = seek_segments(&index, 10, &mut buffer, &mut VecDeque::new());
+
+        assert_eq!(buffer.len(), LIVE_BUFFER_CAPACITY);
+        assert_eq!(buffer.front(), Some(&segment(10)));
+        assert_eq!(backlog, Some(10 + LIVE_BUFFER_CAPACITY as u64 - 1));
+    }
+
+    #[test]
+    fn pubsub_after_seek_keeps_next_segment() {
+        let index = segment_index(0..100);
+        let mut buffer = VecDeque::new();
+        let mut held = VecDeque::new();
+
+        let backlog = seek_segments(&index, 10, &mut buffer, &mut held);
+
+        assert!(queue_live_segment(
+            &mut buffer,
+            &mut held,
+            backlog,
+            segment(100)
+        ));
+        assert_eq!(buffer.front(), Some(&segment(10)));
+        assert_eq!(buffer.len(), LIVE_BUFFER_CAPACITY);
+
+        // Drained queue continues from the index after the backlog.
+        buffer.clear();
+        let backlog =
+            queue_indexed_segments(&index, Excluded(backlog.unwrap()), &mut buffer, &mut held);
+
+        assert_eq!(buffer.front(), Some(&segment(40)));
+        assert_eq!(backlog, Some(69));
+
+        buffer.clear();
+        let backlog =
+            queue_indexed_segments(&index, Excluded(backlog.unwrap()), &mut buffer, &mut held);
+
+        assert_eq!(backlog, None);
+        assert_eq!(buffer.front(), Some(&segment(70)));
+        assert_eq!(buffer.back(), Some(&segment(100)));
+    }
+
+    #[test]
+    fn seek_keeps_segments_newer_than_index() {
+        let index = segment_index(0..56);
+        let mut buffer: VecDeque<Cid> = (50..62).map(segment).collect();
+        let mut held = VecDeque::new();
+
+        let backlog = seek_segments(&index, 40, &mut buffer, &mut held);
+
+        assert_eq!(backlog, None);
+        assert_eq!(buffer.front(), Some(&segment(40)));
+        assert_eq!(buffer.len(), 22);
+        assert!(buffer
+            .iter()
+            .eq((40..62).map(segment).collect::<Vec<_>>().iter()));
+    }
+
+    #[test]
+    fn held_segments_indexed_meanwhile_not_queued_twice() {
+        let mut held: VecDeque<Cid> = (56..60).map(segment).collect();
+        let mut buffer = VecDeque::new();
+
+        // Newer index published while the backlog was loading.
+        let index = segment_index(0..58);
+
+        assert_eq!(
+            queue_indexed_segments(&index, Excluded(49), &mut buffer, &mut held),
+            None
+        );
+        assert!(buffer
+            .iter()
+            .eq((50..60).map(segment).collect::<Vec<_>>().iter()));
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn backlog_ends_with_the_index() {
+        let index = segment_index(0..20);
+        let mut buffer = VecDeque::new();
+
+        assert_eq!(
+            seek_segments(&index, 5, &mut buffer, &mut VecDeque::new()),
+            None
+        );
+        assert_eq!(buffer.len(), 15);
+        assert!(queue_live_segment(
+            &mut buffer,
+            &mut VecDeque::new(),
+            None,
+            segment(20)
+        ));
+        assert_eq!(buffer.back(), Some(&segment(20)));
+    }
+
+    #[test]
+    fn full_queue_drops_new_segment() {
+        let mut buffer: VecDeque<Cid> = (0..LIVE_BUFFER_CAPACITY as u64).map(segment).collect();
+
+        assert!(!queue_live_segment(
+            &mut buffer,
+            &mut VecDeque::new(),
+            None,
+            segment(99)
+        ));
+        assert_eq!(buffer.front(), Some(&segment(0)));
+        assert_eq!(buffer.len(), LIVE_BUFFER_CAPACITY);
+    }
+
+    #[test]
+    fn first_heartbeat_accepted() {
+        assert!(is_newer_heartbeat(None, &heartbeat(1, 100)));
+    }
+
+    #[test]
+    fn replayed_heartbeat_rejected() {
+        let last = heartbeat(5, 100);
+
+        assert!(!is_newer_heartbeat(Some(&last), &heartbeat(5, 100)));
+        assert!(!is_newer_heartbeat(Some(&last), &heartbeat(4, 95)));
+    }
+
+    #[test]
+    fn restarted_streamer_heartbeat_accepted() {
+        let last = heartbeat(5, 100);
+
+        assert!(is_newer_heartbeat(Some(&last), &heartbeat(6, 105)));
+        assert!(is_newer_heartbeat(Some(&last), &heartbeat(1, 200)));
+    }
+
+    #[test]
+    fn silent_stream_stalls() {
+        assert!(!is_stalled(false, 9_000.0, 10.0));
+        assert!(is_stalled(false, 10_001.0, 10.0));
+    }
+
+    #[test]
+    fn ended_stream_never_stalls() {
+        assert!(!is_stalled(true, 60_000.0, 10.0));
+    }
+
+    const MP4_MIME: &str = "video/mp4; codecs=\"avc1.64001F\"";
+
+    fn mp4_box(box_type: &[u8; 4]) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 24];
+        data.extend_from_slice(box_type);
+        data.resize(24, 0);
+        data
+    }
+
+    fn mpeg_ts(packets: usize) -> Vec<u8> {
+        let mut data = vec![0; 188 * packets];
+
+        for packet in data.chunks_mut(188) {
+            packet[0] = 0x47;
+        }
+
+        data
+    }
+
+    #[test]
+    fn mp4_boxes_detected() {
+        for box_type in &[b"ftyp", b"styp", b"moof", b"moov", b"sidx"] {
+            assert_eq!(detect_container(&mp4_box(box_type)), Some(Container::Mp4));
+        }
+    }
+
+    #[test]
+    fn mpeg_ts_detected() {
+        assert_eq!(detect_container(&mpeg_ts(1)), Some(Container::MpegTs));
+        assert_eq!(detect_container(&mpeg_ts(2)), Some(Container::MpegTs));
+    }
+
+    #[test]
+    fn unknown_container_undetected() {
+        assert_eq!(detect_container(&[]), None);
+        assert_eq!(detect_container(&mp4_box(b"free")), None);
+
+        // Sync byte without a second packet boundary.
+        let mut data = mpeg_ts(2);
+        data[188] = 0;
+
+        assert_eq!(detect_container(&data), None);
+    }
+
+    #[test]
+    fn matching_container_accepted() {
+        assert_eq!(check_container(&mp4_box(b"moof"), MP4_MIME), None);
+        assert_eq!(check_container(&mpeg_ts(2), "video/mp2t"), None);
+        assert_eq!(check_container(&[], MP4_MIME), None);
+    }
+
+    #[test]
+    fn mismatched_container_reported() {
+        let ts = check_container(&mpeg_ts(2), MP4_MIME).unwrap();
+        assert!(ts.contains("MPEG-TS"));
+
+        let mp4 = check_container(&mp4_box(b"moof"), "video/mp2t").unwrap();
+        assert!(mp4.contains("video/mp2t"));
+    }
+
+    #[test]
+    fn fit_style_keeps_aspect_ratio() {
+        assert_eq!(
+            fit_style(VideoFit::Letterbox, 1920, 1080),
+            "object-fit: contain; aspect-ratio: 1920 / 1080;"
+        );
+        assert_eq!(
+            fit_style(VideoFit::Crop, 720, 1280),
+            "object-fit: cover; aspect-ratio: 720 / 1280;"
+        );
+    }
+
+    #[test]
+    fn fit_style_without_dimensions() {
+        assert_eq!(fit_style(VideoFit::Letterbox, 0, 0), "object-fit: contain;");
+        assert_eq!(fit_style(VideoFit::Crop, 1920, 0), "object-fit: cover;");
+    }
+
+    #[test]
+    fn first_load_pre_rolls() {
+        assert_eq!(first_load_time(30.5, false, 2.0), 28.5);
+    }
+
+    #[test]
+    fn first_load_never_before_start() {
+        assert_eq!(first_load_time(1.0, false, 2.0), 0.0);
+        assert_eq!(first_load_time(0.0, false, 2.0), 0.0);
+    }
+
+    #[test]
+    fn seek_loads_containing_segment() {
+        assert_eq!(first_load_time(30.5, true, 2.0), 30.0);
+        assert_eq!(first_load_time(0.9, true, 2.0), 0.0);
+    }
+
+    #[test]
+    fn auto_quality_label_shows_level() {
+        assert_eq!(quality_label("720p30", true), "Auto (720p30)");
+    }
+
+    #[test]
+    fn pinned_quality_label_is_track_name() {
+        assert_eq!(quality_label("1080p60", false), "1080p60");
+    }
+
+    #[test]
+    fn dag_cbor_link_supported() {
+        let cid = Cid::new_v1(DAG_CBOR, Code::Sha2_256.digest(b"segment"));
+
+        assert_eq!(check_link(&cid), None);
+    }
+
+    #[test]
+    fn other_codec_link_rejected() {
+        let cid = Cid::new_v1(RAW, Code::Sha2_256.digest(b"segment"));
+
+        assert!(check_link(&cid).unwrap().contains("0x55"));
+    }
+
+    #[test]
+    fn payload_at_limit_accepted() {
+        assert_eq!(check_payload(&[0; 16], 16), None);
+        assert_eq!(check_payload(&[], 16), None);
+    }
+
+    #[test]
+    fn oversized_payload_rejected() {
+        assert!(check_payload(&[0; 17], 16).is_some());
+        assert!(check_payload(
+            &vec![0; DEFAULT_MAX_PUBSUB_PAYLOAD + 1],
+            DEFAULT_MAX_PUBSUB_PAYLOAD
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn mixed_codecs_supported_per_track() {
+        let tracks = vec![
+            Track {
+                codec: r#"audio/mp4; codecs="mp4a.40.2""#.to_owned(),
+                ..track("audio")
+            },
+            video("720p30", "avc1.4D401F"),
+            video("1080p60", "hev1.1.6.L120.90"),
+            video("2160p60", "av01.0.12M.10"),
+        ];
+
+        // A browser without HEVC.
+        let supported = tracks_supported_by(&tracks, |codec| !codec.contains("hev1"));
+
+        assert_eq!(supported, vec![true, true, false, true]);
+    }
+
+    #[test]
+    fn no_track_supported() {
+        let tracks = vec![track("audio"), video("720p30", "avc1.4D401F")];
+
+        assert_eq!(tracks_supported_by(&tracks, |_| false), vec![false, false]);
+    }
+
+    fn segment_link() -> IPLDLink {
+        Cid::new_v1(DAG_CBOR, Code::Sha2_256.digest(b"segment")).into()
+    }
+
+    #[test]
+    fn raw_cid_decoded_as_link() {
+        let cid = segment_link().link;
+
+        match decode_video_message(cid.to_string().as_bytes()) {
+            Ok(VideoMessage::Link(link)) => assert_eq!(link.link, cid),
+            msg => panic!("{:?}", msg),
+        }
+    }
+
+    #[test]
+    fn signed_envelope_decoded() {
+        let secret_key = SecretKey::parse(&[7u8; 32]).unwrap();
+        let msg = VideoMessage::Signed(SignedMessage::sign(segment_link(), &secret_key));
+        let data = serde_json::to_vec(&msg).unwrap();
+
+        match decode_video_message(&data) {
+            Ok(VideoMessage::Signed(signed_msg)) => assert_eq!(signed_msg.data, segment_link()),
+            msg => panic!("{:?}", msg),
+        }
+    }
+
+    #[test]
+    fn tampered_envelope_rejected() {
+        let secret_key = SecretKey::parse(&[7u8; 32]).unwrap();
+        let mut signed_msg = SignedMessage::sign(segment_link(), &secret_key);
+        signed_msg.data = Cid::default().into();
+
+        let data = serde_json::to_vec(&VideoMessage::Signed(signed_msg)).unwrap();
+
+        assert!(decode_video_message(&data).is_err());
+    }
+
+    #[test]
+    fn malformed_message_rejected() {
+        assert!(decode_video_message(b"").is_err());
+        assert!(decode_video_message(b"not a cid").is_err());
+        assert!(decode_video_message(b"{\"Unknown\":null}").is_err());
+        assert!(decode_video_message(&[0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn setup_retried_after_timeout() {
+        // First attempt timed out.
+        assert!(should_retry_setup(1));
+        assert!(should_retry_setup(SETUP_ATTEMPTS - 1));
+    }
+
+    #[test]
+    fn setup_error_after_last_attempt() {
+        assert!(!should_retry_setup(SETUP_ATTEMPTS));
+    }
+
+    #[test]
+    fn pinned_quality_has_no_deadline() {
+        assert_eq!(
+            downgrade_deadline(3, false, 1_000_000, Some(2_000_000.0), 4.0, 500),
+            None
+        );
+    }
+
+    #[test]
+    fn lowest_level_has_no_deadline() {
+        assert_eq!(
+            downgrade_deadline(MIN_VIDEO_LEVEL, true, 128_000, Some(2_000_000.0), 4.0, 500),
+            None
+        );
+    }
+
+    #[test]
+    fn deadline_leaves_time_for_lower_level() {
+        // 500ms to download the lower level before 4s of buffer runs out.
+        assert_eq!(
+            downgrade_deadline(3, true, 1_000_000, Some(2_000_000.0), 4.0, 500),
+            Some(3_500)
+        );
+        assert_eq!(
+            downgrade_deadline(3, true, 1_000_000, None, 4.0, 500),
+            Some(4_000)
+        );
+    }
+
+    #[test]
+    fn deadline_never_below_minimum() {
+        assert_eq!(
+            downgrade_deadline(
+                3,
+                true,
+                1_000_000,
+                Some(500_000.0),
+                0.5,
+                DEFAULT_SEGMENT_DEADLINE
+            ),
+            Some(DEFAULT_SEGMENT_DEADLINE)
+        );
+    }
+
+    #[test]
+    fn slow_fetch_downgrades_before_timeout() {
+        assert_eq!(
+            fetch_deadline(Some(3_500), 10_000),
+            FetchDeadline::Downgrade(3_500)
+        );
+    }
+
+    #[test]
+    fn late_deadline_times_out_instead() {
+        assert_eq!(
+            fetch_deadline(Some(15_000), 10_000),
+            FetchDeadline::Timeout(10_000)
+        );
+        assert_eq!(fetch_deadline(None, 10_000), FetchDeadline::Timeout(10_000));
+    }
+
+    #[test]
+    fn segment_uses_media_timeout() {
+        assert_eq!(
+            fetch_deadline(None, DEFAULT_MEDIA_TIMEOUT),
+            FetchDeadline::Timeout(DEFAULT_MEDIA_TIMEOUT)
+        );
+    }
+
+    #[test]
+    fn preview_track_cues() {
+        let text = "WEBVTT\n\n1\n00:00.000 --> 00:05.000\nbafkreiaaa\n\n00:00:05.000 --> 00:01:10.500 align:start\nipfs://bafkreibbb#xywh=0,0,160,90\n";
+
+        let previews = parse_previews(text);
+
+        assert_eq!(
+            previews,
+            vec![
+                (0.0, 5.0, "ipfs://bafkreiaaa".to_owned()),
+                (5.0, 70.5, "ipfs://bafkreibbb#xywh=0,0,160,90".to_owned()),
+            ]
+        );
+
+        assert_eq!(preview_at(&previews, 4.9), Some("ipfs://bafkreiaaa"));
+        assert_eq!(
+            preview_at(&previews, 5.0),
+            Some("ipfs://bafkreibbb#xywh=0,0,160,90")
+        );
+        assert_eq!(preview_at(&previews, 80.0), None);
+    }
+
+    #[test]
+    fn invalid_preview_cues_skipped() {
+        let text = "WEBVTT\n\nnot a time --> 00:05.000\nbafkreiaaa\n\n00:05.000 --> 00:10.000\n\n";
+
+        assert!(parse_previews(text).is_empty());
+        assert_eq!(parse_vtt_time("01:02:03.5"), Some(3723.5));
+        assert_eq!(parse_vtt_time("1:01:02:03"), None);
+    }
+
+    fn ladder_tracks() -> Vec<Track> {
+        [
+            ("audio", 128_000),
+            ("360p30", 1_000_000),
+            ("720p30", 3_000_000),
+            ("1080p30", 6_000_000),
+        ]
+        .iter()
+        .map(|(name, bandwidth)| Track {
+            bandwidth: *bandwidth,
+            ..track(name)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn shallow_buffer_blocks_upswitch() {
+        let tracks = ladder_tracks();
+
+        assert_eq!(
+            abr_level(&tracks, 10_000_000.0, 1, 1.0, DEFAULT_UPSWITCH_BUFFER),
+            1
+        );
+        assert_eq!(
+            abr_level(&tracks, 10_000_000.0, 1, 6.0, DEFAULT_UPSWITCH_BUFFER),
+            3
+        );
+    }
+
+    #[test]
+    fn shallow_buffer_allows_downswitch() {
+        let tracks = ladder_tracks();
+
+        assert_eq!(
+            abr_level(&tracks, 4_000_000.0, 3, 1.0, DEFAULT_UPSWITCH_BUFFER),
+            2
+        );
+    }
+
+    #[test]
+    fn live_status_announced() {
+        let expected =
+            html! { <div class="video_live_status" role="status"> { "Stream ended." } </div> };
+
+        assert_eq!(live_status_notice(true, true, false), expected);
+        assert_eq!(live_status_notice(false, false, false), html! {});
+    }
+
+    #[test]
+    fn locked_notice_is_alert() {
+        let expected = html! { <div class="video_locked" role="alert"> { "🔒 Locked, your wallet does not meet this video's access requirement." } </div> };
+
+        assert_eq!(locked_notice(true), expected);
+    }
+
+    #[test]
+    fn duplicate_inside_window_ignored() {
+        let mut recent = VecDeque::new();
+
+        assert!(!is_duplicate_segment(&mut recent, segment(1), 0.0, 10.0));
+        assert!(!is_duplicate_segment(
+            &mut recent,
+            segment(2),
+            1_000.0,
+            10.0
+        ));
+        assert!(is_duplicate_segment(&mut recent, segment(1), 9_000.0, 10.0));
+    }
+
+    #[test]
+    fn duplicate_outside_window_allowed() {
+        let mut recent = VecDeque::new();
+
+        assert!(!is_duplicate_segment(&mut recent, segment(1), 0.0, 10.0));
+        assert!(!is_duplicate_segment(
+            &mut recent,
+            segment(1),
+            11_000.0,
+            10.0
+        ));
+        assert!(is_duplicate_segment(
+            &mut recent,
+            segment(1),
+            12_000.0,
+            10.0
+        ));
+    }
+
+    #[test]
+    fn empty_setup_is_error() {
+        let setup = SetupNode { tracks: vec![] };
+
+        assert_eq!(
+            check_setup(&setup),
+            Some("No playable tracks, the video setup is empty.".to_owned())
+        );
+        assert_eq!(select_tracks(&setup.tracks, &[]), (None, None));
+    }
+
+    #[test]
+    fn setup_with_tracks_is_playable() {
+        let setup = SetupNode {
+            tracks: ladder_tracks(),
+        };
+
+        assert_eq!(check_setup(&setup), None);
+    }
+
+    #[test]
+    fn audio_appended_in_request_order() {
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+
+        pending.insert(1, vec![1]);
+        assert_eq!(next_audio_segment(&mut pending, &mut next), None);
+
+        pending.insert(0, vec![0]);
+        pending.insert(2, Vec::new());
+        pending.insert(3, vec![3]);
+
+        assert_eq!(next_audio_segment(&mut pending, &mut next), Some(vec![0]));
+        assert_eq!(next_audio_segment(&mut pending, &mut next), Some(vec![1]));
+        assert_eq!(next_audio_segment(&mut pending, &mut next), Some(vec![3]));
+        assert_eq!(next, 4);
+    }
+
+    #[test]
+    fn live_seek_before_window_clamped() {
+        let index = segment_index(20..40);
+
+        assert_eq!(live_seek_segment(&index, 5), Some((20, segment(20), true)));
+    }
+
+    #[test]
+    fn live_seek_inside_window_not_clamped() {
+        let index = segment_index((20..40).step_by(4));
+
+        assert_eq!(
+            live_seek_segment(&index, 26),
+            Some((24, segment(24), false))
+        );
+        assert_eq!(live_seek_segment(&segment_index(0..0), 26), None);
+    }
+
+    #[test]
+    fn segments_before_media_buffers_are_queued() {
+        let mut buffer = VecDeque::new();
+        let mut held = VecDeque::new();
+
+        // Messages delivered before source buffers are added only queue segments.
+        for n in 0..3 {
+            assert!(queue_live_segment(&mut buffer, &mut held, None, segment(n)));
+        }
+
+        assert_eq!(buffer, vec![segment(0), segment(1), segment(2)]);
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn seek_between_boundaries_resolves_covering_second() {
+        // Segments of 4 seconds, each second links to the segment covering it.
+        let minute = MinuteNode {
+            links_to_seconds: (0..60).map(|s| segment(s - s % 4).into()).collect(),
+        };
+
+        assert_eq!(covering_second(&minute, 45), Some(segment(44)));
+    }
+
+    #[test]
+    fn seek_past_partial_minute_resolves_last_second() {
+        let minute = MinuteNode {
+            links_to_seconds: (0..30).map(|s| segment(s - s % 4).into()).collect(),
+        };
+
+        assert_eq!(covering_second(&minute, 45), Some(segment(28)));
+        assert_eq!(
+            covering_second(
+                &MinuteNode {
+                    links_to_seconds: vec![]
+                },
+                45
+            ),
+            None
+        );
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod browser_tests {
+    use super::*;
+
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    use yew::App;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Player driven directly by the test, its callbacks go to a mounted instance.
+    /// Live streams are published by the peer "streamer".
+    fn player(mode: PlaybackMode) -> VideoPlayer {
+        let (topic, streamer_peer_id) = match mode {
+            PlaybackMode::Live => (Some("live".to_owned()), Some("streamer".to_owned())),
+            PlaybackMode::Vod => (None, None),
+        };
+
+        let props = Props::builder()
+            .ipfs(IpfsService::new())
+            .metadata(None)
+            .mode(mode)
+            .streamer_peer_id(streamer_peer_id)
+            .topic(topic)
+            .build();
+
+        let document = web_sys::window().unwrap().document().unwrap();
+        let element = document.create_element("div").unwrap();
+        let link = App::<VideoPlayer>::new().mount_with_props(element, props.clone());
+
+        VideoPlayer::create(props, link)
+    }
+
+    /// Audio source buffer of an open media source, kept open by the returned element.
+    async fn source_buffer() -> (HtmlMediaElement, SourceBuffer) {
+        let media_source = MediaSource::new().unwrap();
+        let url = Url::create_object_url_with_source(&media_source).unwrap();
+
+        let document = web_sys::window().unwrap().document().unwrap();
+        let element: HtmlMediaElement = document.create_element("video").unwrap().unchecked_into();
+        element.set_src(&url);
+
+        while media_source.ready_state() != MediaSourceReadyState::Open {
+            sleep(10).await;
+        }
+
+        media_source.set_duration(10.0);
+
+        let buffer = media_source
+            .add_source_buffer("audio/webm; codecs=\"opus\"")
+            .unwrap();
+
+        (element, buffer)
+    }
+
+    fn audio_only(audio: SourceBuffer) -> MediaBuffers {
+        MediaBuffers {
+            audio,
+            video: None,
+            tracks: Vec::new(),
+            audio_tracks: Vec::new(),
+            audio_requested: 0,
+            audio_next: 0,
+            audio_pending: BTreeMap::new(),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn flush_all_waits_for_update_end() {
+        let (_element, audio) = source_buffer().await;
+
+        // Updating until the append completes.
+        audio.append_buffer_with_u8_array(&mut []).unwrap();
+
+        let mut player = player(PlaybackMode::Vod);
+        player.media_buffers = Some(audio_only(audio));
+        let generation = player.generation;
+
+        player.flush_all();
+
+        assert_eq!(player.state, MachineState::FlushAll);
+        assert_eq!(player.generation, generation);
+    }
+
+    #[wasm_bindgen_test]
+    async fn flush_all_reloads() {
+        let (_element, audio) = source_buffer().await;
+
+        let mut player = player(PlaybackMode::Vod);
+        player.media_buffers = Some(audio_only(audio));
+        player.state = MachineState::Status;
+        let generation = player.generation;
+
+        player.flush_all();
+
+        assert_eq!(player.state, MachineState::Load);
+        assert_eq!(player.generation, generation.wrapping_add(1));
+    }
+
+    fn message(from: &str) -> Msg {
+        Msg::PubSub(Ok(PubsubSubResponse {
+            from: from.to_owned(),
+            data: b"bafyreib".to_vec(),
+        }))
+    }
+
+    #[wasm_bindgen_test]
+    fn unauthorized_sender_counted() {
+        let mut player = player(PlaybackMode::Live);
+
+        player.update(message("intruder"));
+        player.update(message("intruder"));
+        player.update(message("streamer"));
+
+        let live = player.live_stream.as_ref().unwrap();
+
+        assert_eq!(live.rejected.get("intruder"), Some(&2));
+        assert_eq!(live.rejected.get("streamer"), None);
+    }
+
+    /// Download error of an invalid URL.
+    fn fetch_error() -> Error {
+        reqwest::Client::new().get("not a url").build().unwrap_err()
+    }
+
+    #[wasm_bindgen_test]
+    fn failed_switch_init_reverts_level() {
+        let mut player = player(PlaybackMode::Vod);
+
+        // Switching from the lowest level, which worked, to a pinned higher one.
+        player.set_video_level(3);
+        player.pinned_level = 3;
+
+        let generation = player.generation;
+        player.update(Msg::SwitchInit(generation, 3, Err(fetch_error())));
+
+        assert_eq!(player.video_level(), MIN_VIDEO_LEVEL);
+        assert_eq!(player.pinned_level, MIN_VIDEO_LEVEL);
+
+        // The buffer codec was changed, switching back appends the working initialization segment.
+        assert_eq!(player.state, MachineState::Switch);
+    }
 }
@@ -2,11 +2,12 @@ use crate::app::AppRoute;
 use crate::components::seconds_to_timecode;
 
 use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::Callback;
 use yew_router::components::RouterAnchor;
 
 use yewtil::NeqAssign;
 
-use linked_data::video::VideoMetadata;
+use linked_data::video::{Engagement, ImageSize, VideoMetadata};
 
 use cid::Cid;
 
@@ -16,6 +17,12 @@ type Anchor = RouterAnchor<AppRoute>;
 pub struct VideoThumbnail {
     pub metadata_cid: Cid,
     pub metadata: VideoMetadata,
+
+    #[prop_or_default]
+    pub engagement: Option<Engagement>,
+
+    /// Emit the metadata CID when hovered.
+    pub on_hover: Callback<Cid>,
 }
 
 impl Component for VideoThumbnail {
@@ -36,17 +43,57 @@ impl Component for VideoThumbnail {
 
     fn view(&self) -> Html {
         let (hour, minute, second) = seconds_to_timecode(self.metadata.duration);
+        let cid = self.metadata_cid;
 
         html! {
-            <div class="video_thumbnail">
+            <div class="video_thumbnail" onmouseenter=self.on_hover.reform(move |_| cid)>
                 <Anchor route=AppRoute::Video(self.metadata_cid) classes="thumbnail_link">
                     <div class="thumbnail_title"> {&self.metadata.title} </div>
                     <div class="thumbnail_image">
-                        <img src=format!("ipfs://{}", &self.metadata.image.link.to_string()) alt="This image require IPFS native browser" />
+                        <img src=format!("ipfs://{}", self.metadata.image_link(ImageSize::Thumbnail)) alt="This image require IPFS native browser" />
                     </div>
                     <div class="thumbnail_duration"> {&format!("{}:{}:{}", hour, minute, second) } </div>
+                    { view_engagement(self.engagement.as_ref(), "thumbnail_engagement") }
                 </Anchor>
             </div>
         }
     }
 }
+
+/// Human readable view and like counts.
+fn format_engagement(engagement: &Engagement) -> String {
+    format!("{} views • {} likes", engagement.views, engagement.likes)
+}
+
+/// View and like counts with this class, nothing without engagement data.
+pub fn view_engagement(engagement: Option<&Engagement>, class: &'static str) -> Html {
+    match engagement {
+        Some(engagement) => html! { <div class=class> { format_engagement(engagement) } </div> },
+        None => html! {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engagement_rendered() {
+        let engagement = Engagement {
+            views: 1200,
+            likes: 34,
+        };
+
+        let expected = html! { <div class="video_engagement"> { "1200 views • 34 likes" } </div> };
+
+        assert_eq!(
+            view_engagement(Some(&engagement), "video_engagement"),
+            expected
+        );
+    }
+
+    #[test]
+    fn missing_engagement_renders_nothing() {
+        assert_eq!(view_engagement(None, "thumbnail_engagement"), html! {});
+    }
+}
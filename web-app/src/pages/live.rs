@@ -1,9 +1,10 @@
 use std::rc::Rc;
 
-use crate::app::ENS_NAME;
-use crate::components::{ChatWindow, Navbar, VideoPlayer};
+use crate::components::{ChatWindow, Navbar, PlaybackMode, ReportButton, VideoPlayer};
 use crate::utils::ipfs::IpfsService;
-use crate::utils::local_storage::{get_cid, get_local_storage, set_local_beacon};
+use crate::utils::local_storage::{
+    get_local_beacon, get_local_channel, get_local_storage, set_local_beacon,
+};
 use crate::utils::web3::Web3Service;
 
 use wasm_bindgen_futures::spawn_local;
@@ -18,6 +19,8 @@ use linked_data::video::VideoMetadata;
 
 use cid::Cid;
 
+use web3::types::Address;
+
 use reqwest::Error;
 
 enum DisplayState {
@@ -33,14 +36,21 @@ pub struct Live {
 
     storage: Option<Storage>,
 
+    /// ENS name of the selected channel.
+    channel: String,
+
     beacon_cid: Option<Cid>,
 
+    /// Address of the channel owner.
+    streamer: Option<Address>,
+
     state: DisplayState,
 }
 
 pub enum Msg {
     ResolveName(Result<Cid, web3::contract::Error>),
     Beacon(Result<Beacon, Error>),
+    Streamer(Result<Address, web3::contract::Error>),
 }
 
 #[derive(Properties, Clone)]
@@ -59,7 +69,9 @@ impl Component for Live {
         let window = web_sys::window().expect("Can't get window");
         let storage = get_local_storage(&window);
 
-        let beacon_cid = get_cid(ENS_NAME, storage.as_ref());
+        let channel = get_local_channel(storage.as_ref());
+
+        let beacon_cid = get_local_beacon(&channel, storage.as_ref());
 
         if let Some(cid) = beacon_cid {
             let cb = link.callback_once(Msg::Beacon);
@@ -71,10 +83,16 @@ impl Component for Live {
         // Check for beacon updates by resolving name.
         let cb = link.callback_once(Msg::ResolveName);
         let client = web3.clone();
-        let name = ENS_NAME.to_owned();
+        let name = channel.clone();
 
         spawn_local(async move { cb.emit(client.get_ipfs_content(name).await) });
 
+        let cb = link.callback_once(Msg::Streamer);
+        let client = web3.clone();
+        let name = channel.clone();
+
+        spawn_local(async move { cb.emit(client.get_eth_address(name).await) });
+
         Self {
             link,
 
@@ -82,8 +100,12 @@ impl Component for Live {
             web3,
             storage,
 
+            channel,
+
             beacon_cid,
 
+            streamer: None,
+
             state: DisplayState::Searching,
         }
     }
@@ -92,6 +114,7 @@ impl Component for Live {
         match msg {
             Msg::ResolveName(result) => self.on_name_resolved(result),
             Msg::Beacon(result) => self.on_beacon_update(result),
+            Msg::Streamer(result) => self.on_streamer_resolved(result),
         }
     }
 
@@ -104,8 +127,14 @@ impl Component for Live {
             DisplayState::Searching => html! { <div class="center_text">  {"Loading..."} </div> },
             DisplayState::Beacon(beacon) => html! {
                 <div class="live_stream">
-                    <VideoPlayer ipfs=self.ipfs.clone() metadata=Option::<VideoMetadata>::None topic=Some(beacon.topics.live_video.clone()) streamer_peer_id=Some(beacon.peer_id.clone()) />
-                    <ChatWindow ipfs=self.ipfs.clone() web3=self.web3.clone() topic=Rc::from(beacon.topics.live_chat.clone()) ban_list=Rc::from(beacon.bans.clone()) mod_list=Rc::from(beacon.mods.clone())/>
+                    <VideoPlayer ipfs=self.ipfs.clone() mode=PlaybackMode::Live metadata=Option::<VideoMetadata>::None topic=Some(beacon.topics.live_video.clone()) streamer_peer_id=Some(beacon.peer_id.clone()) streamer=self.streamer.map(|address| address.to_fixed_bytes()) />
+                    <ChatWindow ipfs=self.ipfs.clone() web3=self.web3.clone() topic=Rc::from(beacon.topics.live_chat.clone()) ban_list=Rc::from(beacon.bans.clone()) mod_list=Rc::from(beacon.mods.clone()) streamer=self.streamer.map(|address| address.to_fixed_bytes()) />
+                    {
+                        match self.beacon_cid {
+                            Some(cid) => html! { <ReportButton ipfs=self.ipfs.clone() web3=self.web3.clone() content=cid /> },
+                            None => html! {},
+                        }
+                    }
                 </div>
             },
         };
@@ -144,13 +173,28 @@ impl Live {
         #[cfg(debug_assertions)]
         ConsoleService::info("Name Update");
 
-        set_local_beacon(&ENS_NAME, &cid, self.storage.as_ref());
+        set_local_beacon(&self.channel, &cid, self.storage.as_ref());
 
         self.beacon_cid = Some(cid);
 
         false
     }
 
+    /// Callback when Ethereum Name Service resolve name to streamer address.
+    fn on_streamer_resolved(&mut self, res: Result<Address, web3::contract::Error>) -> bool {
+        let address = match res {
+            Ok(address) => address,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return false;
+            }
+        };
+
+        self.streamer = Some(address);
+
+        true
+    }
+
     /// Callback when IPFS dag get return beacon node.
     fn on_beacon_update(&mut self, res: Result<Beacon, Error>) -> bool {
         let beacon = match res {
@@ -1,11 +1,13 @@
 mod home;
 mod live;
+mod play;
 mod settings;
 mod video;
 mod videos;
 
 pub use home::Home;
 pub use live::Live;
+pub use play::Play;
 pub use settings::Settings;
 pub use video::Video;
 pub use videos::Videos;
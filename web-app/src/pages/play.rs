@@ -0,0 +1,48 @@
+use crate::components::{Navbar, PlaybackMode, VideoPlayer};
+use crate::utils::ipfs::IpfsService;
+
+use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+
+use linked_data::video::VideoMetadata;
+
+use cid::Cid;
+
+/// Play any video root CID directly, without beacon or metadata.
+pub struct Play {
+    ipfs: IpfsService,
+    root_cid: Cid,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub ipfs: IpfsService,
+    pub root_cid: Cid,
+}
+
+impl Component for Play {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        let Props { ipfs, root_cid } = props;
+
+        Self { ipfs, root_cid }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="video_page">
+                <Navbar />
+                <VideoPlayer ipfs=self.ipfs.clone() mode=PlaybackMode::Vod metadata=Option::<VideoMetadata>::None root_cid=self.root_cid topic=Option::<String>::None streamer_peer_id=Option::<String>::None />
+            </div>
+        }
+    }
+}
@@ -7,7 +7,15 @@ use wasm_bindgen::JsCast;
 use yew::prelude::{html, Component, ComponentLink, Html, ShouldRender};
 use yew::ChangeData;
 
-use crate::utils::local_storage::{get_local_ipfs_addrs, get_local_storage, set_local_ipfs_addrs};
+use crate::utils::bitrate::BitrateUnit;
+use crate::utils::ipfs::DEFAULT_SEGMENT_CACHE_MB;
+use crate::utils::local_storage::{
+    get_local_bitrate_unit, get_local_channel, get_local_channels, get_local_gateway,
+    get_local_ipfs_addrs, get_local_offline_mode, get_local_segment_cache, get_local_storage,
+    set_local_bitrate_unit, set_local_channel, set_local_channels, set_local_gateway,
+    set_local_ipfs_addrs, set_local_offline_mode, set_local_segment_cache,
+};
+use crate::utils::web3::normalize_channel_name;
 
 pub struct Settings {
     link: ComponentLink<Self>,
@@ -15,10 +23,25 @@ pub struct Settings {
     window: Window,
 
     storage: Option<Storage>,
+
+    offline: bool,
+
+    channels: Vec<String>,
+    channel: String,
+    /// Why the last channel name entered was refused.
+    channel_error: Option<String>,
+
+    bitrate_unit: BitrateUnit,
 }
 
 pub enum Msg {
     Addrs(ChangeData),
+    Gateway(ChangeData),
+    SegmentCache(ChangeData),
+    Offline,
+    AddChannel(ChangeData),
+    Channel(String),
+    BitrateUnit(ChangeData),
 }
 
 impl Component for Settings {
@@ -28,17 +51,37 @@ impl Component for Settings {
     fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let window = web_sys::window().expect("Can't get window");
         let storage = get_local_storage(&window);
+        let offline = get_local_offline_mode(storage.as_ref());
+        let channel = get_local_channel(storage.as_ref());
+        let bitrate_unit = get_local_bitrate_unit(storage.as_ref());
+
+        let mut channels = get_local_channels(storage.as_ref());
+
+        if !channels.contains(&channel) {
+            channels.push(channel.clone());
+        }
 
         Self {
             link,
             window,
             storage,
+            offline,
+            channels,
+            channel,
+            channel_error: None,
+            bitrate_unit,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Msg::Addrs(msg) => self.addrs(msg),
+            Msg::Gateway(msg) => self.gateway(msg),
+            Msg::SegmentCache(msg) => self.segment_cache(msg),
+            Msg::Offline => self.offline(),
+            Msg::AddChannel(msg) => self.add_channel(msg),
+            Msg::Channel(name) => self.select_channel(name),
+            Msg::BitrateUnit(msg) => self.bitrate_unit(msg),
         }
     }
 
@@ -58,6 +101,52 @@ impl Component for Settings {
                             onchange=self.link.callback(Msg::Addrs)
                             placeholder="IPFS API address" />
                     </div>
+                    <div>
+                        <label for="fallback_gateway"> { "Fallback IPFS gateway: " } </label>
+                        <input type="text" id="fallback_gateway" name="fallback_gateway"
+                            onchange=self.link.callback(Msg::Gateway)
+                            placeholder="https://ipfs.io/ipfs/" />
+                    </div>
+                    <div>
+                        <label for="segment_cache"> { "Segment cache (MB, 0 to disable, applied on reload): " } </label>
+                        <input type="number" id="segment_cache" name="segment_cache" min="0"
+                            onchange=self.link.callback(Msg::SegmentCache) />
+                    </div>
+                    <div>
+                        <input type="checkbox" id="prefer_offline" name="prefer_offline"
+                            checked=self.offline
+                            onclick=self.link.callback(|_| Msg::Offline) />
+                        <label for="prefer_offline"> { " Prefer cached content (offline mode)" } </label>
+                    </div>
+                    <div>
+                        <label for="add_channel"> { "Channels: " } </label>
+                        {
+                            for self.channels.iter().map(|name| {
+                                let class = if *name == self.channel { "channel_selected" } else { "channel" };
+                                let channel = name.clone();
+
+                                html! {
+                                    <button class=class onclick=self.link.callback(move |_| Msg::Channel(channel.clone()))> { name } </button>
+                                }
+                            })
+                        }
+                        <input type="text" id="add_channel" name="add_channel"
+                            onchange=self.link.callback(Msg::AddChannel)
+                            placeholder="ENS name" />
+                        {
+                            match self.channel_error.as_ref() {
+                                Some(error) => html! { <div class="channel_error" role="alert"> { error } </div> },
+                                None => html! {},
+                            }
+                        }
+                    </div>
+                    <div>
+                        <label for="bitrate_unit"> { "Bitrate unit: " } </label>
+                        <select id="bitrate_unit" name="bitrate_unit" onchange=self.link.callback(Msg::BitrateUnit)>
+                            <option value="bits" selected=(self.bitrate_unit == BitrateUnit::Bits)> { "Bits (Mbps)" } </option>
+                            <option value="bytes" selected=(self.bitrate_unit == BitrateUnit::Bytes)> { "Bytes (MB/s)" } </option>
+                        </select>
+                    </div>
                 </div>
             </div>
         }
@@ -76,6 +165,27 @@ impl Component for Settings {
             if let Some(addrs) = get_local_ipfs_addrs(self.storage.as_ref()).as_ref() {
                 text_area.set_value(addrs);
             }
+
+            let gateway: HtmlInputElement = document
+                .get_element_by_id("fallback_gateway")
+                .expect("No element with this Id")
+                .dyn_into()
+                .expect("Not Input Element");
+
+            if let Some(addrs) = get_local_gateway(self.storage.as_ref()).as_ref() {
+                gateway.set_value(addrs);
+            }
+
+            let segment_cache: HtmlInputElement = document
+                .get_element_by_id("segment_cache")
+                .expect("No element with this Id")
+                .dyn_into()
+                .expect("Not Input Element");
+
+            let megabytes =
+                get_local_segment_cache(self.storage.as_ref()).unwrap_or(DEFAULT_SEGMENT_CACHE_MB);
+
+            segment_cache.set_value(&megabytes.to_string());
         }
     }
 }
@@ -90,4 +200,82 @@ impl Settings {
 
         false
     }
+
+    fn gateway(&mut self, msg: ChangeData) -> bool {
+        if let ChangeData::Value(addrs) = msg {
+            set_local_gateway(addrs.trim(), self.storage.as_ref());
+        }
+
+        false
+    }
+
+    fn segment_cache(&mut self, msg: ChangeData) -> bool {
+        if let ChangeData::Value(megabytes) = msg {
+            if let Ok(megabytes) = megabytes.trim().parse::<usize>() {
+                set_local_segment_cache(megabytes, self.storage.as_ref());
+            }
+        }
+
+        false
+    }
+
+    fn add_channel(&mut self, msg: ChangeData) -> bool {
+        let name = match msg {
+            ChangeData::Value(name) => name,
+            ChangeData::Select(_) => return false,
+            ChangeData::Files(_) => return false,
+        };
+
+        let name = match normalize_channel_name(&name) {
+            Ok(name) => name,
+            Err(error) => {
+                self.channel_error = Some(error);
+                return true;
+            }
+        };
+
+        self.channel_error = None;
+
+        if self.channels.contains(&name) {
+            return true;
+        }
+
+        self.channels.push(name.clone());
+
+        set_local_channels(&self.channels, self.storage.as_ref());
+
+        self.select_channel(name)
+    }
+
+    fn select_channel(&mut self, name: String) -> bool {
+        set_local_channel(&name, self.storage.as_ref());
+
+        self.channel = name;
+
+        true
+    }
+
+    fn bitrate_unit(&mut self, msg: ChangeData) -> bool {
+        let unit = match msg {
+            ChangeData::Select(select) => select.value(),
+            ChangeData::Value(value) => value,
+            ChangeData::Files(_) => return false,
+        };
+
+        if let Ok(unit) = unit.parse() {
+            self.bitrate_unit = unit;
+
+            set_local_bitrate_unit(unit, self.storage.as_ref());
+        }
+
+        false
+    }
+
+    fn offline(&mut self) -> bool {
+        self.offline = !self.offline;
+
+        set_local_offline_mode(self.offline, self.storage.as_ref());
+
+        true
+    }
 }
@@ -1,11 +1,15 @@
-use crate::components::{Navbar, VideoPlayer};
+use crate::components::{
+    view_engagement, DownloadButton, Navbar, PlaybackMode, ReportButton, VideoPlayer,
+};
 use crate::utils::ipfs::IpfsService;
+use crate::utils::web3::Web3Service;
 
 use wasm_bindgen_futures::spawn_local;
 
 use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::ConsoleService;
 
-use linked_data::video::VideoMetadata;
+use linked_data::video::{Engagement, VideoMetadata};
 
 use cid::Cid;
 
@@ -19,18 +23,25 @@ enum State {
 }
 
 pub struct Video {
+    link: ComponentLink<Self>,
+
     ipfs: IpfsService,
+    web3: Web3Service,
+    metadata_cid: Cid,
     state: State,
+    engagement: Option<Engagement>,
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {
     pub ipfs: IpfsService,
+    pub web3: Web3Service,
     pub metadata_cid: Cid,
 }
 
 pub enum Msg {
     Metadata(Result<VideoMetadata, Error>),
+    Engagement(Result<Engagement, Error>),
 }
 
 impl Component for Video {
@@ -38,7 +49,11 @@ impl Component for Video {
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let Props { ipfs, metadata_cid } = props;
+        let Props {
+            ipfs,
+            web3,
+            metadata_cid,
+        } = props;
 
         let cb = link.callback_once(Msg::Metadata);
         let client = ipfs.clone();
@@ -48,18 +63,27 @@ impl Component for Video {
         );
 
         Self {
+            link,
+
             ipfs,
+            web3,
+            metadata_cid,
             state: State::Loading,
+            engagement: None,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Msg::Metadata(result) => self.update_metadata(result),
+            Msg::Engagement(result) => self.update_engagement(result),
         }
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        // Provider changed, playback continues and the next wallet check uses the new one.
+        self.web3 = props.web3;
+
         false
     }
 
@@ -70,7 +94,14 @@ impl Component for Video {
             {
                 match &self.state {
                     State::Loading => html! { <div class="center_text"> {"Loading..."} </div> },
-                    State::Ready(md) => html! { <VideoPlayer ipfs=self.ipfs.clone() metadata=Some(md.clone()) topic=Option::<String>::None streamer_peer_id=Option::<String>::None /> },
+                    State::Ready(md) => html! {
+                        <>
+                            <VideoPlayer ipfs=self.ipfs.clone() mode=PlaybackMode::Vod metadata=Some(md.clone()) topic=Option::<String>::None streamer_peer_id=Option::<String>::None web3=Some(self.web3.clone()) />
+                            { view_engagement(self.engagement.as_ref(), "video_engagement") }
+                            <DownloadButton ipfs=self.ipfs.clone() metadata=md.clone() />
+                            <ReportButton ipfs=self.ipfs.clone() web3=self.web3.clone() content=self.metadata_cid />
+                        </>
+                    },
                     State::Error(e) => html! { <div class="center_text"> { format!("{:#?}", e) } </div> },
                 }
             }
@@ -84,10 +115,33 @@ impl Component for Video {
 impl Video {
     fn update_metadata(&mut self, response: Result<VideoMetadata, Error>) -> bool {
         self.state = match response {
-            Ok(md) => State::Ready(md),
+            Ok(md) => {
+                if let Some(link) = md.engagement {
+                    let cb = self.link.callback_once(Msg::Engagement);
+                    let client = self.ipfs.clone();
+
+                    spawn_local(async move {
+                        cb.emit(client.dag_get(link.link, Option::<String>::None).await)
+                    });
+                }
+
+                State::Ready(md)
+            }
             Err(e) => State::Error(e),
         };
 
         true
     }
+
+    fn update_engagement(&mut self, response: Result<Engagement, Error>) -> bool {
+        match response {
+            Ok(engagement) => self.engagement = Some(engagement),
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return false;
+            }
+        }
+
+        true
+    }
 }
@@ -1,26 +1,52 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
 
-use crate::app::ENS_NAME;
-use crate::components::{Navbar, VideoThumbnail};
+use crate::components::{Navbar, VideoThumbnail, SETUP_PATH};
+use crate::utils::cancel::CancelToken;
 use crate::utils::ipfs::IpfsService;
-use crate::utils::local_storage::{get_cid, get_local_storage, set_cid, set_local_beacon};
+use crate::utils::local_storage::{
+    get_cid, get_local_beacon, get_local_channel, get_local_offline_mode, get_local_storage,
+    set_cid, set_local_beacon,
+};
 use crate::utils::web3::Web3Service;
 
-use wasm_bindgen_futures::spawn_local;
-
 use web_sys::Storage;
 
 use yew::prelude::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::timeout::{TimeoutService, TimeoutTask};
 use yew::services::ConsoleService;
+use yew::Callback;
 
 use linked_data::beacon::Beacon;
 use linked_data::feed::Feed;
-use linked_data::video::VideoMetadata;
+use linked_data::video::{Engagement, SetupNode, VideoMetadata};
+
+#[cfg(debug_assertions)]
+use linked_data::debug_json;
 
 use cid::Cid;
 
 use reqwest::Error;
 
+/// Maximum number of VideoMetadata kept in memory.
+const METADATA_CAPACITY: usize = 100;
+
+/// Time the pointer must stay on a thumbnail before prefetching.
+const HOVER_SETTLE: Duration = Duration::from_millis(300);
+
+/// Maximum number of simultaneous prefetches, the oldest is cancelled first.
+const PREFETCH_BUDGET: usize = 2;
+
+/// Where the displayed feed came from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum FeedSource {
+    /// CID saved in local storage.
+    Cache,
+
+    /// IPNS resolution.
+    Network,
+}
+
 // Maintaining an updated content feed should be a different component.
 // Specialized component just refer to feed then dag get & deserialize (videos, blog post, etc...).
 
@@ -28,19 +54,47 @@ pub struct Videos {
     link: ComponentLink<Self>,
 
     ipfs: IpfsService,
+    web3: Web3Service,
 
     storage: Option<Storage>,
 
+    /// ENS name of the selected channel.
+    channel: String,
+
+    /// Serve cached beacon and list, only resolve on refresh.
+    offline: bool,
+
     beacon_cid: Option<Cid>,
     beacon: Option<Beacon>,
 
+    /// IPNS name of the resolved feed, resolved once per load or refresh.
+    feed_resolved: Option<String>,
+
     searching: bool,
 
     list_cid: Option<Cid>,
     feed: Option<Feed>,
+    feed_source: Option<FeedSource>,
+
+    /// Older feed pages loaded on demand, newest first.
+    pages: Vec<Feed>,
+    page_loading: bool,
 
-    call_count: usize,
+    /// Metadata requested but not yet received, rendered once empty.
+    pending: HashSet<Cid>,
     metadata_map: HashMap<Cid, VideoMetadata>,
+    /// Least recently used first.
+    metadata_order: VecDeque<Cid>,
+    engagement_map: HashMap<Cid, Engagement>,
+
+    hover_cb: Callback<Cid>,
+    _hover_task: Option<TimeoutTask>,
+    /// Videos whose setup was prefetched.
+    prefetched: HashSet<Cid>,
+    /// In-flight prefetches, oldest first.
+    prefetches: VecDeque<(Cid, CancelToken)>,
+
+    cancel: CancelToken,
 }
 
 pub enum Msg {
@@ -48,7 +102,14 @@ pub enum Msg {
     Beacon(Result<Beacon, Error>),
     List((Cid, Result<Feed, Error>)),
     ResolveList(Result<(Cid, Feed), Error>),
+    LoadPage,
+    Page((Cid, Result<Feed, Error>)),
     Metadata((Cid, Result<VideoMetadata, Error>)),
+    Engagement((Cid, Result<Engagement, Error>)),
+    Refresh,
+    Hover(Cid),
+    Prefetch(Cid),
+    Prefetched((Cid, Result<SetupNode, Error>)),
 }
 
 #[derive(Properties, Clone)]
@@ -67,32 +128,57 @@ impl Component for Videos {
         let window = web_sys::window().expect("Can't get window");
         let storage = get_local_storage(&window);
 
-        let beacon_cid = get_cid(ENS_NAME, storage.as_ref());
+        let cancel = CancelToken::new();
+
+        let channel = get_local_channel(storage.as_ref());
+
+        let beacon_cid = get_local_beacon(&channel, storage.as_ref());
+
+        let offline = start_offline(get_local_offline_mode(storage.as_ref()), beacon_cid);
 
         if let Some(cid) = beacon_cid {
             let cb = link.callback_once(Msg::Beacon);
             let client = ipfs.clone();
 
-            spawn_local(async move { cb.emit(client.dag_get(cid, Option::<String>::None).await) });
+            cancel.spawn(async move { cb.emit(client.dag_get(cid, Option::<String>::None).await) });
         }
 
-        // Check for beacon updates by resolving name.
-        let cb = link.callback_once(Msg::ResolveName);
-        let name = ENS_NAME.to_owned();
+        if !offline {
+            // Check for beacon updates by resolving name.
+            let cb = link.callback_once(Msg::ResolveName);
+            let client = web3.clone();
+            let name = channel.clone();
 
-        spawn_local(async move { cb.emit(web3.get_ipfs_content(name).await) });
+            cancel.spawn(async move { cb.emit(client.get_ipfs_content(name).await) });
+        }
+
+        let hover_cb = link.callback(Msg::Hover);
 
         Self {
             link,
             ipfs,
+            web3,
+            channel,
+            offline,
             beacon_cid,
             beacon: None,
+            feed_resolved: None,
             searching: true,
             list_cid: None,
             feed: None,
+            feed_source: None,
+            pages: Vec::new(),
+            page_loading: false,
             storage,
-            call_count: 0,
+            pending: HashSet::with_capacity(10),
             metadata_map: HashMap::with_capacity(10),
+            metadata_order: VecDeque::with_capacity(10),
+            engagement_map: HashMap::with_capacity(10),
+            hover_cb,
+            _hover_task: None,
+            prefetched: HashSet::with_capacity(10),
+            prefetches: VecDeque::with_capacity(PREFETCH_BUDGET),
+            cancel,
         }
     }
 
@@ -100,9 +186,16 @@ impl Component for Videos {
         match msg {
             Msg::ResolveName(result) => self.on_name_resolved(result),
             Msg::Beacon(result) => self.on_beacon_update(result),
-            Msg::List((cid, result)) => self.on_feed_update(cid, result),
+            Msg::List((cid, result)) => self.on_feed_update(cid, result, FeedSource::Cache),
             Msg::ResolveList(result) => self.on_feed_resolved(result),
+            Msg::LoadPage => self.load_page(),
+            Msg::Page((cid, result)) => self.on_page_update(cid, result),
             Msg::Metadata((cid, result)) => self.on_video_metadata_update(cid, result),
+            Msg::Engagement((cid, result)) => self.on_engagement_update(cid, result),
+            Msg::Refresh => self.on_refresh(),
+            Msg::Hover(cid) => self.on_hover(cid),
+            Msg::Prefetch(cid) => self.prefetch(cid),
+            Msg::Prefetched((cid, result)) => self.on_prefetched(cid, result),
         }
     }
 
@@ -116,32 +209,109 @@ impl Component for Videos {
         } else {
             let feed = self.feed.as_ref().unwrap();
 
+            let links = feed
+                .content
+                .iter()
+                .rev()
+                .chain(self.pages.iter().flat_map(|page| page.content.iter().rev()));
+
+            let load_more = if self.next_page().is_some() {
+                html! {
+                    <button class="load_more" disabled=self.page_loading onclick=self.link.callback(|_| Msg::LoadPage)>
+                        { "Load More" }
+                    </button>
+                }
+            } else {
+                html! {}
+            };
+
             html! {
+                <>
                 <div class="feed">
                 {
-                    for feed.content.iter().rev().map(|ipld| {
+                    for links.filter_map(|ipld| {
                         let cid = ipld.link;
-                        let mt = &self.metadata_map[&cid];
-                        html! {
-                            <VideoThumbnail metadata_cid=cid metadata=mt />
-                        }
+                        let mt = self.metadata_map.get(&cid)?;
+                        let engagement = self.engagement_map.get(&cid).copied();
+                        Some(html! {
+                            <VideoThumbnail metadata_cid=cid metadata=mt engagement=engagement on_hover=self.hover_cb.clone() />
+                        })
                     }
                     )
                 }
                 </div>
+                { load_more }
+                </>
+            }
+        };
+
+        let offline = if self.offline {
+            html! {
+                <div class="offline_badge">
+                    { "Offline" }
+                    <button onclick=self.link.callback(|_| Msg::Refresh)> { "Refresh" } </button>
+                </div>
             }
+        } else {
+            html! {}
         };
 
         html! {
             <div class="content_feed_page">
                 <Navbar />
+                { offline }
                 { content }
             </div>
         }
     }
+
+    fn destroy(&mut self) {
+        self.cancel.cancel();
+
+        for (_, mut cancel) in self.prefetches.drain(..) {
+            cancel.cancel();
+        }
+    }
 }
 
 impl Videos {
+    /// Callback when the user ask for network resolution while offline.
+    fn on_refresh(&mut self) -> bool {
+        self.offline = false;
+        self.feed_resolved = None;
+
+        // The feed is resolved once the name resolves.
+        let cb = self.link.callback_once(Msg::ResolveName);
+        let client = self.web3.clone();
+        let name = self.channel.clone();
+
+        self.cancel
+            .spawn(async move { cb.emit(client.get_ipfs_content(name).await) });
+
+        true
+    }
+
+    /// Resolve the feed of the current beacon unless offline or already resolved.
+    fn resolve_feed(&mut self) {
+        let ipns = match self.beacon.as_ref() {
+            Some(beacon) => &beacon.content_feed,
+            None => return,
+        };
+
+        if !should_resolve_feed(self.offline, self.feed_resolved.as_deref(), ipns) {
+            return;
+        }
+
+        self.feed_resolved = Some(ipns.clone());
+
+        let cb = self.link.callback_once(Msg::ResolveList);
+        let client = self.ipfs.clone();
+        let ipns = ipns.clone();
+
+        self.cancel
+            .spawn(async move { cb.emit(client.resolve_and_dag_get(ipns).await) });
+    }
+
     /// Callback when Ethereum Name Service resolve name to beacon Cid.
     fn on_name_resolved(&mut self, res: Result<Cid, web3::contract::Error>) -> bool {
         let cid = match res {
@@ -154,6 +324,7 @@ impl Videos {
 
         if let Some(beacon_cid) = self.beacon_cid.as_ref() {
             if *beacon_cid == cid {
+                self.resolve_feed();
                 return false;
             }
         }
@@ -161,12 +332,13 @@ impl Videos {
         let cb = self.link.callback_once(Msg::Beacon);
         let client = self.ipfs.clone();
 
-        spawn_local(async move { cb.emit(client.dag_get(cid, Option::<String>::None).await) });
+        self.cancel
+            .spawn(async move { cb.emit(client.dag_get(cid, Option::<String>::None).await) });
 
         #[cfg(debug_assertions)]
         ConsoleService::info("Name Update");
 
-        set_local_beacon(ENS_NAME, &cid, self.storage.as_ref());
+        set_local_beacon(&self.channel, &cid, self.storage.as_ref());
 
         self.beacon_cid = Some(cid);
 
@@ -192,19 +364,15 @@ impl Videos {
             let cb = self.link.callback_once(Msg::List);
             let client = self.ipfs.clone();
 
-            spawn_local(async move {
+            self.cancel.spawn(async move {
                 cb.emit((cid, client.dag_get(cid, Option::<String>::None).await))
             });
         }
 
-        let cb = self.link.callback_once(Msg::ResolveList);
-        let client = self.ipfs.clone();
-        let ipns = beacon.content_feed.clone();
-
-        spawn_local(async move { cb.emit(client.resolve_and_dag_get(ipns).await) });
-
         self.beacon = Some(beacon);
 
+        self.resolve_feed();
+
         false
     }
 
@@ -218,11 +386,16 @@ impl Videos {
             }
         };
 
-        self.on_feed_update(cid, Ok(feed))
+        self.on_feed_update(cid, Ok(feed), FeedSource::Network)
     }
 
     /// Callback when IPFS resolve and dag get Feed node.
-    fn on_feed_update(&mut self, list_cid: Cid, res: Result<Feed, Error>) -> bool {
+    fn on_feed_update(
+        &mut self,
+        list_cid: Cid,
+        res: Result<Feed, Error>,
+        source: FeedSource,
+    ) -> bool {
         let feed = match res {
             Ok(l) => l,
             Err(e) => {
@@ -237,13 +410,24 @@ impl Videos {
             }
         }
 
+        if let Some(current) = self.feed.as_ref() {
+            if is_stale_feed(&feed, source, current, self.feed_source) {
+                #[cfg(debug_assertions)]
+                ConsoleService::info(&format!("Stale Content Feed From {:?} Ignored", source));
+
+                return false;
+            }
+        }
+
         let beacon = match self.beacon.as_ref() {
             Some(b) => b,
             None => return false,
         };
 
         #[cfg(debug_assertions)]
-        ConsoleService::info("Content Feed Update");
+        ConsoleService::info(&format!("Content Feed Update From {:?}", source));
+
+        self.feed_source = Some(source);
 
         if let Some(old_list_cid) = self.list_cid.as_ref() {
             if *old_list_cid != list_cid {
@@ -255,37 +439,142 @@ impl Videos {
             set_cid(&beacon.content_feed, &list_cid, self.storage.as_ref());
         }
 
+        // Pages are immutable, the loaded ones stay valid if the newest still links to them.
+        let previous = self.feed.as_ref().and_then(|feed| feed.previous);
+        if feed.previous != previous {
+            self.pages.clear();
+            self.page_loading = false;
+        }
+
         if feed.content.is_empty() {
             self.feed = Some(feed);
             self.searching = false;
             return true;
         }
 
-        for metadata in feed.content.iter().rev() {
+        let count = self.fetch_metadata(&feed);
+
+        self.feed = Some(feed);
+        self.searching = false;
+
+        self.evict_metadata();
+
+        count == 0
+    }
+
+    /// Request the metadata of a feed page, newest first. Returns the number of requests.
+    fn fetch_metadata(&mut self, feed: &Feed) -> usize {
+        let requests = new_requests(feed, &self.metadata_map, &mut self.pending);
+
+        for &cid in requests.iter() {
             let cb = self.link.callback_once(Msg::Metadata);
             let client = self.ipfs.clone();
-            let cid = metadata.link;
 
-            spawn_local(async move {
+            self.cancel.spawn(async move {
                 cb.emit((cid, client.dag_get(cid, Option::<String>::None).await))
             });
         }
 
-        self.call_count += feed.content.len();
+        requests.len()
+    }
 
-        self.feed = Some(feed);
-        self.searching = false;
+    /// Link to the next older feed page not yet loaded.
+    fn next_page(&self) -> Option<Cid> {
+        let previous = match self.pages.last() {
+            Some(page) => page.previous,
+            None => self.feed.as_ref()?.previous,
+        };
 
-        false
+        previous.map(|ipld| ipld.link)
+    }
+
+    /// Callback when the user ask for older content.
+    fn load_page(&mut self) -> bool {
+        if self.page_loading {
+            return false;
+        }
+
+        let cid = match self.next_page() {
+            Some(cid) => cid,
+            None => return false,
+        };
+
+        let cb = self.link.callback_once(Msg::Page);
+        let client = self.ipfs.clone();
+
+        self.cancel.spawn(async move {
+            cb.emit((cid, client.dag_get(cid, Option::<String>::None).await))
+        });
+
+        self.page_loading = true;
+
+        true
+    }
+
+    /// Callback when IPFS dag get returns an older Feed page.
+    fn on_page_update(&mut self, cid: Cid, res: Result<Feed, Error>) -> bool {
+        // The feed changed while loading.
+        if self.next_page() != Some(cid) {
+            return false;
+        }
+
+        self.page_loading = false;
+
+        let page = match res {
+            Ok(page) => page,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return true;
+            }
+        };
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("Feed Page Loaded => {}", cid));
+
+        let count = self.fetch_metadata(&page);
+
+        self.pages.push(page);
+
+        self.evict_metadata();
+
+        count == 0
+    }
+
+    /// Remove least recently used metadata not in the current feed.
+    fn evict_metadata(&mut self) {
+        let feed = match self.feed.as_ref() {
+            Some(feed) => feed,
+            None => return,
+        };
+
+        let pages = &self.pages;
+
+        let evicted = evict_lru(
+            &mut self.metadata_map,
+            &mut self.metadata_order,
+            METADATA_CAPACITY,
+            |cid| {
+                std::iter::once(feed)
+                    .chain(pages.iter())
+                    .any(|page| page.content.iter().any(|ipld| ipld.link == *cid))
+            },
+        );
+
+        for cid in evicted {
+            self.engagement_map.remove(&cid);
+        }
     }
 
     /// Callback when IPFS dag get returns VideoMetadata node.
     fn on_video_metadata_update(&mut self, cid: Cid, res: Result<VideoMetadata, Error>) -> bool {
+        // Stale callbacks are not in the set and leave it unchanged.
+        let was_pending = self.pending.remove(&cid);
+
         let metadata = match res {
             Ok(d) => d,
             Err(e) => {
                 ConsoleService::error(&format!("{:?}", e));
-                return false;
+                return was_pending && self.pending.is_empty();
             }
         };
 
@@ -293,16 +582,31 @@ impl Videos {
         ConsoleService::info(&format!(
             "Display Add => {} \n {}",
             &cid.to_string(),
-            &serde_json::to_string_pretty(&metadata).expect("Can't print")
+            &debug_json(&metadata)
         ));
 
-        self.metadata_map.insert(cid, metadata);
+        if let Some(link) = metadata.engagement {
+            let cb = self.link.callback_once(Msg::Engagement);
+            let client = self.ipfs.clone();
+            let engagement_cid = link.link;
 
-        if self.call_count > 0 {
-            self.call_count -= 1;
+            self.cancel.spawn(async move {
+                cb.emit((
+                    cid,
+                    client.dag_get(engagement_cid, Option::<String>::None).await,
+                ))
+            });
         }
 
-        if self.call_count == 0 {
+        if self.metadata_map.insert(cid, metadata).is_some() {
+            self.metadata_order.retain(|item| *item != cid);
+        }
+
+        self.metadata_order.push_back(cid);
+
+        self.evict_metadata();
+
+        if self.pending.is_empty() {
             #[cfg(debug_assertions)]
             ConsoleService::info("Refresh");
 
@@ -311,4 +615,362 @@ impl Videos {
 
         false
     }
+
+    /// Callback when a thumbnail is hovered, prefetch once the pointer settles.
+    fn on_hover(&mut self, cid: Cid) -> bool {
+        if self.prefetched.contains(&cid) {
+            return false;
+        }
+
+        // Replacing the task cancels the previous hover.
+        self._hover_task = Some(TimeoutService::spawn(
+            HOVER_SETTLE,
+            self.link.callback_once(move |_| Msg::Prefetch(cid)),
+        ));
+
+        false
+    }
+
+    /// Fetch the setup node of this video so that playback starts faster.
+    fn prefetch(&mut self, cid: Cid) -> bool {
+        self._hover_task = None;
+
+        if self.prefetched.contains(&cid) || self.prefetches.iter().any(|(item, _)| *item == cid) {
+            return false;
+        }
+
+        let video_cid = match self.metadata_map.get(&cid) {
+            Some(metadata) => metadata.video.link,
+            None => return false,
+        };
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("Prefetch => {}", cid));
+
+        let cancel = CancelToken::new();
+        let cb = self.link.callback_once(Msg::Prefetched);
+        let client = self.ipfs.clone();
+
+        cancel.spawn(
+            async move { cb.emit((cid, client.dag_get(video_cid, Some(SETUP_PATH)).await)) },
+        );
+
+        // Superseded prefetches are cancelled.
+        for (_old, mut cancel) in push_prefetch(&mut self.prefetches, cid, cancel, PREFETCH_BUDGET)
+        {
+            #[cfg(debug_assertions)]
+            ConsoleService::info(&format!("Prefetch Cancelled => {}", _old));
+
+            cancel.cancel();
+        }
+
+        false
+    }
+
+    /// Callback when a prefetch completes.
+    fn on_prefetched(&mut self, cid: Cid, result: Result<SetupNode, Error>) -> bool {
+        self.prefetches.retain(|(item, _)| *item != cid);
+
+        match result {
+            Ok(_) => {
+                self.prefetched.insert(cid);
+            }
+            Err(e) => ConsoleService::warn(&format!("Prefetch failed {:?}", e)),
+        }
+
+        false
+    }
+
+    /// Callback when IPFS dag get returns Engagement node.
+    fn on_engagement_update(&mut self, cid: Cid, res: Result<Engagement, Error>) -> bool {
+        let engagement = match res {
+            Ok(e) => e,
+            Err(e) => {
+                ConsoleService::error(&format!("{:?}", e));
+                return false;
+            }
+        };
+
+        self.engagement_map.insert(cid, engagement);
+
+        self.pending.is_empty()
+    }
+}
+
+/// Metadata of the page neither loaded nor requested, newest first. They are added to the pending set.
+fn new_requests(
+    page: &Feed,
+    loaded: &HashMap<Cid, VideoMetadata>,
+    pending: &mut HashSet<Cid>,
+) -> Vec<Cid> {
+    page.content
+        .iter()
+        .rev()
+        .map(|ipld| ipld.link)
+        .filter(|cid| !loaded.contains_key(cid) && pending.insert(*cid))
+        .collect()
+}
+
+/// Offline mode is only possible with a cached beacon, the name is not resolved.
+fn start_offline(offline_mode: bool, cached_beacon: Option<Cid>) -> bool {
+    offline_mode && cached_beacon.is_some()
+}
+
+/// True if the feed should be resolved, each IPNS name only once.
+fn should_resolve_feed(offline: bool, resolved: Option<&str>, ipns: &str) -> bool {
+    !offline && resolved != Some(ipns)
+}
+
+/// Cached and resolved feeds can arrive in any order, the newest one wins.
+fn is_stale_feed(
+    feed: &Feed,
+    source: FeedSource,
+    current: &Feed,
+    current_source: Option<FeedSource>,
+) -> bool {
+    feed.sequence < current.sequence
+        || (feed.sequence == current.sequence
+            && source == FeedSource::Cache
+            && current_source == Some(FeedSource::Network))
+}
+
+/// Remove least recently used entries over capacity, displayed ones stay. Returns the evicted CIDs.
+fn evict_lru<T, F>(
+    map: &mut HashMap<Cid, T>,
+    order: &mut VecDeque<Cid>,
+    capacity: usize,
+    displayed: F,
+) -> Vec<Cid>
+where
+    F: Fn(&Cid) -> bool,
+{
+    let mut evicted = Vec::new();
+    let mut i = 0;
+
+    while map.len() > capacity && i < order.len() {
+        let cid = order[i];
+
+        if displayed(&cid) {
+            i += 1;
+            continue;
+        }
+
+        order.remove(i);
+        map.remove(&cid);
+        evicted.push(cid);
+    }
+
+    evicted
+}
+
+/// Add a prefetch, returning the oldest ones over budget.
+fn push_prefetch<T>(
+    prefetches: &mut VecDeque<(Cid, T)>,
+    cid: Cid,
+    item: T,
+    budget: usize,
+) -> Vec<(Cid, T)> {
+    prefetches.push_back((cid, item));
+
+    let excess = prefetches.len().saturating_sub(budget);
+
+    prefetches.drain(..excess).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cid::multihash::{Code, MultihashDigest};
+
+    fn metadata_cid(n: u64) -> Cid {
+        Cid::new_v1(0x71, Code::Sha2_256.digest(&n.to_be_bytes()))
+    }
+
+    fn feed(content: &[u64]) -> Feed {
+        Feed {
+            content: content.iter().map(|n| metadata_cid(*n).into()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlapping_refresh_requests_once() {
+        let loaded = HashMap::new();
+        let mut pending = HashSet::new();
+
+        let first = new_requests(&feed(&[1, 2]), &loaded, &mut pending);
+        assert_eq!(first, vec![metadata_cid(2), metadata_cid(1)]);
+
+        // Refreshed before the first requests completed.
+        let second = new_requests(&feed(&[1, 2, 3]), &loaded, &mut pending);
+        assert_eq!(second, vec![metadata_cid(3)]);
+
+        assert!(pending.remove(&metadata_cid(1)));
+        assert!(pending.remove(&metadata_cid(2)));
+        assert!(!pending.is_empty());
+
+        // Stale callback, not pending anymore.
+        assert!(!pending.remove(&metadata_cid(1)));
+
+        assert!(pending.remove(&metadata_cid(3)));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn loaded_metadata_not_requested() {
+        let mut pending = HashSet::new();
+
+        let mut loaded = HashMap::new();
+        loaded.insert(
+            metadata_cid(1),
+            VideoMetadata::create("Title".into(), 60.0, Cid::default(), Cid::default()),
+        );
+
+        let requests = new_requests(&feed(&[1, 2]), &loaded, &mut pending);
+
+        assert_eq!(requests, vec![metadata_cid(2)]);
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[test]
+    fn rapid_hovers_stay_within_budget() {
+        let mut prefetches = VecDeque::new();
+        let mut superseded = Vec::new();
+
+        for n in 0..5 {
+            superseded.extend(push_prefetch(
+                &mut prefetches,
+                metadata_cid(n),
+                n,
+                PREFETCH_BUDGET,
+            ));
+
+            assert!(prefetches.len() <= PREFETCH_BUDGET);
+        }
+
+        let in_flight: Vec<u64> = prefetches.iter().map(|(_, n)| *n).collect();
+        assert_eq!(in_flight, vec![3, 4]);
+
+        let cancelled: Vec<u64> = superseded.iter().map(|(_, n)| *n).collect();
+        assert_eq!(cancelled, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn stale_resolved_feed_after_newer_cached_feed() {
+        let mut cached = feed(&[1, 2]);
+        cached.sequence = 2;
+
+        let mut resolved = feed(&[1]);
+        resolved.sequence = 1;
+
+        assert!(is_stale_feed(
+            &resolved,
+            FeedSource::Network,
+            &cached,
+            Some(FeedSource::Cache)
+        ));
+        assert!(!is_stale_feed(
+            &cached,
+            FeedSource::Cache,
+            &resolved,
+            Some(FeedSource::Network)
+        ));
+    }
+
+    #[test]
+    fn same_sequence_resolved_feed_wins() {
+        let current = feed(&[1]);
+        let other = feed(&[1]);
+
+        assert!(is_stale_feed(
+            &other,
+            FeedSource::Cache,
+            &current,
+            Some(FeedSource::Network)
+        ));
+        assert!(!is_stale_feed(
+            &other,
+            FeedSource::Network,
+            &current,
+            Some(FeedSource::Cache)
+        ));
+    }
+
+    #[test]
+    fn refresh_resolves_feed_once() {
+        let ipns = "k51qzi5uqu5dlvj2baxnqndepeb86cbk3ng7n3i46uzyxzyqj2xjonzllnv0v8";
+
+        // Offline, nothing is resolved until refresh.
+        assert!(!should_resolve_feed(true, None, ipns));
+
+        // After a refresh, the name and beacon callbacks both ask for the feed.
+        let mut resolved: Option<String> = None;
+        let mut count = 0;
+
+        for _ in 0..2 {
+            if should_resolve_feed(false, resolved.as_deref(), ipns) {
+                resolved = Some(ipns.to_owned());
+                count += 1;
+            }
+        }
+
+        assert_eq!(count, 1);
+
+        // A new beacon with another feed is resolved too.
+        assert!(should_resolve_feed(false, resolved.as_deref(), "other"));
+    }
+
+    #[test]
+    fn offline_load_skips_name_resolution() {
+        assert!(start_offline(true, Some(metadata_cid(1))));
+
+        // Nothing to serve without a cached beacon.
+        assert!(!start_offline(true, None));
+        assert!(!start_offline(false, Some(metadata_cid(1))));
+    }
+
+    #[test]
+    fn eviction_at_capacity_keeps_displayed() {
+        let mut map = HashMap::new();
+        let mut order = VecDeque::new();
+
+        for n in 0..5 {
+            map.insert(metadata_cid(n), n);
+            order.push_back(metadata_cid(n));
+        }
+
+        // The oldest one is still on screen.
+        let displayed = metadata_cid(0);
+
+        let evicted = evict_lru(&mut map, &mut order, 3, |cid| *cid == displayed);
+
+        assert_eq!(evicted, vec![metadata_cid(1), metadata_cid(2)]);
+        assert_eq!(map.len(), 3);
+        assert!(map.contains_key(&displayed));
+        assert!(order
+            .iter()
+            .eq([metadata_cid(0), metadata_cid(3), metadata_cid(4)].iter()));
+    }
+
+    #[test]
+    fn evicted_metadata_requested_again() {
+        let mut map = HashMap::new();
+        let mut order = VecDeque::new();
+
+        for n in 0..2 {
+            map.insert(
+                metadata_cid(n),
+                VideoMetadata::create("Title".into(), 60.0, Cid::default(), Cid::default()),
+            );
+            order.push_back(metadata_cid(n));
+        }
+
+        evict_lru(&mut map, &mut order, 1, |_| false);
+
+        // Scrolled back to the evicted video.
+        let requests = new_requests(&feed(&[0]), &map, &mut HashSet::new());
+
+        assert_eq!(requests, vec![metadata_cid(0)]);
+    }
 }
@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+const PREFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitrateUnit {
+    Bits,
+    Bytes,
+}
+
+impl Default for BitrateUnit {
+    fn default() -> Self {
+        BitrateUnit::Bits
+    }
+}
+
+impl BitrateUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BitrateUnit::Bits => "bits",
+            BitrateUnit::Bytes => "bytes",
+        }
+    }
+}
+
+impl FromStr for BitrateUnit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bits" => Ok(BitrateUnit::Bits),
+            "bytes" => Ok(BitrateUnit::Bytes),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Format bandwidth in bits per second as human-readable text (e.g. "2.5 Mbps", "480 kB/s").
+pub fn format_bitrate(bits_per_second: usize, unit: BitrateUnit) -> String {
+    let (mut value, suffix) = match unit {
+        BitrateUnit::Bits => (bits_per_second as f64, "bps"),
+        BitrateUnit::Bytes => (bits_per_second as f64 / 8.0, "B/s"),
+    };
+
+    let mut prefix = 0;
+
+    while value >= 1000.0 && prefix < PREFIXES.len() - 1 {
+        value /= 1000.0;
+        prefix += 1;
+    }
+
+    if prefix == 0 || value >= 100.0 {
+        format!("{:.0} {}{}", value, PREFIXES[prefix], suffix)
+    } else {
+        format!("{:.1} {}{}", value, PREFIXES[prefix], suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_below_kilo() {
+        assert_eq!(format_bitrate(0, BitrateUnit::Bits), "0 bps");
+        assert_eq!(format_bitrate(999, BitrateUnit::Bits), "999 bps");
+    }
+
+    #[test]
+    fn prefix_boundaries() {
+        assert_eq!(format_bitrate(1_000, BitrateUnit::Bits), "1.0 kbps");
+        assert_eq!(format_bitrate(99_000, BitrateUnit::Bits), "99.0 kbps");
+        assert_eq!(format_bitrate(100_000, BitrateUnit::Bits), "100 kbps");
+        assert_eq!(format_bitrate(2_500_000, BitrateUnit::Bits), "2.5 Mbps");
+    }
+
+    #[test]
+    fn bytes_divide_by_eight() {
+        assert_eq!(format_bitrate(8, BitrateUnit::Bytes), "1 B/s");
+        assert_eq!(format_bitrate(3_840_000, BitrateUnit::Bytes), "480 kB/s");
+        assert_eq!(format_bitrate(20_000_000, BitrateUnit::Bytes), "2.5 MB/s");
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn tera_is_the_largest_prefix() {
+        assert_eq!(
+            format_bitrate(2_000_000_000_000, BitrateUnit::Bits),
+            "2.0 Tbps"
+        );
+        assert_eq!(
+            format_bitrate(5_000_000_000_000_000, BitrateUnit::Bits),
+            "5000 Tbps"
+        );
+    }
+
+    #[test]
+    fn unit_round_trip() {
+        for unit in [BitrateUnit::Bits, BitrateUnit::Bytes].iter() {
+            assert_eq!(unit.as_str().parse(), Ok(*unit));
+        }
+
+        assert_eq!("bauds".parse::<BitrateUnit>(), Err(()));
+    }
+}
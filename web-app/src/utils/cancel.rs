@@ -0,0 +1,91 @@
+use std::future::Future;
+
+use futures::channel::oneshot::{channel, Receiver, Sender};
+use futures::future::{select, FutureExt, Shared};
+
+use wasm_bindgen_futures::spawn_local;
+
+/// Drop all futures spawned with this token once cancelled.
+pub struct CancelToken {
+    sender: Option<Sender<()>>,
+    receiver: Shared<Receiver<()>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+
+        Self {
+            sender: Some(sender),
+            receiver: receiver.shared(),
+        }
+    }
+
+    /// Spawn a future that will stop as soon as the token is cancelled.
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        spawn_local(self.cancellable(future));
+    }
+
+    /// Wrap a future so that it stops as soon as the token is cancelled.
+    fn cancellable<F>(&self, future: F) -> impl Future<Output = ()>
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let cancelled = self.receiver.clone();
+
+        async move {
+            let _ = select(Box::pin(future), cancelled).await;
+        }
+    }
+
+    /// Abort all in-flight futures.
+    pub fn cancel(&mut self) {
+        self.sender.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use futures::executor::block_on;
+    use futures::future::pending;
+
+    #[test]
+    fn cancel_aborts_pending_fetch() {
+        let mut token = CancelToken::new();
+
+        let completed = Rc::new(Cell::new(false));
+        let flag = completed.clone();
+
+        // Never answers, like a fetch still in flight.
+        let fetch = token.cancellable(async move {
+            pending::<()>().await;
+            flag.set(true);
+        });
+
+        token.cancel();
+
+        block_on(fetch);
+
+        assert!(!completed.get());
+    }
+
+    #[test]
+    fn fetch_completes_without_cancel() {
+        let token = CancelToken::new();
+
+        let completed = Rc::new(Cell::new(false));
+        let flag = completed.clone();
+
+        block_on(token.cancellable(async move { flag.set(true) }));
+
+        assert!(completed.get());
+    }
+}
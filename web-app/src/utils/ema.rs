@@ -6,22 +6,130 @@ use web_sys::{Performance, Window};
 /// [0.0 <= P <= 1.0]
 const MOVING_AVERAGE_P: f64 = 0.15;
 
+/// Smallest accepted smoothing factor, lower would never move the average.
+const MIN_ALPHA: f64 = 0.01;
+
+/// Default ratio between a download speed and the average considered a large shift.
+pub const DEFAULT_RESET_THRESHOLD: f64 = 3.0;
+
+/// Number of consecutive large shifts before the average is re-seeded.
+const RESET_SAMPLES: usize = 3;
+
 #[derive(Clone)]
 pub struct ExponentialMovingAverage {
     performance: Performance,
 
     download_time: f64,
 
+    speed: AverageSpeed,
+}
+
+/// Average of download speeds, re-seeded after sustained large shifts.
+#[derive(Clone, Debug)]
+struct AverageSpeed {
     moving_average: f64,
+    alpha: f64,
+
+    reset_threshold: f64,
+    deviation_count: usize,
+}
+
+impl AverageSpeed {
+    fn new(alpha: f64) -> Self {
+        Self {
+            moving_average: 0.0,
+            alpha,
+
+            reset_threshold: DEFAULT_RESET_THRESHOLD,
+            deviation_count: 0,
+        }
+    }
+
+    /// Add a download speed to the average. Returns true if the average was reset.
+    fn add(&mut self, new_bitrate: f64) -> bool {
+        if self.is_large_shift(new_bitrate) {
+            self.deviation_count += 1;
+        } else {
+            self.deviation_count = 0;
+        }
+
+        if self.deviation_count >= RESET_SAMPLES {
+            self.deviation_count = 0;
+            self.moving_average = new_bitrate;
+            return true;
+        }
+
+        if self.moving_average >= 0.0 {
+            self.moving_average += (new_bitrate - self.moving_average) * self.alpha;
+        } else {
+            self.moving_average = new_bitrate; // the first entry
+        }
+
+        false
+    }
+
+    /// True if the new bitrate differs from the average by more than the threshold ratio.
+    fn is_large_shift(&self, new_bitrate: f64) -> bool {
+        if self.moving_average <= 0.0 || new_bitrate <= 0.0 {
+            return false;
+        }
+
+        let ratio = if new_bitrate > self.moving_average {
+            new_bitrate / self.moving_average
+        } else {
+            self.moving_average / new_bitrate
+        };
+
+        ratio >= self.reset_threshold
+    }
 }
 
 impl ExponentialMovingAverage {
     pub fn new(window: &Window) -> Self {
+        Self::with_alpha(window, MOVING_AVERAGE_P)
+    }
+
+    /// Alpha is the weight given to each new download speed.
+    ///
+    /// Close to 1.0, a bandwidth drop is reflected in the next recalculate_average_speed().
+    /// Close to 0.0, many slow downloads are needed before the average follows.
+    /// Values outside (0, 1] are clamped.
+    pub fn with_alpha(window: &Window, alpha: f64) -> Self {
+        let alpha = if alpha > 0.0 && alpha <= 1.0 {
+            alpha
+        } else {
+            ConsoleService::warn(&format!("Invalid EMA alpha {}, clamping", alpha));
+
+            alpha.max(MIN_ALPHA).min(1.0)
+        };
+
         Self {
             performance: window.performance().expect("Can't get perf"),
 
             download_time: 0.0,
-            moving_average: 0.0,
+
+            speed: AverageSpeed::new(alpha),
+        }
+    }
+
+    /// Start from an estimated speed instead of the first download.
+    pub fn seed(&mut self, bitrate: f64) {
+        if self.speed.moving_average <= 0.0 {
+            self.speed.moving_average = bitrate;
+        }
+    }
+
+    /// Set the speed to average ratio that triggers a reset when sustained.
+    pub fn set_reset_threshold(&mut self, threshold: f64) {
+        self.speed.reset_threshold = threshold;
+    }
+
+    /// Current average download speed if any was recorded.
+    pub fn average(&self) -> Option<f64> {
+        if self.speed.moving_average > 0.0 {
+            Some(self.speed.moving_average)
+        } else {
+            None
         }
     }
 
@@ -44,18 +152,84 @@ impl ExponentialMovingAverage {
 
         let new_bitrate = bandwidth / time * 1000.0;
 
-        if self.moving_average >= 0.0 {
-            self.moving_average += (new_bitrate - self.moving_average) * MOVING_AVERAGE_P;
-        } else {
-            self.moving_average = new_bitrate; // the first entry
+        let _reset = self.speed.add(new_bitrate);
+
+        #[cfg(debug_assertions)]
+        if _reset {
+            ConsoleService::info("Bandwidth Shift, Average Reset");
         }
 
         #[cfg(debug_assertions)]
         ConsoleService::info(&format!(
             "Average Download Speed {:.0} kbps",
-            self.moving_average / 1000.0
+            self.speed.moving_average / 1000.0
         ));
 
-        Some(self.moving_average)
+        Some(self.speed.moving_average)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(average: f64) -> AverageSpeed {
+        let mut speed = AverageSpeed::new(MOVING_AVERAGE_P);
+        speed.moving_average = average;
+        speed
+    }
+
+    #[test]
+    fn small_changes_are_averaged() {
+        let mut speed = seeded(1000.0);
+
+        assert!(!speed.add(2000.0));
+        assert!((speed.moving_average - 1150.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reset_after_sustained_large_shifts() {
+        let mut speed = seeded(10_000.0);
+
+        for _ in 1..RESET_SAMPLES {
+            assert!(!speed.add(1000.0));
+        }
+
+        assert!(speed.add(1000.0));
+        assert_eq!(speed.moving_average, 1000.0);
+        assert_eq!(speed.deviation_count, 0);
+    }
+
+    #[test]
+    fn normal_sample_clears_deviations() {
+        let mut speed = seeded(10_000.0);
+
+        for _ in 1..RESET_SAMPLES {
+            speed.add(1000.0);
+        }
+
+        // Close enough to the pulled down average to not count as a shift.
+        let average = speed.moving_average;
+        speed.add(average);
+        assert_eq!(speed.deviation_count, 0);
+
+        assert!(!speed.add(average / DEFAULT_RESET_THRESHOLD / 2.0));
+    }
+
+    #[test]
+    fn shift_ratio_in_both_directions() {
+        let speed = seeded(1000.0);
+
+        assert!(speed.is_large_shift(3000.0));
+        assert!(speed.is_large_shift(1000.0 / 3.0));
+        assert!(!speed.is_large_shift(2999.0));
+        assert!(!speed.is_large_shift(0.0));
+    }
+
+    #[test]
+    fn no_shift_without_average() {
+        let speed = AverageSpeed::new(MOVING_AVERAGE_P);
+
+        assert!(!speed.is_large_shift(1_000_000.0));
     }
 }
@@ -1,9 +1,15 @@
 use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::utils::local_storage::{get_local_ipfs_addrs, get_local_storage, set_local_ipfs_addrs};
+use crate::utils::local_storage::{
+    get_local_gateway, get_local_ipfs_addrs, get_local_ipfs_headers, get_local_segment_cache,
+    get_local_storage, set_local_ipfs_addrs,
+};
+use crate::utils::segment_cache::SegmentCache;
 
 use futures::join;
 use futures_util::{AsyncBufReadExt, StreamExt, TryStreamExt};
@@ -14,18 +20,66 @@ use serde::{Deserialize, Deserializer, Serialize};
 use yew::services::ConsoleService;
 use yew::Callback;
 
+#[cfg(debug_assertions)]
+use linked_data::debug_json;
+
 use cid::multibase::Base;
 use cid::Cid;
 
 use reqwest::multipart::Form;
-use reqwest::{Client, Error, Url};
+use reqwest::{Client, Error, RequestBuilder, Url};
 
 const DEFAULT_URI: &str = "http://localhost:5001/api/v0/";
 
+/// Megabytes of recently fetched media segments kept in memory, unless set otherwise.
+pub const DEFAULT_SEGMENT_CACHE_MB: usize = 32;
+
+/// Categories of IPFS API endpoints, each can be sent different headers.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiOperation {
+    Cat,
+    Dag,
+    Name,
+    PubSub,
+    Id,
+}
+
+/// Extra HTTP headers, name and value, sent with each API request category.
+pub type ApiHeaders = HashMap<ApiOperation, HashMap<String, String>>;
+
+/// Where a downloaded segment came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endpoint {
+    Cache,
+    Api,
+    Gateway,
+}
+
+/// Gateway address as a base URL, paths are joined after its last segment.
+/// Without a trailing slash "https://gateway/ipfs" would lose its "ipfs" segment.
+pub fn gateway_url(addrs: &str) -> Option<Url> {
+    let mut url = Url::parse(addrs.trim()).ok()?;
+
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+
+    Some(url)
+}
+
 #[derive(Clone)]
 pub struct IpfsService {
     client: Client,
     base_url: Rc<Url>,
+    headers: Rc<ApiHeaders>,
+    /// HTTP gateway tried when the API fails to return a segment.
+    gateway_url: Option<Rc<Url>>,
+    /// None when disabled in the settings.
+    segment_cache: Option<Rc<RefCell<SegmentCache>>>,
+    /// Endpoint that served the last segment downloaded.
+    last_endpoint: Rc<Cell<Option<Endpoint>>>,
 }
 
 impl IpfsService {
@@ -47,10 +101,105 @@ impl IpfsService {
             url = Some(Url::parse(DEFAULT_URI).expect("Invalid Url"));
         }
 
-        let client = Client::new();
-        let base_url = Rc::from(url.unwrap());
+        let gateway_url = get_local_gateway(storage.as_ref())
+            .and_then(|addrs| gateway_url(&addrs))
+            .map(Rc::from);
 
-        Self { client, base_url }
+        // Configured as JSON, for example {"dag": {"Authorization": "Bearer ..."}}.
+        let headers = match get_local_ipfs_headers(storage.as_ref()) {
+            Some(json) => match serde_json::from_str::<ApiHeaders>(&json) {
+                Ok(headers) => headers,
+                Err(e) => {
+                    ConsoleService::error(&format!("Invalid IPFS API headers {}", e));
+                    ApiHeaders::new()
+                }
+            },
+            None => ApiHeaders::new(),
+        };
+
+        // Values may be credentials, only names are logged.
+        #[cfg(debug_assertions)]
+        for (operation, headers) in headers.iter() {
+            let names: Vec<&str> = headers.keys().map(String::as_str).collect();
+
+            ConsoleService::info(&format!(
+                "IPFS: {:?} headers => {}",
+                operation,
+                names.join(", ")
+            ));
+        }
+
+        let cache_mb =
+            get_local_segment_cache(storage.as_ref()).unwrap_or(DEFAULT_SEGMENT_CACHE_MB);
+
+        Self::with_config(url.unwrap(), gateway_url, headers, cache_mb)
+    }
+
+    fn with_config(
+        base_url: Url,
+        gateway_url: Option<Rc<Url>>,
+        headers: ApiHeaders,
+        cache_mb: usize,
+    ) -> Self {
+        // Bytes overflow on wasm32 above 4095 MB, the default is used instead.
+        let segment_cache = match cache_mb {
+            0 => None,
+            mb => {
+                let bytes = mb
+                    .checked_mul(1024 * 1024)
+                    .unwrap_or(DEFAULT_SEGMENT_CACHE_MB * 1024 * 1024);
+
+                Some(Rc::new(RefCell::new(SegmentCache::new(bytes))))
+            }
+        };
+
+        Self {
+            client: Client::new(),
+            base_url: Rc::from(base_url),
+            headers: Rc::from(headers),
+            gateway_url,
+            segment_cache,
+            last_endpoint: Rc::new(Cell::new(None)),
+        }
+    }
+
+    /// Returns the cached segment at this path.
+    fn cached(&self, path: &str) -> Option<Vec<u8>> {
+        self.segment_cache.as_ref()?.borrow_mut().get(path)
+    }
+
+    /// Returns both segments only if both are cached.
+    fn cached_pair(&self, audio_path: &str, video_path: &str) -> Option<(Vec<u8>, Vec<u8>)> {
+        let audio_data = self.cached(audio_path)?;
+        let video_data = self.cached(video_path)?;
+
+        self.last_endpoint.set(Some(Endpoint::Cache));
+
+        Some((audio_data, video_data))
+    }
+
+    fn cache(&self, path: String, data: &[u8]) {
+        if let Some(cache) = self.segment_cache.as_ref() {
+            cache.borrow_mut().insert(path, data.to_vec());
+        }
+    }
+
+    /// Endpoint that served the last segment downloaded, for diagnostics.
+    pub fn last_endpoint(&self) -> Option<Endpoint> {
+        self.last_endpoint.get()
+    }
+
+    /// API request with the headers configured for this operation.
+    fn post(&self, operation: ApiOperation, url: Url) -> RequestBuilder {
+        let mut request = self.client.post(url);
+
+        if let Some(headers) = self.headers.get(&operation) {
+            for (name, value) in headers.iter() {
+                request = request.header(name.as_str(), value.as_str());
+            }
+        }
+
+        request
     }
 
     /// Download content from block with this CID.
@@ -58,8 +207,7 @@ impl IpfsService {
         let url = self.base_url.join("cat").expect("Invalid URL");
 
         let bytes = self
-            .client
-            .post(url)
+            .post(ApiOperation::Cat, url)
             .query(&[("arg", &cid.to_string())])
             .send()
             .await?
@@ -69,7 +217,73 @@ impl IpfsService {
         Ok(bytes.to_vec())
     }
 
+    /// Download content from a path.
+    /// Recently downloaded segments are served from the cache.
+    /// If the API fails, the fallback gateway is tried before giving up.
+    pub async fn path_cat<U>(&self, path: U) -> Result<Vec<u8>, Error>
+    where
+        U: Into<Cow<'static, str>>,
+    {
+        let path = path.into();
+
+        if let Some(data) = self.cached(&path) {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("IPFS: cat => cache hit");
+
+            self.last_endpoint.set(Some(Endpoint::Cache));
+
+            return Ok(data);
+        }
+
+        let url = self.base_url.join("cat").expect("Invalid URL");
+
+        let result = match self
+            .post(ApiOperation::Cat, url)
+            .query(&[("arg", &path)])
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+        {
+            Ok(res) => {
+                self.last_endpoint.set(Some(Endpoint::Api));
+
+                res.bytes().await
+            }
+            Err(e) => {
+                let gateway = match self.gateway_url.as_ref() {
+                    Some(gateway) => gateway,
+                    None => return Err(e),
+                };
+
+                ConsoleService::warn(&format!(
+                    "IPFS: cat => API failed for {}, trying gateway {} \n {:?}",
+                    path, gateway, e
+                ));
+
+                let url = gateway.join(&path).expect("Invalid URL");
+
+                self.last_endpoint.set(Some(Endpoint::Gateway));
+
+                self.client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await
+            }
+        };
+
+        let data = result?.to_vec();
+
+        self.cache(path.into_owned(), &data);
+
+        Ok(data)
+    }
+
     /// Download content simultaneously from 2 paths.
+    /// Recently downloaded segments are served from the cache.
+    /// If the API fails, the fallback gateway is tried before giving up.
     pub async fn double_path_cat<U>(
         &self,
         audio_path: U,
@@ -78,28 +292,114 @@ impl IpfsService {
     where
         U: Into<Cow<'static, str>>,
     {
+        let audio_path = audio_path.into();
+        let video_path = video_path.into();
+
+        if let Some(data) = self.cached_pair(&audio_path, &video_path) {
+            #[cfg(debug_assertions)]
+            ConsoleService::info("IPFS: cat => cache hit");
+
+            return Ok(data);
+        }
+
+        let (audio_data, video_data) = self
+            .uncached_double_path_cat(&audio_path, &video_path)
+            .await?;
+
+        self.cache(audio_path.into_owned(), &audio_data);
+        self.cache(video_path.into_owned(), &video_data);
+
+        Ok((audio_data, video_data))
+    }
+
+    /// Download content simultaneously from 2 paths, bypassing the segment cache.
+    /// Bulk downloads would otherwise evict the segments being played.
+    /// If the API fails, the fallback gateway is tried before giving up.
+    pub async fn uncached_double_path_cat(
+        &self,
+        audio_path: &str,
+        video_path: &str,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let data = match self.api_double_cat(audio_path, video_path).await {
+            Ok(data) => {
+                #[cfg(debug_assertions)]
+                ConsoleService::info("IPFS: cat => served by API");
+
+                self.last_endpoint.set(Some(Endpoint::Api));
+
+                data
+            }
+            Err(e) => {
+                let gateway = match self.gateway_url.as_ref() {
+                    Some(gateway) => gateway,
+                    None => return Err(e),
+                };
+
+                ConsoleService::warn(&format!(
+                    "IPFS: cat => API failed for {}, trying gateway {} \n {:?}",
+                    video_path, gateway, e
+                ));
+
+                let data = self
+                    .gateway_double_cat(gateway, audio_path, video_path)
+                    .await?;
+
+                self.last_endpoint.set(Some(Endpoint::Gateway));
+
+                data
+            }
+        };
+
+        Ok(data)
+    }
+
+    async fn api_double_cat(
+        &self,
+        audio_path: &str,
+        video_path: &str,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
         let url = self.base_url.join("cat").expect("Invalid URL");
 
         let (audio_res, video_res) = join!(
-            self.client
-                .post(url.clone())
-                .query(&[("arg", &audio_path.into())])
+            self.post(ApiOperation::Cat, url.clone())
+                .query(&[("arg", audio_path)])
                 .send(),
-            self.client
-                .post(url)
-                .query(&[("arg", &video_path.into())])
+            self.post(ApiOperation::Cat, url)
+                .query(&[("arg", video_path)])
                 .send()
         );
 
-        let audio_data = audio_res?;
-        let video_data = video_res?;
+        let audio_data = audio_res?.error_for_status()?;
+        let video_data = video_res?.error_for_status()?;
+
+        let (audio_result, video_result) = join!(audio_data.bytes(), video_data.bytes(),);
+
+        Ok((audio_result?.to_vec(), video_result?.to_vec()))
+    }
+
+    async fn gateway_double_cat(
+        &self,
+        gateway: &Url,
+        audio_path: &str,
+        video_path: &str,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let audio_url = gateway.join(audio_path).expect("Invalid URL");
+        let video_url = gateway.join(video_path).expect("Invalid URL");
+
+        let (audio_res, video_res) = join!(
+            self.client.get(audio_url).send(),
+            self.client.get(video_url).send()
+        );
+
+        let audio_data = audio_res?.error_for_status()?;
+        let video_data = video_res?.error_for_status()?;
 
         let (audio_result, video_result) = join!(audio_data.bytes(), video_data.bytes(),);
 
-        let audio_data = audio_result?;
-        let video_data = video_result?;
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("IPFS: cat => served by gateway {}", gateway));
 
-        Ok((audio_data.to_vec(), video_data.to_vec()))
+        Ok((audio_result?.to_vec(), video_result?.to_vec()))
     }
 
     /// Serialize then add dag node to IPFS. Return a CID.
@@ -108,10 +408,7 @@ impl IpfsService {
         T: ?Sized + Serialize,
     {
         #[cfg(debug_assertions)]
-        ConsoleService::info(&format!(
-            "Serde: Serialize => {}",
-            serde_json::to_string_pretty(node).unwrap()
-        ));
+        ConsoleService::info(&format!("Serde: Serialize => {}", debug_json(node)));
 
         let data = serde_json::to_string(node).expect("Serialization failed");
 
@@ -121,8 +418,7 @@ impl IpfsService {
         let url = self.base_url.join("dag/put").expect("Invalid URL");
 
         let response: DagPutResponse = self
-            .client
-            .post(url)
+            .post(ApiOperation::Dag, url)
             .multipart(form)
             .send()
             .await?
@@ -154,8 +450,7 @@ impl IpfsService {
 
         let url = self.base_url.join("dag/get").expect("Invalid URL");
 
-        self.client
-            .post(url)
+        self.post(ApiOperation::Dag, url)
             .query(&[("arg", &origin)])
             .send()
             .await?
@@ -163,6 +458,36 @@ impl IpfsService {
             .await
     }
 
+    /// Check whether a path leads to a node.
+    /// Ok(false) only if a link along the path is missing, other failures are errors.
+    pub async fn dag_path_exists<U>(&self, cid: Cid, path: U) -> Result<bool, Error>
+    where
+        U: Into<Cow<'static, str>>,
+    {
+        let origin = format!("{}{}", cid, path.into());
+
+        #[cfg(debug_assertions)]
+        ConsoleService::info(&format!("IPFS: dag get => {}", origin));
+
+        let url = self.base_url.join("dag/get").expect("Invalid URL");
+
+        let res = self
+            .post(ApiOperation::Dag, url)
+            .query(&[("arg", &origin)])
+            .send()
+            .await?;
+
+        let error = match res.error_for_status_ref() {
+            Ok(_) => return Ok(true),
+            Err(e) => e,
+        };
+
+        match res.json::<ApiErrorResponse>().await {
+            Ok(response) if is_missing_link(&response.message) => Ok(false),
+            _ => Err(error),
+        }
+    }
+
     pub async fn resolve_and_dag_get<U, T>(&self, ipns: U) -> Result<(Cid, T), reqwest::Error>
     where
         U: Into<Cow<'static, str>>,
@@ -171,8 +496,7 @@ impl IpfsService {
         let url = self.base_url.join("name/resolve").expect("Invalid URL");
 
         let res: NameResolveResponse = self
-            .client
-            .post(url)
+            .post(ApiOperation::Name, url)
             .query(&[("arg", &ipns.into())])
             .send()
             .await?
@@ -200,8 +524,7 @@ impl IpfsService {
         let url = self.base_url.join("pubsub/sub").expect("Invalid URL");
 
         let result = self
-            .client
-            .post(url)
+            .post(ApiOperation::PubSub, url)
             .query(&[("arg", &topic.into())])
             .send()
             .await;
@@ -209,7 +532,9 @@ impl IpfsService {
         let stream = match result {
             Ok(res) => res.bytes_stream(),
             Err(e) => {
-                cb.emit(Err(e.into()));
+                if !drop_sig.load(Ordering::Relaxed) {
+                    cb.emit(Err(e.into()));
+                }
                 return;
             }
         };
@@ -240,8 +565,7 @@ impl IpfsService {
     {
         let url = self.base_url.join("pubsub/pub").expect("Invalid URL");
 
-        self.client
-            .post(url)
+        self.post(ApiOperation::PubSub, url)
             .query(&[("arg", &topic.into()), ("arg", &msg.into())])
             .send()
             .await?;
@@ -253,8 +577,7 @@ impl IpfsService {
         let url = self.base_url.join("id").expect("Invalid URL");
 
         let response = self
-            .client
-            .post(url)
+            .post(ApiOperation::Id, url)
             .send()
             .await?
             .json::<IdResponse>()
@@ -316,9 +639,167 @@ pub struct NameResolveResponse {
     pub path: String,
 }
 
+/// Body of a failed API request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiErrorResponse {
+    message: String,
+}
+
+/// The daemon resolved the path up to a link that does not exist.
+/// Blocks that cannot be found are not missing links, they may be retrieved later.
+fn is_missing_link(message: &str) -> bool {
+    message.contains("no link named") || message.contains("no such link")
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct IdResponse {
     #[serde(rename = "ID")]
     pub id: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nothing listens there, only cached segments can be served.
+    fn service(cache_mb: usize) -> IpfsService {
+        let url = Url::parse("http://127.0.0.1:9/api/v0/").unwrap();
+
+        IpfsService::with_config(url, None, ApiHeaders::new(), cache_mb)
+    }
+
+    #[test]
+    fn second_fetch_served_from_cache() {
+        let ipfs = service(1);
+
+        // As stored by the first double_path_cat once downloaded.
+        ipfs.cache("bafy/track/audio".into(), &[1, 2]);
+        ipfs.cache("bafy/track/720p30".into(), &[3, 4, 5]);
+
+        let result = ipfs.cached_pair("bafy/track/audio", "bafy/track/720p30");
+
+        assert_eq!(result, Some((vec![1, 2], vec![3, 4, 5])));
+        assert_eq!(ipfs.last_endpoint(), Some(Endpoint::Cache));
+    }
+
+    #[test]
+    fn half_cached_pair_is_fetched() {
+        let ipfs = service(1);
+
+        ipfs.cache("bafy/track/audio".into(), &[1, 2]);
+
+        assert!(ipfs
+            .cached_pair("bafy/track/audio", "bafy/track/720p30")
+            .is_none());
+        assert_eq!(ipfs.last_endpoint(), None);
+    }
+
+    #[test]
+    fn oversized_cache_falls_back_to_default() {
+        let ipfs = service(usize::MAX);
+
+        ipfs.cache("bafy/track/audio".into(), &[1, 2]);
+
+        assert_eq!(ipfs.cached("bafy/track/audio"), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn disabled_cache_keeps_nothing() {
+        let ipfs = service(0);
+
+        ipfs.cache("bafy/track/audio".into(), &[1, 2]);
+
+        assert!(ipfs.cached("bafy/track/audio").is_none());
+    }
+
+    #[test]
+    fn missing_link_detected() {
+        let json = r#"{"Message":"no link named \"60\" under bafyreib","Code":0,"Type":"error"}"#;
+        let response: ApiErrorResponse = serde_json::from_str(json).unwrap();
+
+        assert!(is_missing_link(&response.message));
+        assert!(is_missing_link("no such link found"));
+    }
+
+    #[test]
+    fn gateway_path_kept() {
+        let gateway = gateway_url("https://gateway.example/ipfs").unwrap();
+
+        assert_eq!(gateway.as_str(), "https://gateway.example/ipfs/");
+        assert_eq!(
+            gateway.join("bafy/track/audio").unwrap().as_str(),
+            "https://gateway.example/ipfs/bafy/track/audio"
+        );
+    }
+
+    #[test]
+    fn gateway_trailing_slash_unchanged() {
+        let gateway = gateway_url(" https://gateway.example/ipfs/ ").unwrap();
+
+        assert_eq!(gateway.as_str(), "https://gateway.example/ipfs/");
+        assert_eq!(
+            gateway_url("http://127.0.0.1:8080").unwrap().as_str(),
+            "http://127.0.0.1:8080/"
+        );
+    }
+
+    #[test]
+    fn invalid_gateway_rejected() {
+        assert!(gateway_url("").is_none());
+        assert!(gateway_url("gateway.example/ipfs").is_none());
+    }
+
+    #[test]
+    fn unavailable_block_not_missing_link() {
+        assert!(!is_missing_link("merkledag: not found"));
+        assert!(!is_missing_link("context deadline exceeded"));
+    }
+
+    fn headers(ipfs: &IpfsService, operation: ApiOperation) -> Vec<(String, String)> {
+        let url = ipfs.base_url.join("test").unwrap();
+        let request = ipfs.post(operation, url).build().unwrap();
+
+        let mut headers: Vec<(String, String)> = request
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_owned()))
+            .collect();
+
+        headers.sort();
+        headers
+    }
+
+    #[test]
+    fn category_headers_attached() {
+        let config: ApiHeaders = serde_json::from_str(
+            r#"{
+                "dag": { "authorization": "Bearer dag" },
+                "name": { "x-name-key": "name", "x-tenant": "a" },
+                "pubsub": { "x-tenant": "b" }
+            }"#,
+        )
+        .unwrap();
+
+        let url = Url::parse("http://127.0.0.1:9/api/v0/").unwrap();
+        let ipfs = IpfsService::with_config(url, None, config, 0);
+
+        let pair = |name: &str, value: &str| (name.to_owned(), value.to_owned());
+
+        assert_eq!(
+            headers(&ipfs, ApiOperation::Dag),
+            vec![pair("authorization", "Bearer dag")]
+        );
+        assert_eq!(
+            headers(&ipfs, ApiOperation::Name),
+            vec![pair("x-name-key", "name"), pair("x-tenant", "a")]
+        );
+        assert_eq!(
+            headers(&ipfs, ApiOperation::PubSub),
+            vec![pair("x-tenant", "b")]
+        );
+        assert!(headers(&ipfs, ApiOperation::Cat).is_empty());
+        assert!(headers(&ipfs, ApiOperation::Id).is_empty());
+    }
+}
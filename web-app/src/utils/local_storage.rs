@@ -1,5 +1,9 @@
 use std::convert::TryFrom;
 
+use crate::app::ENS_NAME;
+use crate::utils::bitrate::BitrateUnit;
+use crate::utils::ipfs::gateway_url;
+
 use web_sys::{Storage, Window};
 
 use yew::services::ConsoleService;
@@ -19,6 +23,17 @@ pub fn get_local_storage(window: &Window) -> Option<Storage> {
     }
 }
 
+/// Storage cleared when the tab is closed.
+pub fn get_session_storage(window: &Window) -> Option<Storage> {
+    match window.session_storage() {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            None
+        }
+    }
+}
+
 pub fn get_cid(key: &str, storage: Option<&Storage>) -> Option<Cid> {
     let storage = storage?;
 
@@ -54,20 +69,76 @@ pub fn set_cid(key: &str, cid: &Cid, storage: Option<&Storage>) {
     }
 }
 
+pub fn get_f64(key: &str, storage: Option<&Storage>) -> Option<f64> {
+    let storage = storage?;
+
+    let value = match storage.get_item(key) {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            return None;
+        }
+    };
+
+    let value = value?.parse::<f64>().ok()?;
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!("Storage Get => {} \n {}", key, value));
+
+    Some(value)
+}
+
+pub fn set_f64(key: &str, value: f64, storage: Option<&Storage>) {
+    let storage = match storage {
+        Some(st) => st,
+        None => return,
+    };
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!("Storage Set => {} \n {}", key, value));
+
+    if let Err(e) = storage.set_item(key, &value.to_string()) {
+        ConsoleService::error(&format!("{:#?}", e));
+    }
+}
+
+pub fn remove_item(key: &str, storage: Option<&Storage>) {
+    let storage = match storage {
+        Some(st) => st,
+        None => return,
+    };
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!("Storage Remove => {}", key));
+
+    if let Err(e) = storage.remove_item(key) {
+        ConsoleService::error(&format!("{:#?}", e));
+    }
+}
+
+/// Storage key of the beacon CID cached for a channel.
+/// Prefixed, a channel name could otherwise overwrite a setting.
+pub fn beacon_key(ens_name: &str) -> String {
+    format!("beacon_{}", ens_name)
+}
+
+/// Returns the beacon CID cached for this channel.
+pub fn get_local_beacon(ens_name: &str, storage: Option<&Storage>) -> Option<Cid> {
+    get_cid(&beacon_key(ens_name), storage)
+}
+
 pub fn set_local_beacon(ens_name: &str, cid: &Cid, storage: Option<&Storage>) {
     let storage = match storage {
         Some(st) => st,
         None => return,
     };
 
+    let key = beacon_key(ens_name);
+
     #[cfg(debug_assertions)]
-    ConsoleService::info(&format!(
-        "Storage Set => {} \n {}",
-        ens_name,
-        &cid.to_string()
-    ));
+    ConsoleService::info(&format!("Storage Set => {} \n {}", key, &cid.to_string()));
 
-    if let Err(e) = storage.set_item(ens_name, &cid.to_string()) {
+    if let Err(e) = storage.set_item(&key, &cid.to_string()) {
         ConsoleService::error(&format!("{:#?}", e));
     }
 
@@ -123,3 +194,263 @@ pub fn get_local_ipfs_addrs(storage: Option<&Storage>) -> Option<String> {
 
     Some(addrs)
 }
+
+const IPFS_API_HEADERS_KEY: &str = "ipfs_api_headers";
+
+/// Value is not logged, headers can hold credentials.
+pub fn get_local_ipfs_headers(storage: Option<&Storage>) -> Option<String> {
+    let storage = storage?;
+
+    match storage.get_item(IPFS_API_HEADERS_KEY) {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            None
+        }
+    }
+}
+
+const GATEWAY_KEY: &str = "fallback_gateway";
+
+/// An empty or invalid address removes the fallback gateway.
+pub fn set_local_gateway(addrs: &str, storage: Option<&Storage>) {
+    let storage = match storage {
+        Some(st) => st,
+        None => return,
+    };
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!("Storage Set => {} \n {}", GATEWAY_KEY, addrs));
+
+    let result = match gateway_url(addrs) {
+        Some(url) => storage.set_item(GATEWAY_KEY, url.as_str()),
+        None => storage.remove_item(GATEWAY_KEY),
+    };
+
+    if let Err(e) = result {
+        ConsoleService::error(&format!("{:#?}", e));
+    }
+}
+
+pub fn get_local_gateway(storage: Option<&Storage>) -> Option<String> {
+    let storage = storage?;
+
+    match storage.get_item(GATEWAY_KEY) {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            None
+        }
+    }
+}
+
+const SEGMENT_CACHE_KEY: &str = "segment_cache_mb";
+
+/// Megabytes of segments kept in memory, 0 disables the cache.
+pub fn set_local_segment_cache(megabytes: usize, storage: Option<&Storage>) {
+    let storage = match storage {
+        Some(st) => st,
+        None => return,
+    };
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!(
+        "Storage Set => {} \n {}",
+        SEGMENT_CACHE_KEY, megabytes
+    ));
+
+    if let Err(e) = storage.set_item(SEGMENT_CACHE_KEY, &megabytes.to_string()) {
+        ConsoleService::error(&format!("{:#?}", e));
+    }
+}
+
+pub fn get_local_segment_cache(storage: Option<&Storage>) -> Option<usize> {
+    let storage = storage?;
+
+    let megabytes = match storage.get_item(SEGMENT_CACHE_KEY) {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            return None;
+        }
+    };
+
+    megabytes?.parse::<usize>().ok()
+}
+
+const OFFLINE_MODE_KEY: &str = "prefer_offline";
+
+pub fn set_local_offline_mode(offline: bool, storage: Option<&Storage>) {
+    let storage = match storage {
+        Some(st) => st,
+        None => return,
+    };
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!(
+        "Storage Set => {} \n {}",
+        OFFLINE_MODE_KEY, offline
+    ));
+
+    if let Err(e) = storage.set_item(OFFLINE_MODE_KEY, &offline.to_string()) {
+        ConsoleService::error(&format!("{:#?}", e));
+    }
+}
+
+pub fn get_local_offline_mode(storage: Option<&Storage>) -> bool {
+    let storage = match storage {
+        Some(st) => st,
+        None => return false,
+    };
+
+    let offline = match storage.get_item(OFFLINE_MODE_KEY) {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            return false;
+        }
+    };
+
+    offline
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false)
+}
+
+// Underscores are not allowed in channel names, no beacon key can be equal.
+const CHANNELS_KEY: &str = "settings_channels";
+const CHANNEL_KEY: &str = "settings_channel";
+
+pub fn set_local_channels(channels: &[String], storage: Option<&Storage>) {
+    let storage = match storage {
+        Some(st) => st,
+        None => return,
+    };
+
+    let json_string = serde_json::to_string(channels).expect("Can't serialize");
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!(
+        "Storage Set => {} \n {}",
+        CHANNELS_KEY, &json_string
+    ));
+
+    if let Err(e) = storage.set_item(CHANNELS_KEY, &json_string) {
+        ConsoleService::error(&format!("{:#?}", e));
+    }
+}
+
+pub fn get_local_channels(storage: Option<&Storage>) -> Vec<String> {
+    let storage = match storage {
+        Some(st) => st,
+        None => return Vec::new(),
+    };
+
+    let channels = match storage.get_item(CHANNELS_KEY) {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            return Vec::new();
+        }
+    };
+
+    channels
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_local_channel(name: &str, storage: Option<&Storage>) {
+    let storage = match storage {
+        Some(st) => st,
+        None => return,
+    };
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!("Storage Set => {} \n {}", CHANNEL_KEY, name));
+
+    if let Err(e) = storage.set_item(CHANNEL_KEY, name) {
+        ConsoleService::error(&format!("{:#?}", e));
+    }
+}
+
+/// Returns the selected channel ENS name.
+pub fn get_local_channel(storage: Option<&Storage>) -> String {
+    let channel = storage.and_then(|storage| match storage.get_item(CHANNEL_KEY) {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            None
+        }
+    });
+
+    channel.unwrap_or_else(|| ENS_NAME.to_owned())
+}
+
+const BITRATE_UNIT_KEY: &str = "bitrate_unit";
+
+pub fn set_local_bitrate_unit(unit: BitrateUnit, storage: Option<&Storage>) {
+    let storage = match storage {
+        Some(st) => st,
+        None => return,
+    };
+
+    #[cfg(debug_assertions)]
+    ConsoleService::info(&format!(
+        "Storage Set => {} \n {}",
+        BITRATE_UNIT_KEY,
+        unit.as_str()
+    ));
+
+    if let Err(e) = storage.set_item(BITRATE_UNIT_KEY, unit.as_str()) {
+        ConsoleService::error(&format!("{:#?}", e));
+    }
+}
+
+pub fn get_local_bitrate_unit(storage: Option<&Storage>) -> BitrateUnit {
+    let storage = match storage {
+        Some(st) => st,
+        None => return BitrateUnit::default(),
+    };
+
+    let unit = match storage.get_item(BITRATE_UNIT_KEY) {
+        Ok(option) => option,
+        Err(e) => {
+            ConsoleService::error(&format!("{:#?}", e));
+            return BitrateUnit::default();
+        }
+    };
+
+    unit.and_then(|s| s.parse().ok()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::web3::normalize_channel_name;
+
+    #[test]
+    fn switching_channel_switches_beacon_key() {
+        assert_eq!(beacon_key("sionois"), "beacon_sionois");
+        assert_ne!(beacon_key("sionois"), beacon_key("music"));
+    }
+
+    #[test]
+    fn same_channel_same_beacon_key() {
+        let name = normalize_channel_name("Defluencer.SionoiS.eth").unwrap();
+
+        assert_eq!(beacon_key(&name), beacon_key(ENS_NAME));
+    }
+
+    #[test]
+    fn channel_names_never_overwrite_settings() {
+        for name in ["channel", "channels", "settings", "beacon"].iter() {
+            let name = normalize_channel_name(name).unwrap();
+
+            assert_ne!(beacon_key(&name), CHANNEL_KEY);
+            assert_ne!(beacon_key(&name), CHANNELS_KEY);
+        }
+
+        assert!(normalize_channel_name(CHANNEL_KEY).is_err());
+        assert!(normalize_channel_name(CHANNELS_KEY).is_err());
+    }
+}
@@ -1,4 +1,8 @@
+pub mod bitrate;
+pub mod cancel;
 pub mod ema;
 pub mod ipfs;
 pub mod local_storage;
+pub mod segment_cache;
+pub mod timer;
 pub mod web3;
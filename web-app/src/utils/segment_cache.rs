@@ -0,0 +1,123 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Least recently used cache of media segments, bounded by total size in bytes.
+pub struct SegmentCache {
+    capacity: usize,
+    size: usize,
+
+    segments: HashMap<String, Vec<u8>>,
+
+    /// IPFS paths from least to most recently used.
+    order: VecDeque<String>,
+}
+
+impl SegmentCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            size: 0,
+
+            segments: HashMap::with_capacity(32),
+            order: VecDeque::with_capacity(32),
+        }
+    }
+
+    /// Returns a copy of the segment at this path and mark it as recently used.
+    pub fn get(&mut self, path: &str) -> Option<Vec<u8>> {
+        let data = self.segments.get(path)?.clone();
+
+        if let Some(index) = self.order.iter().position(|p| p == path) {
+            if let Some(path) = self.order.remove(index) {
+                self.order.push_back(path);
+            }
+        }
+
+        Some(data)
+    }
+
+    /// Add segment to the cache, evicting the least recently used ones if over capacity.
+    pub fn insert(&mut self, path: String, data: Vec<u8>) {
+        if data.len() > self.capacity {
+            return;
+        }
+
+        if let Some(old) = self.segments.remove(&path) {
+            self.size -= old.len();
+            self.order.retain(|p| *p != path);
+        }
+
+        while self.size + data.len() > self.capacity {
+            let oldest = match self.order.pop_front() {
+                Some(p) => p,
+                None => break,
+            };
+
+            if let Some(old) = self.segments.remove(&oldest) {
+                self.size -= old.len();
+            }
+        }
+
+        self.size += data.len();
+        self.order.push_back(path.clone());
+        self.segments.insert(path, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(len: usize) -> Vec<u8> {
+        vec![0; len]
+    }
+
+    #[test]
+    fn least_recently_used_evicted_first() {
+        let mut cache = SegmentCache::new(30);
+
+        cache.insert("a".into(), segment(10));
+        cache.insert("b".into(), segment(10));
+        cache.insert("c".into(), segment(10));
+
+        // "a" becomes the most recently used, "b" the oldest.
+        assert!(cache.get("a").is_some());
+
+        cache.insert("d".into(), segment(10));
+
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+        assert!(cache.get("d").is_some());
+        assert_eq!(cache.size, 30);
+    }
+
+    #[test]
+    fn replacing_a_key_updates_size() {
+        let mut cache = SegmentCache::new(30);
+
+        cache.insert("a".into(), segment(10));
+        cache.insert("a".into(), segment(25));
+
+        assert_eq!(cache.size, 25);
+        assert_eq!(cache.order.len(), 1);
+        assert_eq!(cache.get("a").map(|data| data.len()), Some(25));
+
+        cache.insert("b".into(), segment(10));
+
+        // Only "a" could be evicted to make room.
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.size, 10);
+    }
+
+    #[test]
+    fn larger_than_capacity_not_cached() {
+        let mut cache = SegmentCache::new(30);
+
+        cache.insert("a".into(), segment(10));
+        cache.insert("big".into(), segment(31));
+
+        assert!(cache.get("big").is_none());
+        assert!(cache.get("a").is_some());
+        assert_eq!(cache.size, 10);
+    }
+}
@@ -0,0 +1,58 @@
+use std::future::Future;
+
+use futures::future::{select, Either};
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use js_sys::{Function, Promise};
+
+/// Resolve after this many milliseconds.
+pub async fn sleep(millis: i32) {
+    let promise = Promise::new(&mut |resolve: Function, _reject: Function| {
+        let window = web_sys::window().expect("Can't get window");
+
+        if let Err(e) = window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(resolve.unchecked_ref(), millis)
+        {
+            let _ = resolve.call1(&wasm_bindgen::JsValue::NULL, &e);
+        }
+    });
+
+    let _ = JsFuture::from(promise).await;
+}
+
+/// Output of the future, None if it takes longer than this many milliseconds.
+pub async fn timeout<F: Future>(millis: i32, future: F) -> Option<F::Output> {
+    race(future, sleep(millis)).await
+}
+
+/// Output of the future, None if the deadline completes first.
+async fn race<F, D>(future: F, deadline: D) -> Option<F::Output>
+where
+    F: Future,
+    D: Future,
+{
+    match select(Box::pin(future), Box::pin(deadline)).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::future::{pending, ready};
+
+    #[test]
+    fn output_before_deadline() {
+        assert_eq!(block_on(race(ready(1), pending::<()>())), Some(1));
+    }
+
+    #[test]
+    fn deadline_before_output() {
+        assert_eq!(block_on(race(pending::<u8>(), ready(()))), None);
+    }
+}
@@ -1,17 +1,68 @@
 use std::convert::TryFrom;
 
-use wasm_bindgen::JsValue;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
 
+use js_sys::{Function, Reflect};
+
+use web3::contract::{Contract, Options};
 use web3::transports::eip_1193::{Eip1193, Provider};
-use web3::types::Address;
+use web3::types::{Address, U256};
 use web3::{Error, Web3};
 
 use yew::services::ConsoleService;
+use yew::Callback;
 
 use serde::Serialize;
 
+use linked_data::video::AccessControl;
+
 use cid::Cid;
 
+/// Parent of every channel name, "defluencer.<name>.eth".
+const ENS_PARENT: &str = "defluencer.";
+const ENS_TLD: &str = ".eth";
+
+/// Trim and lowercase a channel name then check it is a valid ENS label.
+/// The full "defluencer.<name>.eth" form is also accepted.
+pub fn normalize_channel_name(name: &str) -> Result<String, String> {
+    let name = name.trim().to_lowercase();
+
+    let name = name.strip_suffix(ENS_TLD).unwrap_or(&name);
+    let name = name.strip_prefix(ENS_PARENT).unwrap_or(name);
+
+    if name.is_empty() {
+        return Err("Channel name is empty.".to_owned());
+    }
+
+    for label in name.split('.') {
+        if label.is_empty() {
+            return Err(format!("Channel name {} has an empty label.", name));
+        }
+
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!(
+                "Channel name {} has a label starting or ending with a hyphen.",
+                name
+            ));
+        }
+
+        if let Some(c) = label
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-'))
+        {
+            return Err(format!(
+                "Channel name {} contains invalid character {:?}.",
+                name, c
+            ));
+        }
+    }
+
+    Ok(name.to_owned())
+}
+
+const BALANCE_OF_ABI: &[u8] = br#"[{"constant":true,"inputs":[{"name":"owner","type":"address"}],"name":"balanceOf","outputs":[{"name":"","type":"uint256"}],"type":"function"}]"#;
+
 #[derive(Clone)]
 pub struct Web3Service {
     client: Web3<Eip1193>,
@@ -29,7 +80,8 @@ impl Web3Service {
     }
 
     pub async fn get_ipfs_content(&self, name: String) -> Result<Cid, web3::contract::Error> {
-        let name = &format!("defluencer.{}.eth", name);
+        let name = normalize_channel_name(&name).map_err(Error::InvalidResponse)?;
+        let name = &format!("{}{}{}", ENS_PARENT, name, ENS_TLD);
 
         #[cfg(debug_assertions)]
         ConsoleService::info(&format!("ENS get => {}", name));
@@ -58,6 +110,14 @@ impl Web3Service {
         Ok(cid)
     }
 
+    /// Address the channel name resolves to.
+    pub async fn get_eth_address(&self, name: String) -> Result<Address, web3::contract::Error> {
+        let name = normalize_channel_name(&name).map_err(Error::InvalidResponse)?;
+        let name = &format!("{}{}{}", ENS_PARENT, name, ENS_TLD);
+
+        self.client.ens().eth_address(name).await
+    }
+
     //https://docs.rs/web3/0.15.0/web3/api/struct.Eth.html#method.request_accounts
     pub async fn get_eth_accounts(&self) -> Result<Address, Error> {
         let address = self.client.eth().request_accounts().await?;
@@ -77,8 +137,233 @@ impl Web3Service {
         Ok(sign.to_fixed_bytes())
     }
 
+    /// Check if the connected wallet meets the access requirement.
+    pub async fn is_eligible(&self, access: AccessControl) -> Result<bool, web3::contract::Error> {
+        let account = self.get_eth_accounts().await?;
+
+        match access {
+            AccessControl::Allowlist(list) => {
+                Ok(list.iter().any(|addrs| Address::from(*addrs) == account))
+            }
+            AccessControl::Token(token) => {
+                // https://eips.ethereum.org/EIPS/eip-20 & https://eips.ethereum.org/EIPS/eip-721
+                let contract =
+                    Contract::from_json(self.client.eth(), Address::from(token), BALANCE_OF_ABI)?;
+
+                let balance: U256 = contract
+                    .query("balanceOf", (account,), None, Options::default(), None)
+                    .await?;
+
+                Ok(!balance.is_zero())
+            }
+        }
+    }
+
+    //https://docs.rs/web3/0.15.0/web3/api/struct.Eth.html#method.chain_id
+    pub async fn get_chain_id(&self) -> Result<U256, Error> {
+        self.client.eth().chain_id().await
+    }
+
     //https://eips.ethereum.org/EIPS/eip-181
     pub async fn get_name(&self, addrs: Address) -> Result<String, web3::contract::Error> {
         self.client.ens().get_canonical_name(addrs).await
     }
 }
+
+/// Provider event listener, removed from the provider when dropped.
+pub struct ProviderListener {
+    ethereum: JsValue,
+    event: String,
+    closure: Option<Closure<dyn Fn()>>,
+}
+
+impl Drop for ProviderListener {
+    fn drop(&mut self) {
+        let closure = match self.closure.take() {
+            Some(closure) => closure,
+            None => return,
+        };
+
+        let remove: Option<Function> =
+            Reflect::get(&self.ethereum, &JsValue::from_str("removeListener"))
+                .ok()
+                .and_then(|remove| remove.dyn_into().ok());
+
+        let removed = remove.map_or(false, |remove| {
+            remove
+                .call2(
+                    &self.ethereum,
+                    &JsValue::from_str(&self.event),
+                    closure.as_ref(),
+                )
+                .is_ok()
+        });
+
+        // Still registered, a later event must not call a freed closure.
+        if !removed {
+            ConsoleService::warn(&format!("Provider listener {} not removed", self.event));
+            closure.forget();
+        }
+    }
+}
+
+/// Listen to provider events (chainChanged, accountsChanged, etc...).
+/// Dropping the listener stops the callback.
+///
+/// https://eips.ethereum.org/EIPS/eip-1193#events
+pub fn add_provider_listener(event: &str, callback: Callback<()>) -> Option<ProviderListener> {
+    let window = web_sys::window()?;
+
+    let ethereum = Reflect::get(window.as_ref(), &JsValue::from_str("ethereum")).ok()?;
+
+    let on: Function = Reflect::get(&ethereum, &JsValue::from_str("on"))
+        .ok()?
+        .dyn_into()
+        .ok()?;
+
+    let closure = Closure::wrap(Box::new(move || callback.emit(())) as Box<dyn Fn()>);
+
+    if let Err(e) = on.call2(&ethereum, &JsValue::from_str(event), closure.as_ref()) {
+        ConsoleService::error(&format!("{:?}", e));
+        return None;
+    }
+
+    Some(ProviderListener {
+        ethereum,
+        event: event.to_owned(),
+        closure: Some(closure),
+    })
+}
+
+/// Human readable Ethereum network name.
+pub fn network_name(chain_id: u64) -> String {
+    match chain_id {
+        1 => "Mainnet".to_owned(),
+        3 => "Ropsten".to_owned(),
+        4 => "Rinkeby".to_owned(),
+        5 => "Goerli".to_owned(),
+        42 => "Kovan".to_owned(),
+        id => format!("Chain {}", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trimmed_and_lowercased() {
+        assert_eq!(
+            normalize_channel_name("  SionoiS \n"),
+            Ok("sionois".to_owned())
+        );
+    }
+
+    #[test]
+    fn full_ens_form_accepted() {
+        assert_eq!(
+            normalize_channel_name("Defluencer.SionoiS.eth"),
+            Ok("sionois".to_owned())
+        );
+        assert_eq!(
+            normalize_channel_name("sionois.eth"),
+            Ok("sionois".to_owned())
+        );
+    }
+
+    #[test]
+    fn hyphens_inside_labels() {
+        assert_eq!(
+            normalize_channel_name("my-channel"),
+            Ok("my-channel".to_owned())
+        );
+        assert!(normalize_channel_name("-channel").is_err());
+        assert!(normalize_channel_name("channel-").is_err());
+    }
+
+    #[test]
+    fn empty_labels_rejected() {
+        assert!(normalize_channel_name("").is_err());
+        assert!(normalize_channel_name("   ").is_err());
+        assert!(normalize_channel_name("defluencer..eth").is_err());
+        assert!(normalize_channel_name("a..b").is_err());
+        assert!(normalize_channel_name(".channel").is_err());
+    }
+
+    #[test]
+    fn invalid_characters_rejected() {
+        assert!(normalize_channel_name("my channel").is_err());
+        assert!(normalize_channel_name("chan_nel").is_err());
+        assert!(normalize_channel_name("chännel").is_err());
+        assert!(normalize_channel_name("channel/../x").is_err());
+    }
+
+    #[test]
+    fn sub_labels_kept() {
+        assert_eq!(
+            normalize_channel_name("music.sionois"),
+            Ok("music.sionois".to_owned())
+        );
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod provider_tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Minimal EIP-1193 event emitter installed as window.ethereum.
+    fn mock_provider() -> JsValue {
+        Function::new_no_args(
+            r#"
+            const handlers = {};
+            window.ethereum = {
+                on(event, handler) { (handlers[event] = handlers[event] || []).push(handler); },
+                removeListener(event, handler) { handlers[event] = (handlers[event] || []).filter(h => h !== handler); },
+                emit(event) { (handlers[event] || []).forEach(h => h()); },
+                count(event) { return (handlers[event] || []).length; },
+            };
+            return window.ethereum;
+            "#,
+        )
+        .call0(&JsValue::NULL)
+        .unwrap()
+    }
+
+    fn call(provider: &JsValue, method: &str, event: &str) -> JsValue {
+        let method: Function = Reflect::get(provider, &JsValue::from_str(method))
+            .unwrap()
+            .dyn_into()
+            .unwrap();
+
+        method.call1(provider, &JsValue::from_str(event)).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn chain_change_calls_back_until_dropped() {
+        let provider = mock_provider();
+
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+        let callback = Callback::from(move |_| counter.set(counter.get() + 1));
+
+        let listener = add_provider_listener("chainChanged", callback).unwrap();
+
+        call(&provider, "emit", "chainChanged");
+        call(&provider, "emit", "accountsChanged");
+        assert_eq!(count.get(), 1);
+
+        drop(listener);
+
+        assert_eq!(call(&provider, "count", "chainChanged").as_f64(), Some(0.0));
+
+        call(&provider, "emit", "chainChanged");
+        assert_eq!(count.get(), 1);
+    }
+}